@@ -0,0 +1,284 @@
+//! Finds, decodes and encodes album art for display in the terminal
+//!
+//! Art embedded in the track is preferred over a cover image beside it. Terminals speaking the
+//! kitty graphics protocol or sixel are sent the image itself; elsewhere each cell shows two
+//! pixels, using an upper half block with differing foreground and background colours
+
+use std::{env, fmt::Write, fs, path::Path, str::FromStr};
+
+use image::{DynamicImage, RgbaImage, imageops::FilterType};
+
+use crate::loader::{self, AudioTrack};
+
+/// File stems recognised as cover images, most preferred first
+const COVER_NAMES: [&str; 4] = ["cover", "folder", "front", "album"];
+const COVER_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+/// Bytes of base64 sent per kitty escape, the most the protocol allows
+const KITTY_CHUNK: usize = 4096;
+/// Removes the image placed by `kitty`, freeing its data
+pub const KITTY_DELETE: &str = "\x1b_Ga=d,d=I,i=1,q=2\x1b\\";
+
+/// How art is drawn
+#[derive(Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+    /// Coloured half blocks, legible in any terminal with true colour
+    Blocks,
+    None,
+}
+
+/// Colour of the upper pixel of a cell and of the lower pixel, where present
+pub type HalfBlock = ([u8; 3], Option<[u8; 3]>);
+
+/// Cells across, cells down, and the pixel size of a cell
+type Size = (u16, u16, (u16, u16));
+
+/// Art sized to a number of cells
+pub enum Picture {
+    /// Escape sequence drawing the image with its top left corner at the cursor
+    Escape(String),
+    /// Rows of half block cells
+    Cells(Vec<Vec<HalfBlock>>),
+}
+
+/// Art of the playing track, decoded once per track and encoded once per size
+pub struct Art {
+    protocol: Protocol,
+    /// Path of the track the art belongs to
+    track: Option<String>,
+    image: Option<DynamicImage>,
+    /// Size the picture was encoded for
+    rendered: Option<(Size, Picture)>,
+}
+
+impl Protocol {
+    /// Guesses the best supported protocol from the environment, as querying the terminal
+    /// would mean waiting on its reply
+    pub fn detect() -> Self {
+        let term = env::var("TERM").unwrap_or_default();
+        let program = env::var("TERM_PROGRAM").unwrap_or_default();
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || matches!(program.as_str(), "WezTerm" | "ghostty")
+        {
+            Protocol::Kitty
+        } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+            Protocol::Sixel
+        } else {
+            Protocol::Blocks
+        }
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "kitty" => Ok(Protocol::Kitty),
+            "sixel" => Ok(Protocol::Sixel),
+            "blocks" => Ok(Protocol::Blocks),
+            "none" => Ok(Protocol::None),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Art {
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            track: None,
+            image: None,
+            rendered: None,
+        }
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// Loads the art of `track` where it is not the track last loaded, returning whether the
+    /// art may have changed
+    pub fn update(&mut self, track: Option<&AudioTrack>) -> bool {
+        let path = track.map(|t| t.file());
+        if self.track.as_deref() == path {
+            return false;
+        }
+        self.track = path.map(str::to_owned);
+        self.image = match self.protocol {
+            Protocol::None => None,
+            _ => path.and_then(|p| load(Path::new(p))),
+        };
+        self.rendered = None;
+        true
+    }
+
+    /// The art fitted within `cols` by `rows` cells, each `cell` pixels wide and high
+    pub fn picture(&mut self, cols: u16, rows: u16, cell: (u16, u16)) -> Option<&Picture> {
+        let image = self.image.as_ref()?;
+        if cols == 0 || rows == 0 {
+            return None;
+        }
+        let key = (cols, rows, cell);
+        if self.rendered.as_ref().is_none_or(|(k, _)| *k != key) {
+            let (width, height) = (cols as u32 * cell.0 as u32, rows as u32 * cell.1 as u32);
+            let picture = match self.protocol {
+                Protocol::Kitty => Picture::Escape(kitty(&fit(image, width, height))),
+                Protocol::Sixel => Picture::Escape(sixel(&fit(image, width, height))),
+                Protocol::Blocks => {
+                    Picture::Cells(blocks(&fit(image, cols as u32, rows as u32 * 2)))
+                }
+                Protocol::None => return None,
+            };
+            self.rendered = Some((key, picture));
+        }
+        self.rendered.as_ref().map(|(_, p)| p)
+    }
+}
+
+/// Decodes the art embedded in the file at `path`, or else a cover image in its directory
+fn load(path: &Path) -> Option<DynamicImage> {
+    loader::read_visual(path)
+        .and_then(|data| image::load_from_memory(&data).ok())
+        .or_else(|| cover_file(path.parent()?).and_then(|p| image::open(p).ok()))
+}
+
+/// Finds an image such as `cover.jpg` or `Folder.png`, ignoring case
+fn cover_file(dir: &Path) -> Option<std::path::PathBuf> {
+    let mut found: Vec<(usize, std::path::PathBuf)> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter_map(|p| {
+            let stem = p.file_stem()?.to_str()?.to_lowercase();
+            let ext = p.extension()?.to_str()?.to_lowercase();
+            let rank = COVER_NAMES.iter().position(|n| *n == stem)?;
+            COVER_EXTENSIONS
+                .contains(&ext.as_str())
+                .then_some((rank, p))
+        })
+        .collect();
+    found.sort();
+    found.into_iter().next().map(|(_, p)| p)
+}
+
+/// Scales to fit within `width` by `height` pixels, keeping the aspect ratio
+fn fit(image: &DynamicImage, width: u32, height: u32) -> RgbaImage {
+    image
+        .resize(width.max(1), height.max(1), FilterType::Triangle)
+        .to_rgba8()
+}
+
+/// Transmits and places the image under a fixed id, so the next image replaces it
+fn kitty(image: &RgbaImage) -> String {
+    let data = base64(image.as_raw());
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::with_capacity(data.len() + chunks.len() * 16 + 64);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = (i + 1 < chunks.len()) as u8;
+        match i {
+            // responses are suppressed, as they would arrive as key presses
+            0 => write!(
+                out,
+                "\x1b_Ga=T,f=32,s={},v={},i=1,q=2,C=1,m={more};",
+                image.width(),
+                image.height()
+            ),
+            _ => write!(out, "\x1b_Gm={more};"),
+        }
+        .expect("write to string");
+        out.push_str(std::str::from_utf8(chunk).expect("base64 is ascii"));
+        out.push_str("\x1b\\");
+    }
+    out
+}
+
+/// Encodes six rows of pixels per line, against a fixed palette of 6 levels per channel.
+/// Transparent pixels are left unpainted
+fn sixel(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let mut out = String::with_capacity((width * height) as usize);
+    write!(out, "\x1bP0;1;0q\"1;1;{width};{height}").expect("write to string");
+    for i in 0..216 {
+        let (r, g, b) = (i / 36, i / 6 % 6, i % 6);
+        write!(out, "#{i};2;{};{};{}", r * 20, g * 20, b * 20).expect("write to string");
+    }
+
+    let level = |v: u8| (v as usize * 5 + 127) / 255;
+    for top in (0..height).step_by(6) {
+        // for each palette entry used in this band, the pixels it covers in each column
+        let mut colours: Vec<Option<Vec<u8>>> = vec![None; 216];
+        for x in 0..width {
+            for dy in 0..(height - top).min(6) {
+                let p = image.get_pixel(x, top + dy);
+                if p[3] >= 128 {
+                    let c = level(p[0]) * 36 + level(p[1]) * 6 + level(p[2]);
+                    colours[c].get_or_insert_with(|| vec![0; width as usize])[x as usize] |=
+                        1 << dy;
+                }
+            }
+        }
+
+        let mut first = true;
+        for (c, columns) in colours.iter().enumerate() {
+            let Some(columns) = columns else { continue };
+            if !first {
+                // return to the start of the band to overlay the next colour
+                out.push('$');
+            }
+            first = false;
+            write!(out, "#{c}").expect("write to string");
+            let mut x = 0;
+            while x < columns.len() {
+                let run = columns[x..]
+                    .iter()
+                    .take_while(|b| **b == columns[x])
+                    .count();
+                let ch = (63 + columns[x]) as char;
+                match run {
+                    1..=3 => (0..run).for_each(|_| out.push(ch)),
+                    _ => write!(out, "!{run}{ch}").expect("write to string"),
+                }
+                x += run;
+            }
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Pairs each row of pixels with the one below it, one pair per cell
+fn blocks(image: &RgbaImage) -> Vec<Vec<HalfBlock>> {
+    let rgb = |x, y| {
+        let p = image.get_pixel(x, y);
+        [p[0], p[1], p[2]]
+    };
+    (0..image.height())
+        .step_by(2)
+        .map(|y| {
+            (0..image.width())
+                .map(|x| (rgb(x, y), (y + 1 < image.height()).then(|| rgb(x, y + 1))))
+                .collect()
+        })
+        .collect()
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,205 @@
+//! Keeps the queue from running dry, adding tracks of the library chosen by a strategy
+//!
+//! Tracks are chosen alongside the last one queued, or the one playing where the queue is
+//! empty. Tracks played or chosen lately are passed over, as long as any others remain
+
+use std::{
+    collections::{HashSet, VecDeque},
+    rc::Rc,
+    str::FromStr,
+};
+
+use crate::config::AutoDjConfig;
+use crate::loader::AudioTrack;
+use crate::stats::TrackData;
+
+/// How the next track is chosen
+#[derive(Clone, Copy, PartialEq)]
+pub enum Strategy {
+    Random,
+    /// By the artist of the last track
+    SameArtist,
+    /// Of the genre of the last track
+    SameGenre,
+    /// Released within some years of the last track
+    SimilarEra,
+    /// Never played, or played longest ago
+    LeastRecent,
+}
+
+pub struct AutoDj {
+    pub enabled: bool,
+    pub strategy: Strategy,
+    /// Fewest tracks left to play before more are added
+    queue: usize,
+    /// Years either side of the last track a similar era spans
+    era: u32,
+    /// How many of the latest tracks are not chosen again
+    avoid: usize,
+    /// Paths of the latest tracks played or chosen, oldest first
+    recent: VecDeque<String>,
+}
+
+impl Strategy {
+    const ALL: [Strategy; 5] = [
+        Strategy::Random,
+        Strategy::SameArtist,
+        Strategy::SameGenre,
+        Strategy::SimilarEra,
+        Strategy::LeastRecent,
+    ];
+
+    /// Name of the strategy in the configuration
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Random => "random",
+            Strategy::SameArtist => "same_artist",
+            Strategy::SameGenre => "same_genre",
+            Strategy::SimilarEra => "similar_era",
+            Strategy::LeastRecent => "least_recent",
+        }
+    }
+
+    /// Name of the strategy as shown
+    pub fn title(self) -> &'static str {
+        match self {
+            Strategy::Random => "random",
+            Strategy::SameArtist => "same artist",
+            Strategy::SameGenre => "same genre",
+            Strategy::SimilarEra => "similar era",
+            Strategy::LeastRecent => "least recently played",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|s| *s == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        Self::ALL.into_iter().find(|k| k.name() == s).ok_or(())
+    }
+}
+
+impl AutoDj {
+    pub fn new(config: &AutoDjConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            strategy: config.strategy,
+            queue: config.queue.max(1),
+            era: config.era,
+            avoid: config.avoid,
+            recent: VecDeque::with_capacity(config.avoid + 1),
+        }
+    }
+
+    /// Remembers a track as played, so it is not soon chosen again
+    pub fn played(&mut self, track: &AudioTrack) {
+        if !self.recent.iter().any(|p| p == track.path()) {
+            self.remember(track);
+        }
+    }
+
+    fn remember(&mut self, track: &AudioTrack) {
+        self.recent.push_back(track.path().to_owned());
+        while self.recent.len() > self.avoid {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Tracks to add to a queue holding `queued`, the last of them or the one playing being
+    /// `last`; none while disabled or where the queue holds enough
+    pub fn fill(
+        &mut self,
+        library: &[Rc<AudioTrack>],
+        queued: usize,
+        mut last: Option<Rc<AudioTrack>>,
+        data: impl Fn(&AudioTrack) -> TrackData,
+    ) -> Vec<Rc<AudioTrack>> {
+        let mut chosen = Vec::new();
+        if !self.enabled {
+            return chosen;
+        }
+        for _ in queued..self.queue {
+            let Some(track) = self.pick(library, last.as_deref(), &data) else {
+                break;
+            };
+            self.remember(&track);
+            last = Some(track.clone());
+            chosen.push(track);
+        }
+        chosen
+    }
+
+    /// Chooses a track to follow `last`, keeping to the strategy where any track fits it
+    fn pick(
+        &self,
+        library: &[Rc<AudioTrack>],
+        last: Option<&AudioTrack>,
+        data: &impl Fn(&AudioTrack) -> TrackData,
+    ) -> Option<Rc<AudioTrack>> {
+        let recent: HashSet<&str> = self.recent.iter().map(String::as_str).collect();
+        let mut candidates: Vec<&Rc<AudioTrack>> = library
+            .iter()
+            .filter(|t| !recent.contains(t.path()))
+            .collect();
+        if candidates.is_empty() {
+            // the whole library was played lately; anything but the last track will do
+            candidates = library
+                .iter()
+                .filter(|t| last.is_none_or(|l| l.path() != t.path()))
+                .collect();
+        }
+
+        let lower = |s: Option<&str>| s.map(str::to_lowercase);
+        let fitting: Vec<&Rc<AudioTrack>> = match (self.strategy, last) {
+            (Strategy::SameArtist, Some(l)) if l.artists().is_some() => {
+                let artists = lower(l.artists());
+                candidates
+                    .iter()
+                    .copied()
+                    .filter(|t| lower(t.artists()) == artists)
+                    .collect()
+            }
+            (Strategy::SameGenre, Some(l)) if l.genre().is_some() => {
+                let genre = lower(l.genre());
+                candidates
+                    .iter()
+                    .copied()
+                    .filter(|t| lower(t.genre()) == genre)
+                    .collect()
+            }
+            (Strategy::SimilarEra, Some(l)) if l.year().is_some() => {
+                let year = l.year().unwrap_or_default();
+                candidates
+                    .iter()
+                    .copied()
+                    .filter(|t| t.year().is_some_and(|y| y.abs_diff(year) <= self.era))
+                    .collect()
+            }
+            (Strategy::LeastRecent, _) => {
+                // of those played longest ago, never played coming first, any one
+                let oldest = candidates.iter().map(|t| data(t).last_played).min();
+                candidates
+                    .iter()
+                    .copied()
+                    .filter(|t| Some(data(t).last_played) == oldest)
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        let pool = match fitting.is_empty() {
+            true => candidates,
+            false => fitting,
+        };
+        match pool.len() {
+            0 => None,
+            n => Some(pool[fastrand::usize(..n)].clone()),
+        }
+    }
+}
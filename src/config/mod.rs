@@ -0,0 +1,485 @@
+//! Reads user configuration from `$XDG_CONFIG_HOME/mplay/config`
+//!
+//! The file is a flat list of `key = value` pairs grouped under `[section]` headers, with lines
+//! starting with `#` left as comments. A `#` elsewhere is part of the value, as in a `#rrggbb`
+//! colour or a URL. Unknown keys are ignored and malformed values fall back to their default.
+//! Each smart playlist has a section of its own, named `[smart.<name>]`
+
+use std::{env, fs, io, path::PathBuf, time::Duration};
+
+use crate::art::Protocol;
+use crate::autodj::Strategy;
+use crate::keymap::Keymap;
+use crate::playback::{BANDS, Band, DspSettings};
+use crate::playlist::SmartPlaylist;
+use crate::theme::{Depth, Theme};
+
+pub struct Config {
+    /// Directory scanned for audio files on launch
+    pub music_dir: PathBuf,
+    /// Whether scanning and watching the library follows symbolic links
+    pub follow_symlinks: bool,
+    /// Whether the interface takes mouse input
+    pub mouse: bool,
+    pub playback: PlaybackConfig,
+    /// How album art is drawn, or `None` to detect from the terminal
+    pub art: Option<Protocol>,
+    pub keys: Keymap,
+    pub theme: Theme,
+    /// Colours the terminal shows, or `None` to detect from the environment
+    pub colours: Option<Depth>,
+    pub scrobble: ScrobbleConfig,
+    pub stats: StatsConfig,
+    /// Whether ratings are also written to the tags of the files rated
+    pub rating_tags: bool,
+    /// Smart playlists, in the order defined
+    pub smart: Vec<SmartPlaylist>,
+    pub autodj: AutoDjConfig,
+    /// Radio stations by name, with the URL of each stream
+    pub stations: Vec<(String, String)>,
+    /// Directory podcast episodes are downloaded into
+    pub podcast_dir: PathBuf,
+}
+
+#[derive(Clone)]
+pub struct PlaybackConfig {
+    /// Length of the fade applied when pausing, resuming or skipping
+    pub fade: Duration,
+    /// Overlap between consecutive tracks of different albums; zero disables crossfading
+    pub crossfade: Duration,
+    /// Equaliser and effects applied to every source
+    pub dsp: DspSettings,
+    /// Name of the output device, or `None` for the system default
+    pub device: Option<String>,
+    /// Initial playback speed multiplier
+    pub speed: f32,
+    /// Change speed by time stretching, keeping pitch, rather than by resampling
+    pub preserve_pitch: bool,
+}
+
+/// Accounts listens are submitted to; a service is used where its credentials are all set
+#[derive(Clone)]
+pub struct ScrobbleConfig {
+    pub listenbrainz_token: Option<String>,
+    pub listenbrainz_url: String,
+    pub lastfm_key: Option<String>,
+    pub lastfm_secret: Option<String>,
+    /// Session key authorising submissions to the account
+    pub lastfm_session: Option<String>,
+    pub lastfm_url: String,
+}
+
+#[derive(Clone)]
+pub struct StatsConfig {
+    /// Periods the statistics view steps through, in days, `None` being all time
+    pub periods: Vec<Option<u32>>,
+    /// Days without a play after which a track counts as forgotten
+    pub forgotten: u32,
+}
+
+/// Adding tracks to the queue as it runs low
+#[derive(Clone)]
+pub struct AutoDjConfig {
+    pub enabled: bool,
+    pub strategy: Strategy,
+    /// Fewest tracks left to play before more are added
+    pub queue: usize,
+    /// Latest tracks not chosen again
+    pub avoid: usize,
+    /// Years either side a similar era spans
+    pub era: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            music_dir: home_dir().join("Music"),
+            follow_symlinks: true,
+            mouse: true,
+            playback: PlaybackConfig::default(),
+            art: None,
+            keys: Keymap::default(),
+            theme: Theme::default(),
+            colours: None,
+            scrobble: ScrobbleConfig::default(),
+            stats: StatsConfig::default(),
+            rating_tags: false,
+            smart: Vec::new(),
+            autodj: AutoDjConfig::default(),
+            stations: Vec::new(),
+            podcast_dir: home_dir().join("Podcasts"),
+        }
+    }
+}
+
+impl Default for AutoDjConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            strategy: Strategy::Random,
+            queue: 3,
+            avoid: 50,
+            era: 5,
+        }
+    }
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            periods: vec![Some(7), Some(30), Some(365), None],
+            forgotten: 180,
+        }
+    }
+}
+
+impl Default for ScrobbleConfig {
+    fn default() -> Self {
+        Self {
+            listenbrainz_token: None,
+            listenbrainz_url: String::from("https://api.listenbrainz.org"),
+            lastfm_key: None,
+            lastfm_secret: None,
+            lastfm_session: None,
+            lastfm_url: String::from("https://ws.audioscrobbler.com"),
+        }
+    }
+}
+
+impl Default for PlaybackConfig {
+    fn default() -> Self {
+        Self {
+            fade: Duration::from_millis(150),
+            crossfade: Duration::ZERO,
+            dsp: DspSettings::default(),
+            device: None,
+            speed: 1.0,
+            preserve_pitch: true,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration file, falling back to defaults where it is absent
+    pub fn load() -> Self {
+        match fs::read_to_string(config_dir().join("config")) {
+            Ok(s) => Self::parse(&s),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut config = Self::default();
+
+        let mut section = String::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(s) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = s.trim().to_owned();
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                eprintln!("config: expected `key = value`, found `{line}`");
+                continue;
+            };
+            config.set(&section, key.trim(), value.trim());
+        }
+
+        config
+    }
+
+    /// Applies a single entry, where `section` is empty for keys preceding any header
+    fn set(&mut self, section: &str, key: &str, value: &str) {
+        if let Some(name) = section.strip_prefix("smart.") {
+            self.set_smart(name.trim(), key, value);
+            return;
+        }
+        match (section, key) {
+            ("", "music_dir") => self.music_dir = expand_home(value),
+            ("", "follow_symlinks") => set_parsed(&mut self.follow_symlinks, value),
+            ("", "mouse") => set_parsed(&mut self.mouse, value),
+            ("playback", "fade") => set_seconds(&mut self.playback.fade, value),
+            ("playback", "crossfade") => set_seconds(&mut self.playback.crossfade, value),
+            ("playback", "device") => self.playback.device = Some(value.to_owned()),
+            ("playback", "speed") => set_parsed(&mut self.playback.speed, value),
+            ("playback", "preserve_pitch") => set_parsed(&mut self.playback.preserve_pitch, value),
+            ("equalizer", _) => set_dsp(&mut self.playback.dsp, key, value),
+            ("art", "protocol") => match value {
+                "auto" => self.art = None,
+                _ => match value.parse() {
+                    Ok(p) => self.art = Some(p),
+                    Err(()) => eprintln!("config: unknown art protocol `{value}`"),
+                },
+            },
+            ("theme", "colours") => match value {
+                "auto" => self.colours = None,
+                _ => match value.parse() {
+                    Ok(d) => self.colours = Some(d),
+                    Err(()) => eprintln!("config: unknown colour depth `{value}`"),
+                },
+            },
+            ("theme", _) => {
+                let result = match key {
+                    "name" => self.theme.apply_preset(value),
+                    _ => self.theme.set(key, value),
+                };
+                if let Err(e) = result {
+                    eprintln!("config: {e}");
+                }
+            }
+            ("scrobble", "listenbrainz_token") => {
+                self.scrobble.listenbrainz_token = Some(value.to_owned())
+            }
+            ("scrobble", "listenbrainz_url") => self.scrobble.listenbrainz_url = value.to_owned(),
+            ("scrobble", "lastfm_key") => self.scrobble.lastfm_key = Some(value.to_owned()),
+            ("scrobble", "lastfm_secret") => self.scrobble.lastfm_secret = Some(value.to_owned()),
+            ("scrobble", "lastfm_session") => self.scrobble.lastfm_session = Some(value.to_owned()),
+            ("scrobble", "lastfm_url") => self.scrobble.lastfm_url = value.to_owned(),
+            ("stats", "periods") => {
+                let periods: Option<Vec<Option<u32>>> = value
+                    .split(',')
+                    .map(|p| match p.trim() {
+                        "all" => Some(None),
+                        p => p.parse().ok().filter(|d| *d > 0).map(Some),
+                    })
+                    .collect();
+                match periods {
+                    Some(p) if !p.is_empty() => self.stats.periods = p,
+                    _ => eprintln!("config: invalid periods `{value}`"),
+                }
+            }
+            ("stats", "forgotten") => set_parsed(&mut self.stats.forgotten, value),
+            ("stats", "rating_tags") => set_parsed(&mut self.rating_tags, value),
+            ("autodj", "enabled") => set_parsed(&mut self.autodj.enabled, value),
+            ("autodj", "strategy") => match value.parse() {
+                Ok(s) => self.autodj.strategy = s,
+                Err(()) => eprintln!("config: unknown auto-DJ strategy `{value}`"),
+            },
+            ("autodj", "queue") => set_parsed(&mut self.autodj.queue, value),
+            ("autodj", "avoid") => set_parsed(&mut self.autodj.avoid, value),
+            ("autodj", "era") => set_parsed(&mut self.autodj.era, value),
+            ("podcasts", "directory") => self.podcast_dir = expand_home(value),
+            ("stations", _) => self.stations.push((key.to_owned(), value.to_owned())),
+            ("keys", _) => {
+                let result = match key {
+                    "preset" => self.keys.apply_preset(value),
+                    _ => self.keys.bind(key, value),
+                };
+                if let Err(e) = result {
+                    eprintln!("config: {e}");
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Config {
+    fn set_smart(&mut self, name: &str, key: &str, value: &str) {
+        let i = match self.smart.iter().position(|p| p.name == name) {
+            Some(i) => i,
+            None => {
+                self.smart.push(SmartPlaylist::new(name));
+                self.smart.len() - 1
+            }
+        };
+        let playlist = &mut self.smart[i];
+        match key {
+            "rules" => {
+                if let Err(e) = playlist.set_rules(value) {
+                    eprintln!("config: {e} in the rules of {name}");
+                }
+            }
+            "limit" => match value.parse() {
+                Ok(0) | Err(_) => eprintln!("config: invalid limit `{value}` of {name}"),
+                Ok(n) => playlist.limit = Some(n),
+            },
+            "order" => match value.parse() {
+                Ok(o) => playlist.order = o,
+                Err(()) => eprintln!("config: unknown order `{value}` of {name}"),
+            },
+            _ => (),
+        }
+    }
+}
+
+fn set_dsp(dsp: &mut DspSettings, key: &str, value: &str) {
+    let valid = match key {
+        "enabled" => value.parse().map(|v| dsp.enabled = v).is_ok(),
+        "preset" => dsp.apply_preset(value),
+        "preamp" => value.parse().map(|v| dsp.preamp = v).is_ok(),
+        "balance" => value
+            .parse()
+            .map(|v: f32| dsp.balance = v.clamp(-1.0, 1.0))
+            .is_ok(),
+        "mono" => value.parse().map(|v| dsp.mono = v).is_ok(),
+        "limiter" => value.parse().map(|v| dsp.limiter = v).is_ok(),
+        _ => match key
+            .strip_prefix("band")
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|n| (1..=BANDS).contains(n))
+        {
+            // `band<n> = <frequency> <gain> <q>`
+            Some(n) => {
+                let fields: Vec<f32> = value
+                    .split_whitespace()
+                    .filter_map(|f| f.parse().ok())
+                    .collect();
+                match fields[..] {
+                    [frequency, gain, q] => {
+                        dsp.bands[n - 1] = Band { frequency, gain, q };
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            None => true,
+        },
+    };
+    if !valid {
+        eprintln!("config: invalid equalizer value `{key} = {value}`");
+    }
+}
+
+/// Serialises equaliser settings as the entries of an `[equalizer]` section
+pub fn dsp_entries(dsp: &DspSettings) -> Vec<(String, String)> {
+    let mut entries = Vec::with_capacity(16);
+    if let Some(p) = &dsp.preset {
+        entries.push((String::from("preset"), p.clone()));
+    }
+    entries.extend([
+        (String::from("enabled"), dsp.enabled.to_string()),
+        (String::from("preamp"), dsp.preamp.to_string()),
+        (String::from("balance"), dsp.balance.to_string()),
+        (String::from("mono"), dsp.mono.to_string()),
+        (String::from("limiter"), dsp.limiter.to_string()),
+    ]);
+    for (i, b) in dsp.bands.iter().enumerate() {
+        entries.push((
+            format!("band{}", i + 1),
+            format!("{} {} {}", b.frequency, b.gain, b.q),
+        ));
+    }
+    entries
+}
+
+/// Replaces one section of the configuration file with the given entries, leaving the rest of
+/// the file, comments included, untouched
+pub fn save_section(name: &str, entries: &[(String, String)]) -> io::Result<()> {
+    let dir = config_dir();
+    let path = dir.join("config");
+    let text = match fs::read_to_string(&path) {
+        Ok(s) => s,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    let mut out = String::with_capacity(text.len() + 256);
+    let mut skipping = false;
+    for line in text.lines() {
+        let header = line
+            .trim()
+            .strip_prefix('[')
+            .and_then(|l| l.strip_suffix(']'))
+            .map(str::trim);
+        if let Some(h) = header {
+            skipping = h == name;
+        }
+        if !skipping {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+    out.push_str(&format!("[{name}]\n"));
+    for (k, v) in entries {
+        out.push_str(&format!("{k} = {v}\n"));
+    }
+
+    // write beside the original and rename over it so a failed write never truncates the file
+    fs::create_dir_all(&dir)?;
+    let tmp = dir.join("config.tmp");
+    fs::write(&tmp, out)?;
+    fs::rename(tmp, path)
+}
+
+fn set_parsed<T: std::str::FromStr>(field: &mut T, value: &str) {
+    match value.parse() {
+        Ok(v) => *field = v,
+        Err(_) => eprintln!("config: invalid value `{value}`"),
+    }
+}
+
+fn set_seconds(field: &mut Duration, value: &str) {
+    match value
+        .parse::<f32>()
+        .ok()
+        .and_then(|v| Duration::try_from_secs_f32(v).ok())
+    {
+        Some(d) => *field = d,
+        None => eprintln!("config: `{value}` is not a number of seconds"),
+    }
+}
+
+fn expand_home(value: &str) -> PathBuf {
+    match value.strip_prefix("~/") {
+        Some(rest) => home_dir().join(rest),
+        None => PathBuf::from(value),
+    }
+}
+
+fn home_dir() -> PathBuf {
+    env::var_os("HOME").map(PathBuf::from).unwrap_or_default()
+}
+
+/// Directory holding the configuration file, per the XDG base directory specification
+pub fn config_dir() -> PathBuf {
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(p) if !p.is_empty() => PathBuf::from(p).join("mplay"),
+        _ => home_dir().join(".config/mplay"),
+    }
+}
+
+/// Directory holding state kept between runs, per the XDG base directory specification
+pub fn data_dir() -> PathBuf {
+    match env::var_os("XDG_DATA_HOME") {
+        Some(p) if !p.is_empty() => PathBuf::from(p).join("mplay"),
+        _ => home_dir().join(".local/share/mplay"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::{Color, Modifier};
+
+    use super::*;
+
+    #[test]
+    fn hash_within_a_value_is_kept() {
+        let config = Config::parse(
+            "# a comment\n\
+             [theme]\n\
+             highlight = bold black on #88c0d0\n\
+             [scrobble]\n\
+             lastfm_url = http://localhost/#fragment\n",
+        );
+        let highlight = config.theme.highlight;
+        assert_eq!(highlight.bg, Some(Color::Rgb(0x88, 0xc0, 0xd0)));
+        assert_eq!(highlight.fg, Some(Color::Black));
+        assert!(highlight.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(config.scrobble.lastfm_url, "http://localhost/#fragment");
+    }
+
+    #[test]
+    fn indented_comment_is_skipped() {
+        let config = Config::parse("  # music_dir = /nowhere\nfollow_symlinks = true\n");
+        assert_ne!(config.music_dir, PathBuf::from("/nowhere"));
+        assert!(config.follow_symlinks);
+    }
+}
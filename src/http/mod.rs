@@ -0,0 +1,368 @@
+//! Just enough HTTP to post a form or a JSON document and to read a stream, over plain TCP or
+//! TLS
+//!
+//! Requests are HTTP/1.0, so responses come unchunked and the connection closes once sent
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned, pki_types::ServerName};
+
+#[cfg(test)]
+pub mod stand_in;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most redirects followed from one URL
+const REDIRECTS: usize = 5;
+
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+/// A response read as it arrives, from just after its headers
+pub struct Body {
+    /// 200, or 206 where a range was asked for and sent
+    pub status: u16,
+    headers: Vec<(String, String)>,
+    pub reader: BufReader<Connection>,
+}
+
+/// An open connection, encrypted for `https://`
+pub enum Connection {
+    Plain(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+/// Parts of an `http://` or `https://` URL
+struct Url<'a> {
+    tls: bool,
+    /// Host with any port, as sent in the `Host` header
+    authority: &'a str,
+    host: &'a str,
+    port: u16,
+    /// Path and query, without the fragment, which is never sent
+    path: String,
+}
+
+/// Posts `body` to a URL, reading the whole response
+pub fn post(
+    url: &str,
+    headers: &[(&str, &str)],
+    content_type: &str,
+    body: &str,
+) -> io::Result<Response> {
+    let url = Url::parse(url)?;
+    let mut connection = url.connect()?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: {}\r\n\
+         Content-Type: {content_type}\r\nContent-Length: {}\r\n",
+        url.path,
+        url.authority,
+        user_agent(),
+        body.len(),
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{name}: {value}\r\n"));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+    connection.write_all(request.as_bytes())?;
+
+    let mut response = Vec::new();
+    connection.read_to_end(&mut response)?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let Some(status) = head.split_whitespace().nth(1).and_then(|s| s.parse().ok()) else {
+        return Err(invalid(format!(
+            "malformed response from `{}`",
+            url.authority
+        )));
+    };
+    Ok(Response {
+        status,
+        body: body.to_owned(),
+    })
+}
+
+/// Requests a URL, following redirects, and returns once the headers of a successful response
+/// have arrived
+pub fn get(url: &str, headers: &[(&str, &str)]) -> io::Result<Body> {
+    let mut url = url.to_owned();
+    for _ in 0..=REDIRECTS {
+        let parsed = Url::parse(&url)?;
+        let mut connection = parsed.connect()?;
+        let mut request = format!(
+            "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: {}\r\n",
+            parsed.path,
+            parsed.authority,
+            user_agent(),
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+        request.push_str("\r\n");
+        connection.write_all(request.as_bytes())?;
+
+        let mut reader = BufReader::new(connection);
+        let (status, headers) = read_head(&mut reader)?;
+        let body = Body {
+            status,
+            headers,
+            reader,
+        };
+        match status {
+            200 | 206 => return Ok(body),
+            301 | 302 | 303 | 307 | 308 => {
+                let Some(location) = body.header("location") else {
+                    return Err(invalid(format!("redirect from `{url}` goes nowhere")));
+                };
+                url = parsed.redirect(location)?;
+            }
+            s => return Err(io::Error::other(format!("`{url}` answered {s}"))),
+        }
+    }
+    Err(io::Error::other(format!("too many redirects from `{url}`")))
+}
+
+impl Body {
+    /// Value of a header, the name matched ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Reads the status line and headers of a response. Shoutcast servers answer `ICY 200 OK`
+/// in place of an HTTP version, which is taken as the same
+fn read_head(reader: &mut impl BufRead) -> io::Result<(u16, Vec<(String, String)>)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let Some(status) = line.split_whitespace().nth(1).and_then(|s| s.parse().ok()) else {
+        return Err(invalid(format!("malformed status line `{}`", line.trim())));
+    };
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            break;
+        };
+        headers.push((name.trim().to_owned(), value.trim().to_owned()));
+    }
+    Ok((status, headers))
+}
+
+impl<'a> Url<'a> {
+    fn parse(url: &'a str) -> io::Result<Self> {
+        let (tls, rest) = match (url.strip_prefix("http://"), url.strip_prefix("https://")) {
+            (Some(rest), _) => (false, rest),
+            (_, Some(rest)) => (true, rest),
+            _ => return Err(invalid(format!("`{url}` is not an http or https URL"))),
+        };
+        let rest = rest.split_once('#').map_or(rest, |(r, _)| r);
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+        let path = match path.starts_with('/') {
+            true => path.to_owned(),
+            false => format!("/{path}"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((h, p)) => match p.parse() {
+                Ok(p) => (h, p),
+                Err(_) => return Err(invalid(format!("bad port in `{url}`"))),
+            },
+            None => (authority, if tls { 443 } else { 80 }),
+        };
+        Ok(Self {
+            tls,
+            authority,
+            host,
+            port,
+            path,
+        })
+    }
+
+    fn scheme(&self) -> &'static str {
+        if self.tls { "https" } else { "http" }
+    }
+
+    /// URL a redirect to `location` leads to, which may be relative to this one. A redirect
+    /// from `https://` to `http://` is refused, as it would send the request in the clear
+    fn redirect(&self, location: &str) -> io::Result<String> {
+        let url = if location.starts_with("http://") || location.starts_with("https://") {
+            location.to_owned()
+        } else if location.starts_with("//") {
+            format!("{}:{location}", self.scheme())
+        } else {
+            let path = self.path.split_once('?').map_or(&*self.path, |(p, _)| p);
+            let path = match location.as_bytes().first() {
+                Some(b'/') => location.to_owned(),
+                Some(b'?') => format!("{path}{location}"),
+                // relative to the directory of the path
+                _ => format!("{}{location}", &path[..=path.rfind('/').unwrap_or(0)]),
+            };
+            format!("{}://{}{path}", self.scheme(), self.authority)
+        };
+        if self.tls && url.starts_with("http://") {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("refused redirect from https to `{url}`"),
+            ));
+        }
+        Ok(url)
+    }
+
+    fn connect(&self) -> io::Result<Connection> {
+        let Some(addr) = (self.host, self.port).to_socket_addrs()?.next() else {
+            return Err(invalid(format!("no address for `{}`", self.host)));
+        };
+        let stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        if !self.tls {
+            return Ok(Connection::Plain(stream));
+        }
+        let name = ServerName::try_from(self.host.to_owned())
+            .map_err(|_| invalid(format!("`{}` cannot name a server", self.host)))?;
+        let tls = ClientConnection::new(tls_config(), name).map_err(io::Error::other)?;
+        Ok(Connection::Tls(Box::new(StreamOwned::new(tls, stream))))
+    }
+}
+
+/// Settings of every TLS connection, trusting the Mozilla root certificates
+fn tls_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            Arc::new(
+                ClientConfig::builder()
+                    .with_root_certificates(roots)
+                    .with_no_client_auth(),
+            )
+        })
+        .clone()
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Plain(s) => s.read(buf),
+            // many servers close without notifying, which is as good an end as any here
+            Connection::Tls(s) => match s.read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(0),
+                r => r,
+            },
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Plain(s) => s.write(buf),
+            Connection::Tls(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Plain(s) => s.flush(),
+            Connection::Tls(s) => s.flush(),
+        }
+    }
+}
+
+fn user_agent() -> String {
+    format!("mplay/{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Encodes pairs as an `application/x-www-form-urlencoded` body
+pub fn form(pairs: &[(String, String)]) -> String {
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(v)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stand_in::StandIn;
+
+    #[test]
+    fn fragment_is_not_sent() {
+        let url = Url::parse("https://host:8443/a/b?q=1#part").unwrap();
+        assert_eq!(
+            (url.authority, url.port, url.path.as_str()),
+            ("host:8443", 8443, "/a/b?q=1")
+        );
+        assert_eq!(Url::parse("http://host#top").unwrap().path, "/");
+        assert_eq!(Url::parse("http://host?q").unwrap().path, "/?q");
+    }
+
+    #[test]
+    fn relative_redirects_resolve_against_the_url() {
+        let url = Url::parse("https://host/a/b?q=1").unwrap();
+        let redirect = |location| url.redirect(location).unwrap();
+        assert_eq!(redirect("c"), "https://host/a/c");
+        assert_eq!(redirect("../c"), "https://host/a/../c");
+        assert_eq!(redirect("/c"), "https://host/c");
+        assert_eq!(redirect("?r=2"), "https://host/a/b?r=2");
+        assert_eq!(redirect("//other/c"), "https://other/c");
+        assert_eq!(redirect("https://other/c"), "https://other/c");
+    }
+
+    #[test]
+    fn redirect_to_plain_http_is_refused() {
+        let url = Url::parse("https://host/a").unwrap();
+        assert!(url.redirect("http://host/a").is_err());
+        let url = Url::parse("http://host/a").unwrap();
+        assert_eq!(url.redirect("http://other/").unwrap(), "http://other/");
+    }
+
+    #[test]
+    fn relative_redirect_is_followed() {
+        let server = StandIn::serve(vec![
+            b"HTTP/1.0 302 Found\r\nLocation: next\r\n\r\n".to_vec(),
+            stand_in::response(200, b"here"),
+        ]);
+        let mut body = get(&format!("{}/dir/first#part", server.url), &[]).unwrap();
+        let mut text = String::new();
+        body.reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "here");
+        assert!(server.request().head.starts_with("GET /dir/first HTTP/1.0"));
+        assert!(server.request().head.starts_with("GET /dir/next HTTP/1.0"));
+    }
+}
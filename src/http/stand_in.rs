@@ -0,0 +1,80 @@
+//! A local server standing in for a remote one in tests, answering each connection with the
+//! next of a list of canned responses and closing it
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+pub struct StandIn {
+    /// Base URL of the server, without a trailing slash
+    pub url: String,
+    requests: Receiver<Request>,
+}
+
+/// A request as the server received it
+pub struct Request {
+    /// Request line and headers
+    pub head: String,
+    pub body: String,
+}
+
+impl StandIn {
+    /// Serves one connection for each response, then stops listening
+    pub fn serve(responses: Vec<Vec<u8>>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for response in responses {
+                let Ok((stream, _)) = listener.accept() else {
+                    return;
+                };
+                let mut reader = BufReader::new(stream);
+                let mut head = String::new();
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+                let len = head
+                    .lines()
+                    .filter_map(|l| l.split_once(':'))
+                    .find(|(n, _)| n.eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, v)| v.trim().parse().ok())
+                    .unwrap_or(0);
+                let mut body = vec![0; len];
+                let _ = reader.read_exact(&mut body);
+                let body = String::from_utf8_lossy(&body).into_owned();
+                let _ = reader.get_mut().write_all(&response);
+                let _ = tx.send(Request { head, body });
+            }
+        });
+        Self { url, requests }
+    }
+
+    /// The next request received, waiting a few seconds for it
+    pub fn request(&self) -> Request {
+        self.requests
+            .recv_timeout(Duration::from_secs(5))
+            .expect("no request reached the stand-in")
+    }
+}
+
+/// A response of `status` carrying `body`
+pub fn response(status: u16, body: &[u8]) -> Vec<u8> {
+    let mut response = format!("HTTP/1.0 {status} Stand-in\r\n\r\n").into_bytes();
+    response.extend_from_slice(body);
+    response
+}
+
+/// URL of a port nothing listens on
+pub fn unreachable() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
@@ -0,0 +1,432 @@
+//! Everything a key can be bound to, with the names used in the configuration
+
+/// Where an action applies: everywhere, or in one view
+#[derive(Clone, Copy, PartialEq)]
+pub enum Context {
+    Global,
+    Tracks,
+    Equalizer,
+    Lyrics,
+    Report,
+    Stats,
+    Podcasts,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Quit,
+    Help,
+    NextView,
+    Devices,
+    Stations,
+    TogglePause,
+    Skip,
+    ClearQueue,
+    Slower,
+    Faster,
+    ResetSpeed,
+    TogglePitch,
+    LoopStart,
+    LoopEnd,
+    ClearLoop,
+    ToggleAutoDj,
+    NextDjStrategy,
+    Down,
+    Up,
+    PageDown,
+    PageUp,
+    HalfPageDown,
+    HalfPageUp,
+    Bottom,
+    Top,
+    ScreenTop,
+    ScreenMiddle,
+    ScreenBottom,
+    Center,
+    Enqueue,
+    PlayNext,
+    Search,
+    Rescan,
+    EditTags,
+    EditAlbumTags,
+    Mark,
+    Visual,
+    MarkAll,
+    ClearMarks,
+    AddToPlaylist,
+    QueuePlaylist,
+    JumpToPlaying,
+    JumpToLetter,
+    Rate,
+    ToggleFavourite,
+    NextSort,
+    ReverseSort,
+    Decrease,
+    Increase,
+    LowerFrequency,
+    RaiseFrequency,
+    LowerQ,
+    RaiseQ,
+    NextPreset,
+    ResetEqualizer,
+    ToggleMono,
+    ToggleLimiter,
+    ToggleEqualizer,
+    SaveEqualizer,
+    LyricsEarlier,
+    LyricsLater,
+    ResetLyricsOffset,
+    PreviousPeriod,
+    NextPeriod,
+    QueueForgotten,
+    PreviousFeed,
+    NextFeed,
+    PlayEpisode,
+    DownloadEpisode,
+    ToggleFinished,
+    Subscribe,
+    Unsubscribe,
+    RefreshFeeds,
+}
+
+/// Every view, for actions moving through whatever a view lists
+const VIEWS: &[Context] = &[
+    Context::Tracks,
+    Context::Equalizer,
+    Context::Lyrics,
+    Context::Report,
+    Context::Stats,
+    Context::Podcasts,
+];
+
+impl Context {
+    pub fn title(self) -> &'static str {
+        match self {
+            Context::Global => "everywhere",
+            Context::Tracks => "tracks",
+            Context::Equalizer => "equalizer",
+            Context::Lyrics => "lyrics",
+            Context::Report => "scan report",
+            Context::Stats => "stats",
+            Context::Podcasts => "podcasts",
+        }
+    }
+}
+
+impl Action {
+    /// Every action, in the order they are listed in help
+    pub const ALL: [Action; 73] = [
+        Action::Quit,
+        Action::Help,
+        Action::NextView,
+        Action::Devices,
+        Action::Stations,
+        Action::TogglePause,
+        Action::Skip,
+        Action::ClearQueue,
+        Action::Slower,
+        Action::Faster,
+        Action::ResetSpeed,
+        Action::TogglePitch,
+        Action::LoopStart,
+        Action::LoopEnd,
+        Action::ClearLoop,
+        Action::ToggleAutoDj,
+        Action::NextDjStrategy,
+        Action::Down,
+        Action::Up,
+        Action::PageDown,
+        Action::PageUp,
+        Action::HalfPageDown,
+        Action::HalfPageUp,
+        Action::Bottom,
+        Action::Top,
+        Action::ScreenTop,
+        Action::ScreenMiddle,
+        Action::ScreenBottom,
+        Action::Center,
+        Action::Enqueue,
+        Action::PlayNext,
+        Action::Search,
+        Action::Rescan,
+        Action::EditTags,
+        Action::EditAlbumTags,
+        Action::Mark,
+        Action::Visual,
+        Action::MarkAll,
+        Action::ClearMarks,
+        Action::AddToPlaylist,
+        Action::QueuePlaylist,
+        Action::JumpToPlaying,
+        Action::JumpToLetter,
+        Action::Rate,
+        Action::ToggleFavourite,
+        Action::NextSort,
+        Action::ReverseSort,
+        Action::Decrease,
+        Action::Increase,
+        Action::LowerFrequency,
+        Action::RaiseFrequency,
+        Action::LowerQ,
+        Action::RaiseQ,
+        Action::NextPreset,
+        Action::ResetEqualizer,
+        Action::ToggleMono,
+        Action::ToggleLimiter,
+        Action::ToggleEqualizer,
+        Action::SaveEqualizer,
+        Action::LyricsEarlier,
+        Action::LyricsLater,
+        Action::ResetLyricsOffset,
+        Action::PreviousPeriod,
+        Action::NextPeriod,
+        Action::QueueForgotten,
+        Action::PreviousFeed,
+        Action::NextFeed,
+        Action::PlayEpisode,
+        Action::DownloadEpisode,
+        Action::ToggleFinished,
+        Action::Subscribe,
+        Action::Unsubscribe,
+        Action::RefreshFeeds,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    /// Name of the action in the `[keys]` section of the configuration
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Help => "help",
+            Action::NextView => "next_view",
+            Action::Devices => "devices",
+            Action::Stations => "stations",
+            Action::TogglePause => "toggle_pause",
+            Action::Skip => "skip",
+            Action::ClearQueue => "clear_queue",
+            Action::Slower => "slower",
+            Action::Faster => "faster",
+            Action::ResetSpeed => "reset_speed",
+            Action::TogglePitch => "toggle_pitch",
+            Action::LoopStart => "loop_start",
+            Action::LoopEnd => "loop_end",
+            Action::ClearLoop => "clear_loop",
+            Action::ToggleAutoDj => "toggle_autodj",
+            Action::NextDjStrategy => "next_dj_strategy",
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::PageDown => "page_down",
+            Action::PageUp => "page_up",
+            Action::HalfPageDown => "half_page_down",
+            Action::HalfPageUp => "half_page_up",
+            Action::Bottom => "bottom",
+            Action::Top => "top",
+            Action::ScreenTop => "screen_top",
+            Action::ScreenMiddle => "screen_middle",
+            Action::ScreenBottom => "screen_bottom",
+            Action::Center => "center",
+            Action::Enqueue => "enqueue",
+            Action::PlayNext => "play_next",
+            Action::Search => "search",
+            Action::Rescan => "rescan",
+            Action::EditTags => "edit_tags",
+            Action::EditAlbumTags => "edit_album_tags",
+            Action::Mark => "mark",
+            Action::Visual => "visual",
+            Action::MarkAll => "mark_all",
+            Action::ClearMarks => "clear_marks",
+            Action::AddToPlaylist => "add_to_playlist",
+            Action::QueuePlaylist => "queue_playlist",
+            Action::JumpToPlaying => "jump_to_playing",
+            Action::JumpToLetter => "jump_to_letter",
+            Action::Rate => "rate",
+            Action::ToggleFavourite => "toggle_favourite",
+            Action::NextSort => "next_sort",
+            Action::ReverseSort => "reverse_sort",
+            Action::Decrease => "decrease",
+            Action::Increase => "increase",
+            Action::LowerFrequency => "lower_frequency",
+            Action::RaiseFrequency => "raise_frequency",
+            Action::LowerQ => "lower_q",
+            Action::RaiseQ => "raise_q",
+            Action::NextPreset => "next_preset",
+            Action::ResetEqualizer => "reset_equalizer",
+            Action::ToggleMono => "toggle_mono",
+            Action::ToggleLimiter => "toggle_limiter",
+            Action::ToggleEqualizer => "toggle_equalizer",
+            Action::SaveEqualizer => "save_equalizer",
+            Action::LyricsEarlier => "lyrics_earlier",
+            Action::LyricsLater => "lyrics_later",
+            Action::ResetLyricsOffset => "reset_lyrics_offset",
+            Action::PreviousPeriod => "previous_period",
+            Action::NextPeriod => "next_period",
+            Action::QueueForgotten => "queue_forgotten",
+            Action::PreviousFeed => "previous_feed",
+            Action::NextFeed => "next_feed",
+            Action::PlayEpisode => "play_episode",
+            Action::DownloadEpisode => "download_episode",
+            Action::ToggleFinished => "toggle_finished",
+            Action::Subscribe => "subscribe",
+            Action::Unsubscribe => "unsubscribe",
+            Action::RefreshFeeds => "refresh_feeds",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Help => "show key bindings",
+            Action::NextView => "switch to the next view",
+            Action::Devices => "choose the output device",
+            Action::Stations => "tune in to a radio station",
+            Action::TogglePause => "pause or resume",
+            Action::Skip => "skip to the next track",
+            Action::ClearQueue => "clear the queue",
+            Action::Slower => "slow down",
+            Action::Faster => "speed up",
+            Action::ResetSpeed => "play at normal speed",
+            Action::TogglePitch => "keep or resample pitch when changing speed",
+            Action::LoopStart => "set loop start here",
+            Action::LoopEnd => "set loop end here",
+            Action::ClearLoop => "clear the loop",
+            Action::ToggleAutoDj => "keep the queue filled, or stop",
+            Action::NextDjStrategy => "choose queued tracks another way",
+            Action::Down => "move down",
+            Action::Up => "move up",
+            Action::PageDown => "move down a page",
+            Action::PageUp => "move up a page",
+            Action::HalfPageDown => "move down half a page",
+            Action::HalfPageUp => "move up half a page",
+            Action::Bottom => "move to the last row",
+            Action::Top => "move to the first row",
+            Action::ScreenTop => "move to the top of the screen",
+            Action::ScreenMiddle => "move to the middle of the screen",
+            Action::ScreenBottom => "move to the bottom of the screen",
+            Action::Center => "scroll the selection to the middle",
+            Action::Enqueue => "queue the chosen tracks",
+            Action::PlayNext => "play the chosen tracks next",
+            Action::Search => "search",
+            Action::Rescan => "rescan the library",
+            Action::EditTags => "edit tags of the chosen tracks",
+            Action::EditAlbumTags => "edit tags of the whole album",
+            Action::Mark => "mark or unmark a track",
+            Action::Visual => "start or end a range of tracks",
+            Action::MarkAll => "mark every track found",
+            Action::ClearMarks => "clear marks",
+            Action::AddToPlaylist => "add the chosen tracks to a playlist",
+            Action::QueuePlaylist => "queue a saved or smart playlist",
+            Action::JumpToPlaying => "select the playing track",
+            Action::JumpToLetter => "select the next title starting with the letter typed",
+            Action::Rate => "rate the chosen tracks with the digit typed, 0 clearing",
+            Action::ToggleFavourite => "mark or unmark the chosen tracks as favourites",
+            Action::NextSort => "sort by the next column",
+            Action::ReverseSort => "reverse the sort order",
+            Action::Decrease => "decrease",
+            Action::Increase => "increase",
+            Action::LowerFrequency => "lower the band frequency",
+            Action::RaiseFrequency => "raise the band frequency",
+            Action::LowerQ => "widen the band",
+            Action::RaiseQ => "narrow the band",
+            Action::NextPreset => "next preset",
+            Action::ResetEqualizer => "reset",
+            Action::ToggleMono => "toggle mono",
+            Action::ToggleLimiter => "toggle the limiter",
+            Action::ToggleEqualizer => "bypass or enable",
+            Action::SaveEqualizer => "save to the configuration",
+            Action::LyricsEarlier => "show lyrics earlier",
+            Action::LyricsLater => "show lyrics later",
+            Action::ResetLyricsOffset => "reset the lyrics offset",
+            Action::PreviousPeriod => "show a shorter period",
+            Action::NextPeriod => "show a longer period",
+            Action::QueueForgotten => "queue tracks not played for a long time",
+            Action::PreviousFeed => "show the feed above",
+            Action::NextFeed => "show the feed below",
+            Action::PlayEpisode => "play the episode, resuming where it stopped",
+            Action::DownloadEpisode => "download the episode",
+            Action::ToggleFinished => "mark the episode finished or not",
+            Action::Subscribe => "subscribe to a feed by its URL",
+            Action::Unsubscribe => "unsubscribe from the feed",
+            Action::RefreshFeeds => "fetch every feed again",
+        }
+    }
+
+    pub fn contexts(self) -> &'static [Context] {
+        match self {
+            Action::Quit
+            | Action::Help
+            | Action::NextView
+            | Action::Devices
+            | Action::Stations
+            | Action::TogglePause
+            | Action::Skip
+            | Action::ClearQueue
+            | Action::Slower
+            | Action::Faster
+            | Action::ResetSpeed
+            | Action::TogglePitch
+            | Action::LoopStart
+            | Action::LoopEnd
+            | Action::ClearLoop
+            | Action::ToggleAutoDj
+            | Action::NextDjStrategy => &[Context::Global],
+            Action::Down
+            | Action::Up
+            | Action::PageDown
+            | Action::PageUp
+            | Action::HalfPageDown
+            | Action::HalfPageUp
+            | Action::Bottom
+            | Action::Top => VIEWS,
+            Action::Rescan
+            | Action::ScreenTop
+            | Action::ScreenMiddle
+            | Action::ScreenBottom
+            | Action::Center => &[Context::Tracks, Context::Report],
+            Action::Enqueue
+            | Action::PlayNext
+            | Action::Search
+            | Action::EditTags
+            | Action::EditAlbumTags
+            | Action::Mark
+            | Action::Visual
+            | Action::MarkAll
+            | Action::ClearMarks
+            | Action::AddToPlaylist
+            | Action::QueuePlaylist
+            | Action::JumpToPlaying
+            | Action::JumpToLetter
+            | Action::Rate
+            | Action::ToggleFavourite
+            | Action::NextSort
+            | Action::ReverseSort => &[Context::Tracks],
+            Action::Decrease
+            | Action::Increase
+            | Action::LowerFrequency
+            | Action::RaiseFrequency
+            | Action::LowerQ
+            | Action::RaiseQ
+            | Action::NextPreset
+            | Action::ResetEqualizer
+            | Action::ToggleMono
+            | Action::ToggleLimiter
+            | Action::ToggleEqualizer
+            | Action::SaveEqualizer => &[Context::Equalizer],
+            Action::LyricsEarlier | Action::LyricsLater | Action::ResetLyricsOffset => {
+                &[Context::Lyrics]
+            }
+            Action::PreviousPeriod | Action::NextPeriod | Action::QueueForgotten => {
+                &[Context::Stats]
+            }
+            Action::PreviousFeed
+            | Action::NextFeed
+            | Action::PlayEpisode
+            | Action::DownloadEpisode
+            | Action::ToggleFinished
+            | Action::Subscribe
+            | Action::Unsubscribe
+            | Action::RefreshFeeds => &[Context::Podcasts],
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! Maps keys, and sequences of keys, to the actions of the interface
+//!
+//! Bindings start from a preset and are replaced action by action from the `[keys]` section of
+//! the configuration. Text entry, such as the search box and the popups, reads keys directly
+
+use std::fmt;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+mod action;
+
+pub use action::{Action, Context};
+
+/// Names of keys without a character of their own, as written in the configuration
+const NAMES: [(&str, KeyCode); 18] = [
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("space", KeyCode::Char(' ')),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    // both would otherwise be read as syntax of the configuration
+    ("comma", KeyCode::Char(',')),
+    ("hash", KeyCode::Char('#')),
+];
+
+const MODIFIERS: [(&str, KeyModifiers); 3] = [
+    ("ctrl", KeyModifiers::CONTROL),
+    ("alt", KeyModifiers::ALT),
+    ("shift", KeyModifiers::SHIFT),
+];
+
+/// Bindings of every action, in preset order; an action may have several
+const DEFAULT: [(Action, &str); 73] = [
+    (Action::Quit, "q"),
+    (Action::Help, "?"),
+    (Action::NextView, "tab"),
+    (Action::Devices, "o"),
+    (Action::Stations, "w"),
+    (Action::TogglePause, "space"),
+    (Action::Skip, ">"),
+    (Action::ClearQueue, "c"),
+    (Action::Slower, "-"),
+    (Action::Faster, "=, +"),
+    (Action::ResetSpeed, "0"),
+    (Action::TogglePitch, "P"),
+    (Action::LoopStart, "a"),
+    (Action::LoopEnd, "b"),
+    (Action::ClearLoop, "x"),
+    (Action::ToggleAutoDj, "A"),
+    (Action::NextDjStrategy, "D"),
+    (Action::Down, "down"),
+    (Action::Up, "up"),
+    (Action::PageDown, "pagedown"),
+    (Action::PageUp, "pageup"),
+    (Action::HalfPageDown, "d"),
+    (Action::HalfPageUp, "u"),
+    (Action::Bottom, "G, end"),
+    (Action::Top, "g g, home"),
+    (Action::ScreenTop, "H"),
+    (Action::ScreenMiddle, "M"),
+    (Action::ScreenBottom, "L"),
+    (Action::Center, "z z"),
+    (Action::Enqueue, "enter"),
+    (Action::PlayNext, "n"),
+    (Action::Search, "/"),
+    (Action::Rescan, "r"),
+    (Action::EditTags, "e"),
+    (Action::EditAlbumTags, "E"),
+    (Action::Mark, "m"),
+    (Action::Visual, "v"),
+    (Action::MarkAll, "*"),
+    (Action::ClearMarks, "esc"),
+    (Action::AddToPlaylist, "p"),
+    (Action::QueuePlaylist, "l"),
+    (Action::JumpToPlaying, "f"),
+    (Action::JumpToLetter, "'"),
+    (Action::Rate, "s"),
+    (Action::ToggleFavourite, "F"),
+    (Action::NextSort, "S"),
+    (Action::ReverseSort, "R"),
+    (Action::Decrease, "left"),
+    (Action::Increase, "right"),
+    (Action::LowerFrequency, "["),
+    (Action::RaiseFrequency, "]"),
+    (Action::LowerQ, "{"),
+    (Action::RaiseQ, "}"),
+    (Action::NextPreset, "p"),
+    (Action::ResetEqualizer, "r"),
+    (Action::ToggleMono, "m"),
+    (Action::ToggleLimiter, "l"),
+    (Action::ToggleEqualizer, "e"),
+    (Action::SaveEqualizer, "s"),
+    (Action::LyricsEarlier, "["),
+    (Action::LyricsLater, "]"),
+    (Action::ResetLyricsOffset, "r"),
+    (Action::PreviousPeriod, "["),
+    (Action::NextPeriod, "]"),
+    (Action::QueueForgotten, "n"),
+    (Action::PreviousFeed, "["),
+    (Action::NextFeed, "]"),
+    (Action::PlayEpisode, "enter"),
+    (Action::DownloadEpisode, "s"),
+    (Action::ToggleFinished, "m"),
+    (Action::Subscribe, "n"),
+    (Action::Unsubscribe, "X"),
+    (Action::RefreshFeeds, "r"),
+];
+
+/// Bindings of the vim preset differing from the default
+const VIM: [(Action, &str); 9] = [
+    (Action::Down, "j, down"),
+    (Action::Up, "k, up"),
+    (Action::PageDown, "ctrl+f, pagedown"),
+    (Action::PageUp, "ctrl+b, pageup"),
+    (Action::HalfPageDown, "ctrl+d"),
+    (Action::HalfPageUp, "ctrl+u"),
+    (Action::Decrease, "h, left"),
+    (Action::Increase, "l, right"),
+    (Action::ToggleLimiter, "L"),
+];
+
+/// A key with the modifiers held, shift being implied by the character where there is one
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+#[derive(Clone)]
+struct Binding {
+    keys: Vec<Key>,
+    action: Action,
+}
+
+#[derive(Clone)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+    /// Actions bound by the configuration, which a preset leaves alone
+    custom: Vec<Action>,
+}
+
+/// Outcome of looking up the keys pressed so far
+pub enum Lookup {
+    /// The keys are bound to nothing, nor begin any sequence
+    None,
+    /// The keys begin a longer sequence, and are bound to the action given where there is one
+    Partial(Option<Action>),
+    Exact(Action),
+}
+
+impl Key {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            code => code,
+        };
+        if let KeyCode::Char(_) | KeyCode::BackTab = code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl std::str::FromStr for Key {
+    type Err = String;
+
+    /// Parses a key such as `x`, `G`, `space`, `f5` or `ctrl+d`
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        while let Some((m, tail)) = rest.split_once('+')
+            && !tail.is_empty()
+            && let Some((_, m)) = MODIFIERS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(m))
+        {
+            modifiers |= *m;
+            rest = tail;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match NAMES
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(rest))
+            {
+                Some((_, code)) => *code,
+                None => match rest
+                    .strip_prefix(['f', 'F'])
+                    .and_then(|n| n.parse().ok())
+                    .filter(|n| (1..=12).contains(n))
+                {
+                    Some(n) => KeyCode::F(n),
+                    None => return Err(format!("unknown key `{s}`")),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, m) in MODIFIERS {
+            if self.modifiers.contains(m) {
+                write!(f, "{name}+")?;
+            }
+        }
+        match NAMES.iter().find(|(_, code)| *code == self.code) {
+            Some((name, _)) => f.write_str(name),
+            None => match self.code {
+                KeyCode::Char(c) => write!(f, "{c}"),
+                KeyCode::F(n) => write!(f, "f{n}"),
+                code => write!(f, "{code:?}"),
+            },
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self {
+            bindings: Vec::new(),
+            custom: Vec::new(),
+        };
+        keymap.apply(&DEFAULT);
+        keymap
+    }
+}
+
+impl Keymap {
+    /// Replaces the bindings of every action not bound by the configuration with those of a
+    /// preset
+    pub fn apply_preset(&mut self, name: &str) -> Result<(), String> {
+        let overrides: &[(Action, &str)] = match name {
+            "default" => &[],
+            "vim" => &VIM,
+            _ => return Err(format!("unknown key preset `{name}`")),
+        };
+        self.bindings.retain(|b| self.custom.contains(&b.action));
+        self.apply(&DEFAULT);
+        self.apply(overrides);
+        Ok(())
+    }
+
+    /// Binds an action by name to a comma separated list of key sequences, each a space
+    /// separated list of keys, replacing its current bindings. An empty list unbinds it
+    pub fn bind(&mut self, name: &str, keys: &str) -> Result<(), String> {
+        let Some(action) = Action::from_name(name) else {
+            return Err(format!("unknown action `{name}`"));
+        };
+        let sequences = parse_sequences(keys)?;
+        self.bindings.retain(|b| b.action != action);
+        self.bindings
+            .extend(sequences.into_iter().map(|keys| Binding { keys, action }));
+        if !self.custom.contains(&action) {
+            self.custom.push(action);
+        }
+        Ok(())
+    }
+
+    /// Finds what the keys pressed so far are bound to within a context. Bindings of the
+    /// context take precedence over global ones
+    pub fn lookup(&self, context: Context, keys: &[Key]) -> Lookup {
+        let mut exact = None;
+        let mut longer = false;
+        for b in self
+            .bindings
+            .iter()
+            .filter(|b| b.action.contexts().contains(&context))
+            .chain(
+                self.bindings
+                    .iter()
+                    .filter(|b| b.action.contexts() == [Context::Global]),
+            )
+        {
+            if b.keys == keys {
+                exact = exact.or(Some(b.action));
+            } else if b.keys.starts_with(keys) {
+                longer = true;
+            }
+        }
+        match (exact, longer) {
+            (_, true) => Lookup::Partial(exact),
+            (Some(a), false) => Lookup::Exact(a),
+            (None, false) => Lookup::None,
+        }
+    }
+
+    /// Every sequence bound to an action, as written in the configuration
+    pub fn sequences(&self, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|b| b.action == action)
+            .map(|b| {
+                let keys: Vec<String> = b.keys.iter().map(Key::to_string).collect();
+                keys.join(" ")
+            })
+            .collect()
+    }
+
+    /// First sequence bound to an action, for hints
+    pub fn hint(&self, action: Action) -> String {
+        self.sequences(action)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| String::from("unbound"))
+    }
+
+    fn apply(&mut self, preset: &[(Action, &str)]) {
+        for (action, keys) in preset {
+            if self.custom.contains(action) {
+                continue;
+            }
+            let sequences = parse_sequences(keys).expect("preset bindings parse");
+            self.bindings.retain(|b| b.action != *action);
+            self.bindings
+                .extend(sequences.into_iter().map(|keys| Binding {
+                    keys,
+                    action: *action,
+                }));
+        }
+    }
+}
+
+fn parse_sequences(s: &str) -> Result<Vec<Vec<Key>>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|seq| !seq.is_empty())
+        .map(|seq| seq.split_whitespace().map(str::parse).collect())
+        .collect()
+}
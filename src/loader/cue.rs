@@ -0,0 +1,296 @@
+//! CUE sheets, marking out the tracks of an album ripped to a single file
+//!
+//! A sheet is taken from the `CUESHEET` tag of the file, or else from a `.cue` file beside it
+//! that names it. Times in a sheet are minutes, seconds and frames of 1/75 second
+
+use std::{fs, path::Path, time::Duration};
+
+use symphonia::core::meta::Tag;
+
+/// Frames in a second of CD audio, the unit of times in a sheet
+const FRAMES: u64 = 75;
+
+/// Part of an audio file played as a track of its own
+pub struct Cue {
+    /// The audio file the track is part of
+    pub file: String,
+    pub start: Duration,
+    /// Where the next track begins, or `None` for the last, which runs to the end of the file
+    pub end: Option<Duration>,
+}
+
+/// Album details and tracks of a sheet
+#[derive(Default)]
+pub struct Sheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub date: Option<String>,
+    pub genre: Option<String>,
+    pub tracks: Vec<Track>,
+}
+
+pub struct Track {
+    /// File named by the last `FILE` command before the track
+    pub file: Option<String>,
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// Start of `INDEX 01`, leaving any pregap to the track before
+    pub start: Duration,
+}
+
+/// Whether `path` is a CUE sheet, judged by its extension
+pub fn is_sheet(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("cue"))
+}
+
+/// The sheet splitting the file at `path` into tracks, where it has one of at least two
+/// tracks. An embedded sheet is preferred over a file beside the audio
+pub fn find(path: &Path, tags: &[Tag]) -> Option<Sheet> {
+    let embedded = tags
+        .iter()
+        .find(|t| t.key.eq_ignore_ascii_case("cuesheet"))
+        .map(|t| parse(&t.value.to_string()));
+    let sheet = embedded.or_else(|| beside(path))?;
+    (sheet.tracks.len() > 1).then_some(sheet)
+}
+
+/// Reads the sheets in the directory of `path` for the first naming it, keeping only the
+/// tracks of that file
+fn beside(path: &Path) -> Option<Sheet> {
+    let name = path.file_name()?;
+    let entries = path.parent()?.read_dir().ok()?;
+    entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| is_sheet(p))
+        .find_map(|p| {
+            let mut sheet = parse(&read_text(&fs::read(p).ok()?));
+            sheet.tracks.retain(|t| {
+                t.file
+                    .as_deref()
+                    .and_then(|f| Path::new(f).file_name())
+                    .is_some_and(|f| f == name)
+            });
+            (!sheet.tracks.is_empty()).then_some(sheet)
+        })
+}
+
+/// Text of a sheet, which older rippers write in Latin-1 rather than UTF-8
+fn read_text(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_owned(),
+        Err(_) => bytes.iter().map(|b| char::from(*b)).collect(),
+    }
+}
+
+pub fn parse(text: &str) -> Sheet {
+    let mut sheet = Sheet::default();
+    let mut file = None;
+    // whether `TITLE` and `PERFORMER` fall to the last track rather than the album
+    let mut in_track = false;
+    // tracks are kept only once `INDEX 01` gives their start
+    let mut started = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let last = sheet.tracks.last_mut().filter(|_| in_track);
+        match (command.to_ascii_uppercase().as_str(), last) {
+            ("FILE", _) => {
+                // the file type follows the name
+                let name = rest
+                    .rsplit_once(char::is_whitespace)
+                    .map_or(rest, |(f, _)| f);
+                file = Some(unquote(name));
+                in_track = false;
+            }
+            ("TRACK", _) => {
+                let Some(number) = rest.split_whitespace().next().and_then(|n| n.parse().ok())
+                else {
+                    in_track = false;
+                    continue;
+                };
+                sheet.tracks.push(Track {
+                    file: file.clone(),
+                    number,
+                    title: None,
+                    performer: None,
+                    start: Duration::ZERO,
+                });
+                started.push(false);
+                in_track = true;
+            }
+            ("TITLE", Some(t)) => t.title = Some(unquote(rest)),
+            ("PERFORMER", Some(t)) => t.performer = Some(unquote(rest)),
+            ("TITLE", None) => sheet.title = Some(unquote(rest)),
+            ("PERFORMER", None) => sheet.performer = Some(unquote(rest)),
+            ("INDEX", Some(t)) => {
+                let mut parts = rest.split_whitespace();
+                if parts.next().and_then(|n| n.parse::<u32>().ok()) == Some(1)
+                    && let Some(start) = parts.next().and_then(time)
+                {
+                    t.start = start;
+                    if let Some(s) = started.last_mut() {
+                        *s = true;
+                    }
+                }
+            }
+            ("REM", _) => match rest.split_once(char::is_whitespace) {
+                Some((key, value)) if key.eq_ignore_ascii_case("DATE") => {
+                    sheet.date = Some(unquote(value))
+                }
+                Some((key, value)) if key.eq_ignore_ascii_case("GENRE") => {
+                    sheet.genre = Some(unquote(value))
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+    let mut started = started.into_iter();
+    sheet.tracks.retain(|_| started.next() == Some(true));
+    sheet
+}
+
+/// Reads a time given as `mm:ss:ff`
+fn time(text: &str) -> Option<Duration> {
+    let mut parts = text.split(':').map(|p| p.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    let frames = (minutes * 60 + seconds) * FRAMES + frames;
+    Some(Duration::from_nanos(frames * 1_000_000_000 / FRAMES))
+}
+
+fn unquote(text: &str) -> String {
+    let text = text.trim();
+    text.strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text)
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::AudioTrack;
+
+    const SHEET: &str = r#"REM GENRE "Post Rock"
+REM DATE 1997
+PERFORMER "The Band"
+TITLE "The Album"
+FILE "The Album.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "Opening"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Guest Spot"
+    PERFORMER "A Guest"
+    INDEX 00 03:58:50
+    INDEX 01 04:00:00
+  TRACK 03 AUDIO
+    TITLE "Hidden"
+    INDEX 00 07:00:00
+FILE second.flac WAVE
+  TRACK 04 AUDIO
+    INDEX 01 00:01:74
+"#;
+
+    #[test]
+    fn album_and_tracks() {
+        let sheet = parse(SHEET);
+        assert_eq!(sheet.title.as_deref(), Some("The Album"));
+        assert_eq!(sheet.performer.as_deref(), Some("The Band"));
+        assert_eq!(sheet.date.as_deref(), Some("1997"));
+        assert_eq!(sheet.genre.as_deref(), Some("Post Rock"));
+
+        // the third track never gives where it starts, so it is dropped
+        let numbers: Vec<u32> = sheet.tracks.iter().map(|t| t.number).collect();
+        assert_eq!(numbers, [1, 2, 4]);
+        let files: Vec<_> = sheet.tracks.iter().map(|t| t.file.as_deref()).collect();
+        assert_eq!(
+            files,
+            [
+                Some("The Album.flac"),
+                Some("The Album.flac"),
+                Some("second.flac")
+            ]
+        );
+    }
+
+    #[test]
+    fn track_details_stay_with_the_track() {
+        let sheet = parse(SHEET);
+        let performers: Vec<_> = sheet
+            .tracks
+            .iter()
+            .map(|t| t.performer.as_deref())
+            .collect();
+        assert_eq!(performers, [None, Some("A Guest"), None]);
+        let titles: Vec<_> = sheet.tracks.iter().map(|t| t.title.as_deref()).collect();
+        assert_eq!(titles, [Some("Opening"), Some("Guest Spot"), None]);
+    }
+
+    #[test]
+    fn track_performer_overrides_the_album() {
+        let sheet = parse(SHEET);
+        let tracks = AudioTrack::split(Path::new("/music/The Album.flac"), &[], 1, sheet);
+        let artists: Vec<_> = tracks.iter().map(|t| t.artists()).collect();
+        assert_eq!(
+            artists,
+            [Some("The Band"), Some("A Guest"), Some("The Band")]
+        );
+        assert_eq!(tracks[1].path(), "/music/The Album.flac#02");
+        assert_eq!(tracks[1].album(), Some("The Album"));
+        let cue = tracks[1].cue().unwrap();
+        assert_eq!(cue.file, "/music/The Album.flac");
+        assert_eq!(
+            (cue.start, cue.end),
+            (
+                Duration::from_secs(240),
+                Some(tracks[2].cue().unwrap().start)
+            )
+        );
+        assert_eq!(tracks[2].cue().unwrap().end, None);
+    }
+
+    #[test]
+    fn tracks_start_at_index_one() {
+        let starts: Vec<_> = parse(SHEET).tracks.iter().map(|t| t.start).collect();
+        assert_eq!(
+            starts,
+            [
+                Duration::ZERO,
+                Duration::from_secs(240),
+                Duration::from_nanos(1_986_666_666)
+            ]
+        );
+    }
+
+    #[test]
+    fn times_count_frames_of_a_75th() {
+        assert_eq!(time("00:00:00"), Some(Duration::ZERO));
+        assert_eq!(time("00:00:75"), Some(Duration::from_secs(1)));
+        assert_eq!(time("01:02:15"), Some(Duration::from_millis(62_200)));
+        assert_eq!(
+            time("99:59:74"),
+            Some(Duration::from_nanos(5_999_986_666_666))
+        );
+        assert_eq!(time("01:02"), None);
+        assert_eq!(time("aa:00:00"), None);
+    }
+
+    #[test]
+    fn latin1_is_read() {
+        let bytes = b"PERFORMER \"Bj\xf6rk\"\r\nTITLE \"Caf\xe9\"\r\n";
+        let sheet = parse(&read_text(bytes));
+        assert_eq!(sheet.performer.as_deref(), Some("Björk"));
+        assert_eq!(sheet.title.as_deref(), Some("Café"));
+
+        let utf8 = "\u{feff}TITLE \"Café\"".as_bytes();
+        assert_eq!(parse(&read_text(utf8)).title.as_deref(), Some("Café"));
+    }
+}
@@ -6,20 +6,43 @@
 
 use std::{
     //collections::HashMap,
-    fs::File,
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    os::unix::fs::MetadataExt,
     path::{Path, PathBuf},
     rc::Rc,
 };
 
 use symphonia::{
     core::{
-        errors::Error as SymphoniaError, formats::FormatOptions, io::MediaSourceStream,
-        meta::MetadataOptions, meta::Tag, probe::Hint,
+        errors::Error as SymphoniaError,
+        formats::FormatOptions,
+        io::MediaSourceStream,
+        meta::{MetadataOptions, StandardVisualKey, Tag},
+        probe::{Hint, ProbeResult},
     },
     default::get_probe,
 };
 
-pub type LibraryCollection = Box<[Rc<AudioTrack>]>; // reference to slice? but it's heap, so.. doesn't matter?
+mod cue;
+mod report;
+mod scan;
+
+pub use cue::{Cue, is_sheet};
+pub use report::{Diagnostic, Problem, Report};
+pub use scan::{ScanEvent, scan, scan_all};
+
+pub type LibraryCollection = Vec<Rc<AudioTrack>>;
+
+const SUPPORTED_EXTENSIONS: [&str; 1] = ["flac"];
+
+/// Bytes of audio hashed to identify a file
+const FINGERPRINT_LEN: u64 = 16 * 1024;
+
+/// Bytes at the end of a file left out of its fingerprint, where tags may follow the audio.
+/// Audio shorter than this is hashed whole
+const TRAILER_LEN: u64 = 4 * 1024;
 
 /// Audio track with extended metadata present
 #[derive(Default)]
@@ -28,7 +51,7 @@ pub struct FullAudioTrack {
     pub album: String,
     /// Track artists
     pub artists: String,
-    /// File path
+    /// File path, with the track number appended for a track of a CUE sheet
     pub path: String,
     /// Track title
     pub title: String,
@@ -36,10 +59,15 @@ pub struct FullAudioTrack {
     // artist: &Artist,
     /// Date of track release
     pub date: String,
+    pub genre: String,
+    /// Identity of the audio, kept through moves and retagging
+    pub identity: u64,
     /// Track lyrics
     lyrics: String, // add visuals ?
-                    // duration
-                    // track num ?
+    // duration
+    // track num ?
+    /// Part of the file played, where the track is one of a CUE sheet
+    pub cue: Option<Box<Cue>>,
 }
 
 /// Audio track with no detected metadata
@@ -48,6 +76,8 @@ pub struct LimitedAudioTrack {
     pub path: String,
     /// Track title, assumed from the file name
     pub title: String,
+    /// Identity of the audio, kept through moves and retagging
+    pub identity: u64,
 }
 
 /// Base unit representing an audio file with accompanying metadata for playback
@@ -61,15 +91,17 @@ pub enum AudioTrack {
     Limited(LimitedAudioTrack),
 }
 
-pub struct AudioLibrary {
-    pub tracks: LibraryCollection,
-}
-
 impl AudioTrack {
-    fn new_full(path: &Path, metadata: &[Tag]) -> Self {
-        let mut track: FullAudioTrack = Default::default();
+    fn new_full(path: &Path, metadata: &[Tag], identity: u64) -> Self {
+        AudioTrack::Extended(Self::tagged(path, metadata, identity))
+    }
 
-        track.path = path.to_string_lossy().into_owned();
+    fn tagged(path: &Path, metadata: &[Tag], identity: u64) -> FullAudioTrack {
+        let mut track = FullAudioTrack {
+            path: path.to_string_lossy().into_owned(),
+            identity,
+            ..Default::default()
+        };
 
         for tag in metadata.iter().filter(|t| t.is_known()) {
             if let Some(key) = tag.std_key {
@@ -79,16 +111,63 @@ impl AudioTrack {
                     "Artist" => track.artists = tag.value.to_string(),
                     "TrackTitle" => track.title = tag.value.to_string(),
                     "Date" => track.date = tag.value.to_string(),
+                    "Genre" => track.genre = tag.value.to_string(),
                     "Lyrics" => track.lyrics = tag.value.to_string(),
                     _ => (),
                 }
             }
         }
-        AudioTrack::Extended(track)
+        track
     }
 
-    fn new_limited(path: &Path) -> Self {
+    /// Tracks of a file split by a CUE sheet, each ending where the next begins. Details the
+    /// sheet leaves out are taken from the tags of the file
+    fn split(path: &Path, metadata: &[Tag], identity: u64, sheet: cue::Sheet) -> Vec<Self> {
+        let file = path.to_string_lossy();
+        let ends = sheet
+            .tracks
+            .iter()
+            .skip(1)
+            .map(|t| Some(t.start))
+            .chain([None]);
+        sheet
+            .tracks
+            .iter()
+            .zip(ends)
+            .map(|(t, end)| {
+                let whole = Self::tagged(path, metadata, identity);
+                AudioTrack::Extended(FullAudioTrack {
+                    album: sheet.title.clone().unwrap_or(whole.album),
+                    artists: t
+                        .performer
+                        .clone()
+                        .or_else(|| sheet.performer.clone())
+                        .unwrap_or(whole.artists),
+                    path: format!("{file}#{:02}", t.number),
+                    title: t
+                        .title
+                        .clone()
+                        .unwrap_or_else(|| format!("Track {:02}", t.number)),
+                    date: sheet.date.clone().unwrap_or(whole.date),
+                    genre: sheet.genre.clone().unwrap_or(whole.genre),
+                    identity: fnv(
+                        &[identity.to_le_bytes(), u64::from(t.number).to_le_bytes()].concat()
+                    ),
+                    // lyrics of the file are those of the whole album
+                    lyrics: String::new(),
+                    cue: Some(Box::new(Cue {
+                        file: file.clone().into_owned(),
+                        start: t.start,
+                        end,
+                    })),
+                })
+            })
+            .collect()
+    }
+
+    fn new_limited(path: &Path, identity: u64) -> Self {
         AudioTrack::Limited(LimitedAudioTrack {
+            identity,
             path: path.to_string_lossy().into_owned(),
             title: path
                 .file_name()
@@ -97,101 +176,292 @@ impl AudioTrack {
                 .into_owned(),
         })
     }
+
+    /// A radio station, played from the stream at `url`
+    pub fn station(name: &str, url: &str) -> Self {
+        AudioTrack::Limited(LimitedAudioTrack {
+            path: url.to_owned(),
+            title: name.to_owned(),
+            identity: fnv(url.as_bytes()),
+        })
+    }
+
+    /// A podcast episode, played from the file it was downloaded to or else from its URL
+    pub fn episode(title: &str, path: &str) -> Self {
+        AudioTrack::Limited(LimitedAudioTrack {
+            path: path.to_owned(),
+            title: title.to_owned(),
+            identity: fnv(path.as_bytes()),
+        })
+    }
+
+    /// Path identifying the track: that of its audio file, with the track number appended
+    /// for a track of a CUE sheet
+    pub fn path(&self) -> &str {
+        match self {
+            AudioTrack::Extended(a) => &a.path,
+            AudioTrack::Limited(a) => &a.path,
+        }
+    }
+
+    /// Path of the underlying audio file, shared by every track of a CUE sheet
+    pub fn file(&self) -> &str {
+        match self.cue() {
+            Some(c) => &c.file,
+            None => self.path(),
+        }
+    }
+
+    /// Part of the file played, where the track is one of a CUE sheet
+    pub fn cue(&self) -> Option<&Cue> {
+        match self {
+            AudioTrack::Extended(a) => a.cue.as_deref(),
+            AudioTrack::Limited(_) => None,
+        }
+    }
+
+    /// Title of the track, or the file name it was assumed from
+    pub fn title(&self) -> &str {
+        match self {
+            AudioTrack::Extended(a) => &a.title,
+            AudioTrack::Limited(a) => &a.title,
+        }
+    }
+
+    /// Identity of the audio, the same for a file moved or retagged
+    pub fn identity(&self) -> u64 {
+        match self {
+            AudioTrack::Extended(a) => a.identity,
+            AudioTrack::Limited(a) => a.identity,
+        }
+    }
+
+    /// Lyrics embedded in the track's tags, where present
+    pub fn lyrics(&self) -> Option<&str> {
+        match self {
+            AudioTrack::Extended(a) if !a.lyrics.is_empty() => Some(&a.lyrics),
+            _ => None,
+        }
+    }
+
+    /// Artists of the track, where tagged
+    pub fn artists(&self) -> Option<&str> {
+        match self {
+            AudioTrack::Extended(a) if !a.artists.is_empty() => Some(&a.artists),
+            _ => None,
+        }
+    }
+
+    /// Album the track belongs to, where one is tagged
+    pub fn album(&self) -> Option<&str> {
+        match self {
+            AudioTrack::Extended(a) if !a.album.is_empty() => Some(&a.album),
+            _ => None,
+        }
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        match self {
+            AudioTrack::Extended(a) if !a.genre.is_empty() => Some(&a.genre),
+            _ => None,
+        }
+    }
+
+    /// Year of release, taken from the start of the date tag
+    pub fn year(&self) -> Option<u32> {
+        match self {
+            AudioTrack::Extended(a) => a.date.get(..4)?.parse().ok(),
+            AudioTrack::Limited(_) => None,
+        }
+    }
 }
 
-impl AudioLibrary {
-    /// Given a directory, take a vector of resulting `AudioTrack`s and group them by album and artist
-    pub fn from_directory(directory: PathBuf) -> Self {
-        let mut tracks: Vec<Rc<AudioTrack>> = Vec::with_capacity(256);
-
-        let supported_extensions: [&str; 1] = ["flac"];
-
-        // base capacity is arbitrary in size
-        // don't need to optimise too greatly (pay this once), but don't want to spam realloc either
-        let mut dirs: Vec<PathBuf> = Vec::with_capacity(256);
-        dirs.push(directory);
-
-        // iterate through given directory
-        // if item is directory, iterate over its children, pushing all to vec
-        // if item is file, check extension is supported then pass off to build track
-        loop {
-            match dirs.pop() {
-                Some(path) => {
-                    if path.is_dir() {
-                        for entry in path.read_dir().unwrap() {
-                            match entry {
-                                Ok(i) => {
-                                    dirs.push(i.path());
-                                }
-                                Err(e) => eprintln!("{e}"),
-                            }
-                        }
-                    } else if path.is_file() {
-                        // a rather unscientific method for determining file type
-                        // prefer a more robust solution, but this has advantage of requiring handle
-                        match path.extension() {
-                            Some(p) => {
-                                if supported_extensions.contains(&p.to_str().expect("ext to str")) {
-                                    match read_audio_file(path.as_path()) {
-                                        Ok(ok) => tracks.push(Rc::new(ok)),
-                                        Err(e) => eprintln!("{e}"),
-                                    }
-                                }
-                            }
-                            None => continue,
-                        }
-                    }
+/// Whether the file at `path` is of a format the library holds
+pub fn is_supported(path: &Path) -> bool {
+    // a rather unscientific method for determining file type
+    // prefer a more robust solution, but this has advantage of requiring handle
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| SUPPORTED_EXTENSIONS.contains(&e))
+}
+
+/// Every supported audio file beneath `directory`, or `directory` itself where it is one
+pub fn audio_files(directory: PathBuf, follow_symlinks: bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    walk(directory, follow_symlinks, |f| files.push(f), |_| ());
+    files
+}
+
+/// Calls `found` with each supported audio file beneath `directory`, and `problem` with each
+/// directory that cannot be listed. Symbolic links are followed only where `follow_symlinks`
+/// is set, and each directory is entered once, so links cannot loop
+pub fn walk(
+    directory: PathBuf,
+    follow_symlinks: bool,
+    mut found: impl FnMut(PathBuf),
+    mut problem: impl FnMut(Diagnostic),
+) {
+    let mut visited = HashSet::new();
+
+    // base capacity is arbitrary in size
+    // don't need to optimise too greatly (pay this once), but don't want to spam realloc either
+    let mut dirs: Vec<PathBuf> = Vec::with_capacity(256);
+    dirs.push(directory);
+
+    // iterate through given directory
+    // if item is directory, iterate over its children, pushing all to vec
+    // if item is file, check extension is supported then pass off to build track
+    while let Some(path) = dirs.pop() {
+        // a path may vanish while being walked, as when watching the library
+        let Ok(meta) = fs::metadata(&path) else {
+            continue;
+        };
+        if meta.is_dir() {
+            if !visited.insert((meta.dev(), meta.ino())) {
+                continue;
+            }
+            let entries = match path.read_dir() {
+                Ok(entries) => entries,
+                Err(e) => {
+                    problem(Diagnostic::new(path, Problem::UnreadableDirectory(e)));
+                    continue;
+                }
+            };
+            for entry in entries.flatten() {
+                if follow_symlinks || !entry.file_type().is_ok_and(|t| t.is_symlink()) {
+                    dirs.push(entry.path());
                 }
-                None => break,
             }
+        } else if meta.is_file() && is_supported(&path) {
+            found(path);
         }
+    }
+}
+
+/// Takes a string slice representing path to an audio file as input, then reads the file and
+/// attempts to create representative Tracks from the audio file: one, or one for each
+/// track of a CUE sheet splitting it
+pub fn read_audio_file(path: &Path) -> Result<Vec<AudioTrack>, SymphoniaError> {
+    let mut probe = probe_file(path)?;
+    let identity = fingerprint(path)?;
 
-        Self {
-            tracks: tracks.into_boxed_slice(),
+    let tags = match probe.format.metadata().current() {
+        Some(meta) => meta.tags().to_vec(),
+        None => probe
+            .metadata
+            .get()
+            .as_ref()
+            .and_then(|m| m.current())
+            .map(|m| m.tags().to_vec())
+            .unwrap_or_default(),
+    };
+    if let Some(sheet) = cue::find(path, &tags) {
+        return Ok(AudioTrack::split(path, &tags, identity, sheet));
+    }
+    Ok(vec![match tags.is_empty() {
+        true => AudioTrack::new_limited(path, identity),
+        false => AudioTrack::new_full(path, &tags, identity),
+    }])
+}
+
+/// Hashes the first audio of a file, past the tags at its start and short of a trailer after
+/// it, so that neither moving nor retagging the file changes it. Little enough is read to do
+/// so for every file scanned
+fn fingerprint(path: &Path) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = audio_start(&mut file)?.min(len);
+    let audio_len = match len - start {
+        n if n > TRAILER_LEN => (n - TRAILER_LEN).min(FINGERPRINT_LEN),
+        n => n,
+    };
+    file.seek(SeekFrom::Start(start))?;
+    let mut audio = Vec::with_capacity(audio_len as usize);
+    file.take(audio_len).read_to_end(&mut audio)?;
+    Ok(fnv(&audio))
+}
+
+/// Offset of the first audio frame: past an ID3v2 tag, then past the metadata blocks of FLAC
+fn audio_start(file: &mut File) -> io::Result<u64> {
+    let mut start = 0;
+    let mut header = [0; 10];
+    if read_at(file, start, &mut header)? && &header[..3] == b"ID3" {
+        let size = header[6..10]
+            .iter()
+            .fold(0, |n, b| n << 7 | u64::from(b & 0x7f));
+        // a footer repeats the header after the tag
+        let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+        start += 10 + size + footer;
+    }
+    let mut marker = [0; 4];
+    if !read_at(file, start, &mut marker)? || &marker != b"fLaC" {
+        return Ok(start);
+    }
+    start += 4;
+    // each block has a header of its type, the last flagged, and a 24 bit length
+    let mut block = [0; 4];
+    while read_at(file, start, &mut block)? {
+        start += 4 + u64::from(u32::from_be_bytes([0, block[1], block[2], block[3]]));
+        if block[0] & 0x80 != 0 {
+            break;
         }
     }
+    Ok(start)
 }
 
-/// Takes a string slice representing path to an audio file as input, then reads the file and
-/// attempts to convert create a representative Track from the audio file
-fn read_audio_file(path: &Path) -> Result<AudioTrack, SymphoniaError> {
-    let source = Box::new(File::open(path).expect("box file error"));
+/// Fills `buf` from `offset`, or returns false where the file ends first
+fn read_at(file: &mut File, offset: u64, buf: &mut [u8]) -> io::Result<bool> {
+    file.seek(SeekFrom::Start(offset))?;
+    match file.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// FNV-1a, whose values stay the same from one build to the next
+fn fnv(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Reads the picture embedded in an audio file, preferring the front cover where several are
+/// present. Pictures are read on demand rather than kept on every track, as they are large
+pub fn read_visual(path: &Path) -> Option<Box<[u8]>> {
+    let mut probe = probe_file(path).ok()?;
+
+    let mut visuals = match probe.format.metadata().current() {
+        Some(meta) if !meta.visuals().is_empty() => meta.visuals().to_vec(),
+        _ => probe
+            .metadata
+            .get()
+            .as_ref()
+            .and_then(|m| m.current())
+            .map(|m| m.visuals().to_vec())
+            .unwrap_or_default(),
+    };
+
+    let front = visuals
+        .iter()
+        .position(|v| v.usage == Some(StandardVisualKey::FrontCover))
+        .unwrap_or(0);
+    (front < visuals.len()).then(|| visuals.swap_remove(front).data)
+}
+
+fn probe_file(path: &Path) -> Result<ProbeResult, SymphoniaError> {
+    let source = Box::new(File::open(path)?);
     let mss = MediaSourceStream::new(source, Default::default());
 
     let mut hint = Hint::new();
-    hint.with_extension(
-        Path::new(path)
-            .extension()
-            .expect("failed to extract file extension")
-            .to_str()
-            .expect("extension to str conversion failed"),
-    );
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
 
     let meta_opts: MetadataOptions = Default::default();
     let fmt_opts: FormatOptions = Default::default();
 
-    let mut probe = match get_probe().format(&hint, mss, &fmt_opts, &meta_opts) {
-        Ok(p) => p,
-        Err(e) => return Err(e),
-    };
-
-    if let Some(meta) = probe.format.metadata().current() {
-        let tags = meta.tags();
-        if !tags.is_empty() {
-            Ok(AudioTrack::new_full(path, tags))
-        } else {
-            Ok(AudioTrack::new_limited(path))
-        }
-    } else if let Some(meta) = probe.metadata.get().as_ref().and_then(|m| m.current()) {
-        let tags = meta.tags();
-        if !tags.is_empty() {
-            Ok(AudioTrack::new_full(path, tags))
-        } else {
-            Ok(AudioTrack::new_limited(path))
-        }
-    } else {
-        Ok(AudioTrack::new_limited(path))
-    }
+    get_probe().format(&hint, mss, &fmt_opts, &meta_opts)
 }
 
 // fn _check_cache() {
@@ -201,3 +471,66 @@ fn read_audio_file(path: &Path) -> Result<AudioTrack, SymphoniaError> {
 // fn _cache_library() {
 //     todo!("cache current library");
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A FLAC file whose metadata blocks hold `comment`, followed by `audio`
+    fn flac(id3: bool, comment: &[u8], audio: &[u8]) -> Vec<u8> {
+        let mut file = Vec::new();
+        if id3 {
+            file.extend(b"ID3\x04\0\0\0\0\x01\x00");
+            file.extend([0; 128]);
+        }
+        file.extend(b"fLaC");
+        // stream info, then the comment as the last block
+        file.extend([0, 0, 0, 34]);
+        file.extend([7; 34]);
+        file.extend([0x84, 0, 0, comment.len() as u8]);
+        file.extend(comment);
+        file.extend(audio);
+        file
+    }
+
+    fn identity(name: &str, bytes: &[u8]) -> u64 {
+        let path = std::env::temp_dir().join(format!("mplay-fingerprint-{name}.flac"));
+        fs::write(&path, bytes).unwrap();
+        let identity = fingerprint(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        identity
+    }
+
+    #[test]
+    fn retagging_keeps_the_identity() {
+        let audio: Vec<u8> = (0..2000).map(|i| (i % 251) as u8).collect();
+        let before = identity("before", &flac(false, b"TITLE=Old", &audio));
+        let after = identity("after", &flac(false, b"TITLE=A new title", &audio));
+        let id3 = identity("id3", &flac(true, b"TITLE=Old", &audio));
+        assert_eq!(before, after);
+        assert_eq!(before, id3);
+        assert_eq!(before, fnv(&audio));
+    }
+
+    #[test]
+    fn other_audio_differs() {
+        let audio: Vec<u8> = (0..2000).map(|i| (i % 251) as u8).collect();
+        let mut other = audio.clone();
+        other[0] ^= 1;
+        assert_ne!(
+            identity("one", &flac(false, b"", &audio)),
+            identity("other", &flac(false, b"", &other))
+        );
+    }
+
+    #[test]
+    fn trailer_is_left_out_of_long_audio() {
+        let audio = vec![3; (FINGERPRINT_LEN + TRAILER_LEN) as usize];
+        let mut tagged = audio.clone();
+        tagged.extend(b"TAG trailer");
+        assert_eq!(
+            identity("untagged", &flac(false, b"", &audio)),
+            identity("tagged", &flac(false, b"", &tagged))
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Problems met while scanning the library, collected for review rather than printed over the
+//! interface
+
+use std::{collections::HashMap, fmt, io, path::PathBuf};
+
+use symphonia::core::errors::Error as SymphoniaError;
+
+use super::AudioTrack;
+
+pub enum Problem {
+    /// A directory could not be listed, so nothing beneath it was scanned
+    UnreadableDirectory(io::Error),
+    /// A file could not be opened for want of permission
+    PermissionDenied,
+    /// A file could not be read as audio
+    ProbeFailed(SymphoniaError),
+    /// A file was read but carries no tags, so is listed by its file name
+    Untagged,
+    /// A file has the same title, artists and album as the file given
+    Duplicate(PathBuf),
+}
+
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub problem: Problem,
+}
+
+/// Every problem found by a scan, ordered by kind then path once complete
+#[derive(Default)]
+pub struct Report {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Problem {
+    /// Name of the kind of problem, as a heading of many
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Problem::UnreadableDirectory(_) => "unreadable directories",
+            Problem::PermissionDenied => "permission denied",
+            Problem::ProbeFailed(_) => "unreadable files",
+            Problem::Untagged => "untagged files",
+            Problem::Duplicate(_) => "duplicates",
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Problem::UnreadableDirectory(_) => 0,
+            Problem::PermissionDenied => 1,
+            Problem::ProbeFailed(_) => 2,
+            Problem::Untagged => 3,
+            Problem::Duplicate(_) => 4,
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn new(path: PathBuf, problem: Problem) -> Self {
+        Self { path, problem }
+    }
+
+    /// Diagnoses a file that failed to read
+    pub fn unreadable(path: PathBuf, error: SymphoniaError) -> Self {
+        let problem = match error {
+            SymphoniaError::IoError(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                Problem::PermissionDenied
+            }
+            e => Problem::ProbeFailed(e),
+        };
+        Self { path, problem }
+    }
+}
+
+impl Report {
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Marks every tagged track sharing its title, artists and album with an earlier one, then
+    /// orders the report
+    pub fn complete<'a>(&mut self, tracks: impl IntoIterator<Item = &'a AudioTrack>) {
+        let mut seen: HashMap<(String, String, String), &str> = HashMap::new();
+        for track in tracks {
+            let AudioTrack::Extended(t) = track else {
+                continue;
+            };
+            if t.title.is_empty() {
+                continue;
+            }
+            let key = (
+                t.title.to_lowercase(),
+                t.artists.to_lowercase(),
+                t.album.to_lowercase(),
+            );
+            match seen.get(&key) {
+                Some(first) => self.push(Diagnostic::new(
+                    PathBuf::from(&t.path),
+                    Problem::Duplicate(PathBuf::from(first)),
+                )),
+                None => {
+                    seen.insert(key, &t.path);
+                }
+            }
+        }
+        self.diagnostics
+            .sort_by(|a, b| (a.problem.rank(), &a.path).cmp(&(b.problem.rank(), &b.path)));
+    }
+
+    /// Number of problems of each kind present, in report order
+    pub fn counts(&self) -> Vec<(&'static str, usize)> {
+        let mut counts: Vec<(&'static str, usize)> = Vec::new();
+        for d in &self.diagnostics {
+            match counts.last_mut() {
+                Some((kind, n)) if *kind == d.problem.kind() => *n += 1,
+                _ => counts.push((d.problem.kind(), 1)),
+            }
+        }
+        counts
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::UnreadableDirectory(e) => write!(f, "cannot list directory: {e}"),
+            Problem::PermissionDenied => write!(f, "permission denied"),
+            Problem::ProbeFailed(e) => write!(f, "cannot read: {e}"),
+            Problem::Untagged => write!(f, "no tags"),
+            Problem::Duplicate(p) => write!(f, "duplicate of {}", p.display()),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.problem)
+    }
+}
+
+/// Summary of counts, then each problem under a heading for its kind
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no problems found");
+        }
+        for (kind, n) in self.counts() {
+            writeln!(f, "{n:>6} {kind}")?;
+        }
+        let mut heading = None;
+        for d in &self.diagnostics {
+            if heading != Some(d.problem.kind()) {
+                heading = Some(d.problem.kind());
+                writeln!(f, "\n{}:", d.problem.kind())?;
+            }
+            writeln!(f, "  {d}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+//! Reads the library across a pool of threads, streaming out tracks as they are read so the
+//! library is usable before the scan completes
+
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver},
+    },
+    thread,
+};
+
+use super::{AudioTrack, Diagnostic, Problem, Report, read_audio_file, walk};
+
+/// Most threads reading files at once; beyond this the disk rather than decoding is the limit
+const MAX_WORKERS: usize = 8;
+
+pub enum ScanEvent {
+    /// A supported file was found and queued to be read
+    Found,
+    /// The tracks of a file read, several where a CUE sheet splits it
+    Read(Vec<AudioTrack>),
+    /// A found file could not be read
+    Failed(Diagnostic),
+    /// A directory could not be listed, or a file was read with something amiss
+    Problem(Diagnostic),
+}
+
+/// Walks `directory` on one thread while others read the files it finds. The channel
+/// disconnects once every file found has been read
+pub fn scan(directory: PathBuf, follow_symlinks: bool) -> Receiver<ScanEvent> {
+    let (events, rx) = mpsc::channel();
+    let (paths, queue) = mpsc::channel::<PathBuf>();
+    let queue = Arc::new(Mutex::new(queue));
+
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(MAX_WORKERS);
+    for _ in 0..workers {
+        let queue = queue.clone();
+        let events = events.clone();
+        thread::spawn(move || {
+            loop {
+                // the lock is released before reading, so workers only contend for the next path
+                let Ok(path) = queue.lock().expect("scan queue lock").recv() else {
+                    return;
+                };
+                let event = match read_audio_file(&path) {
+                    Ok(tracks) => {
+                        if let [AudioTrack::Limited(_)] = tracks[..] {
+                            let untagged = Diagnostic::new(path, Problem::Untagged);
+                            let _ = events.send(ScanEvent::Problem(untagged));
+                        }
+                        ScanEvent::Read(tracks)
+                    }
+                    Err(e) => ScanEvent::Failed(Diagnostic::unreadable(path, e)),
+                };
+                if events.send(event).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    thread::spawn(move || {
+        walk(
+            directory,
+            follow_symlinks,
+            |path| {
+                // announce before queueing, so a file is never read before it is counted
+                let _ = events.send(ScanEvent::Found);
+                let _ = paths.send(path);
+            },
+            |problem| {
+                let _ = events.send(ScanEvent::Problem(problem));
+            },
+        )
+    });
+    rx
+}
+
+/// Scans `directory` to completion, for use without the interface
+pub fn scan_all(directory: PathBuf, follow_symlinks: bool) -> (Vec<AudioTrack>, Report) {
+    let mut tracks = Vec::new();
+    let mut report = Report::default();
+    for event in scan(directory, follow_symlinks) {
+        match event {
+            ScanEvent::Found => (),
+            ScanEvent::Read(read) => tracks.extend(read),
+            ScanEvent::Failed(d) | ScanEvent::Problem(d) => report.push(d),
+        }
+    }
+    tracks.sort_by(|a, b| a.path().cmp(b.path()));
+    report.complete(&tracks);
+    (tracks, report)
+}
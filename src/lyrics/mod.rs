@@ -0,0 +1,200 @@
+//! Finds and parses lyrics for a track, from a sidecar `.lrc` file or embedded tags
+//!
+//! Text with LRC timestamps (`[mm:ss.xx]`) is treated as synchronised, anything else as plain.
+//! An ID3 `SYLT` frame is synchronised too, and preferred over unsynchronised tag text
+
+mod sylt;
+
+use std::{fs, path::Path, time::Duration};
+
+use crate::loader::AudioTrack;
+
+pub enum Lyrics {
+    /// Lines with the time they begin, sorted by time
+    Timed(Vec<(Duration, String)>),
+    Plain(Vec<String>),
+}
+
+impl Lyrics {
+    /// Loads lyrics for a track, preferring a sidecar file next to the audio over tags. A
+    /// track of a CUE sheet has neither, the file holding the whole album
+    pub fn load(track: &AudioTrack) -> Option<Self> {
+        if track.cue().is_some() {
+            return None;
+        }
+        let path = Path::new(track.path());
+        fs::read_to_string(path.with_extension("lrc"))
+            .ok()
+            .and_then(|s| Self::parse(&s))
+            .or_else(|| sylt::read(path).map(Lyrics::Timed))
+            .or_else(|| track.lyrics().and_then(Self::parse))
+    }
+
+    /// Parses LRC formatted or plain text, returning `None` where there is no text at all
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut timed = Vec::new();
+        let mut plain = Vec::new();
+        // `[offset:+/-ms]` shifts every timestamp; positive values show lines earlier
+        let mut offset = 0i64;
+
+        for line in text.lines() {
+            let mut rest = line.trim();
+            let mut stamps = Vec::new();
+            while let Some((tag, after)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+                match parse_timestamp(tag) {
+                    Some(t) => stamps.push(t),
+                    None => {
+                        if let Some(v) = tag.strip_prefix("offset:") {
+                            offset = v.trim().parse().unwrap_or(0);
+                        }
+                        // other id tags, such as `[ar:...]`, carry no lyric
+                    }
+                }
+                rest = after.trim_start();
+            }
+            match stamps.is_empty() {
+                true if !is_id_tag(line.trim()) => plain.push(line.trim().to_owned()),
+                true => (),
+                false => timed.extend(stamps.into_iter().map(|t| (t, rest.to_owned()))),
+            }
+        }
+
+        if !timed.is_empty() {
+            for (t, _) in timed.iter_mut() {
+                *t = shift(*t, -offset);
+            }
+            timed.sort_by_key(|(t, _)| *t);
+            Some(Lyrics::Timed(timed))
+        } else if plain.iter().any(|l| !l.is_empty()) {
+            Some(Lyrics::Plain(plain))
+        } else {
+            None
+        }
+    }
+
+    /// Index of the line being sung at `position`, for synchronised lyrics
+    pub fn current_line(&self, position: Duration) -> Option<usize> {
+        match self {
+            Lyrics::Timed(lines) => lines
+                .partition_point(|(t, _)| *t <= position)
+                .checked_sub(1),
+            Lyrics::Plain(_) => None,
+        }
+    }
+
+    pub fn lines(&self) -> Vec<&str> {
+        match self {
+            Lyrics::Timed(lines) => lines.iter().map(|(_, l)| l.as_str()).collect(),
+            Lyrics::Plain(lines) => lines.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// Shifts a duration by a signed number of milliseconds, saturating at zero
+pub fn shift(d: Duration, millis: i64) -> Duration {
+    match millis >= 0 {
+        true => d + Duration::from_millis(millis as u64),
+        false => d.saturating_sub(Duration::from_millis(millis.unsigned_abs())),
+    }
+}
+
+/// Whether a line is an LRC id tag such as `[ar:Artist]`, rather than lyric text
+fn is_id_tag(line: &str) -> bool {
+    line.strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .and_then(|l| l.split_once(':'))
+        .is_some_and(|(key, _)| !key.is_empty() && key.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+/// Parses `mm:ss`, `mm:ss.xx` or `mm:ss:xx`
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+    let (minutes, rest) = tag.split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+    let (seconds, fraction) = match rest.split_once(['.', ':']) {
+        Some((s, f)) => (s, f),
+        None => (rest, "0"),
+    };
+    let seconds: u64 = seconds.trim().parse().ok()?;
+    if seconds >= 60 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // scale hundredths or thousandths alike to milliseconds
+    let millis = format!("{fraction:0<3}")[..3].parse::<u64>().ok()?;
+    Some(Duration::from_millis(
+        (minutes * 60 + seconds) * 1000 + millis,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timed(text: &str) -> Vec<(Duration, String)> {
+        match Lyrics::parse(text) {
+            Some(Lyrics::Timed(lines)) => lines,
+            _ => panic!("not synchronised"),
+        }
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn timestamp_forms() {
+        assert_eq!(parse_timestamp("01:02"), Some(ms(62_000)));
+        assert_eq!(parse_timestamp("01:02.5"), Some(ms(62_500)));
+        assert_eq!(parse_timestamp("01:02.34"), Some(ms(62_340)));
+        assert_eq!(parse_timestamp("01:02.345"), Some(ms(62_345)));
+        assert_eq!(parse_timestamp("01:02:34"), Some(ms(62_340)));
+        assert_eq!(parse_timestamp("01:60.00"), None);
+        assert_eq!(parse_timestamp("ar:Artist"), None);
+    }
+
+    #[test]
+    fn several_stamps_repeat_a_line() {
+        let lines = timed("[00:10.00][00:30.00]chorus\n[00:20.00]verse");
+        assert_eq!(
+            lines,
+            [
+                (ms(10_000), String::from("chorus")),
+                (ms(20_000), String::from("verse")),
+                (ms(30_000), String::from("chorus")),
+            ]
+        );
+    }
+
+    #[test]
+    fn positive_offset_shows_lines_earlier() {
+        let lines = timed("[offset:+500]\n[00:10.00]line\n[00:00.20]first");
+        assert_eq!(lines[0].0, ms(0));
+        assert_eq!(lines[1].0, ms(9_500));
+    }
+
+    #[test]
+    fn negative_offset_shows_lines_later() {
+        let lines = timed("[offset:-500]\n[00:10.00]line");
+        assert_eq!(lines[0].0, ms(10_500));
+    }
+
+    #[test]
+    fn id_tags_are_dropped() {
+        let lines = timed("[ar:Artist]\n[ti:Title]\n[00:01.00]line");
+        assert_eq!(lines, [(ms(1_000), String::from("line"))]);
+        match Lyrics::parse("[ar:Artist]\nplain line\n") {
+            Some(Lyrics::Plain(lines)) => assert_eq!(lines, ["plain line"]),
+            _ => panic!("not plain"),
+        }
+        assert!(Lyrics::parse("[ar:Artist]\n[al:Album]").is_none());
+    }
+
+    #[test]
+    fn current_line_follows_the_stamps() {
+        let lyrics = Lyrics::parse("[00:05.00]one\n[00:10.00]two").unwrap();
+        assert_eq!(lyrics.current_line(ms(4_999)), None);
+        assert_eq!(lyrics.current_line(ms(5_000)), Some(0));
+        assert_eq!(lyrics.current_line(ms(12_000)), Some(1));
+        let plain = Lyrics::parse("one\ntwo").unwrap();
+        assert_eq!(plain.current_line(ms(5_000)), None);
+    }
+}
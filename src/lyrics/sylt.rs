@@ -0,0 +1,260 @@
+//! Synchronised lyrics from the ID3v2 `SYLT` frame at the start of a file, which the decoder
+//! does not expose. Only times in milliseconds are read, not those counted in MPEG frames
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+    time::Duration,
+};
+
+/// Lines of the first `SYLT` frame of lyrics timed in milliseconds, where the file has one
+pub fn read(path: &Path) -> Option<Vec<(Duration, String)>> {
+    tag(path).ok().flatten().and_then(|tag| frames(&tag))
+}
+
+/// Header and frames of the ID3v2 tag at the start of a file
+fn tag(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    let mut file = File::open(path)?;
+    let mut header = [0; 10];
+    file.read_exact(&mut header)?;
+    if &header[..3] != b"ID3" {
+        return Ok(None);
+    }
+    let size = syncsafe(&header[6..10]) as u64;
+    let mut tag = header.to_vec();
+    file.take(size).read_to_end(&mut tag)?;
+    Ok(Some(tag))
+}
+
+/// A 28 bit size stored seven bits to a byte
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |n, b| n << 7 | usize::from(b & 0x7f))
+}
+
+/// Takes out the zero byte following each 0xff, put in so no false MPEG sync appears
+fn resynchronise(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    for (i, b) in bytes.iter().enumerate() {
+        if !(*b == 0 && i > 0 && bytes[i - 1] == 0xff) {
+            out.push(*b);
+        }
+    }
+    out
+}
+
+/// Walks the frames of a tag of version 2.3 or 2.4 for a `SYLT` frame it can read
+fn frames(tag: &[u8]) -> Option<Vec<(Duration, String)>> {
+    let (version, flags) = (tag[3], tag[5]);
+    if !(3..=4).contains(&version) {
+        return None;
+    }
+    // version 2.3 unsynchronises the whole tag, 2.4 each frame that says so
+    let whole = match version == 3 && flags & 0x80 != 0 {
+        true => resynchronise(&tag[10..]),
+        false => tag[10..].to_vec(),
+    };
+    let mut at = 0;
+    if flags & 0x40 != 0 {
+        let size = whole.get(..4)?;
+        at = match version {
+            3 => 4 + u32::from_be_bytes(size.try_into().ok()?) as usize,
+            _ => syncsafe(size),
+        };
+    }
+    while let Some(header) = whole.get(at..at + 10) {
+        if header[0] == 0 {
+            // padding
+            break;
+        }
+        let size = match version {
+            3 => u32::from_be_bytes(header[4..8].try_into().ok()?) as usize,
+            _ => syncsafe(&header[4..8]),
+        };
+        let body = whole.get(at + 10..at + 10 + size)?;
+        at += 10 + size;
+        if &header[..4] != b"SYLT" {
+            continue;
+        }
+        let body = match version == 4 && header[9] & 0x02 != 0 {
+            true => resynchronise(body),
+            false => body.to_vec(),
+        };
+        if let Some(lines) = sylt(&body) {
+            return Some(lines);
+        }
+    }
+    None
+}
+
+/// Parses a `SYLT` frame body: encoding, language, time format, content type, a descriptor,
+/// then each piece of text followed by the time it begins
+fn sylt(body: &[u8]) -> Option<Vec<(Duration, String)>> {
+    let (&encoding, rest) = body.split_first()?;
+    let format = *rest.get(3)?;
+    // 1 is lyrics, 0 other text that may be lyrics too
+    let content = *rest.get(4)?;
+    if format != 2 || content > 1 {
+        return None;
+    }
+    let (_, mut rest) = text(encoding, &rest[5..])?;
+    let mut lines = Vec::new();
+    while !rest.is_empty() {
+        let (line, after) = text(encoding, rest)?;
+        let time = u32::from_be_bytes(after.get(..4)?.try_into().ok()?);
+        lines.push((Duration::from_millis(time.into()), line));
+        rest = &after[4..];
+    }
+    // a line is often sent with the line break ending the one before it
+    for (_, line) in lines.iter_mut() {
+        *line = line.trim_matches(['\n', '\r']).to_owned();
+    }
+    lines.sort_by_key(|(t, _)| *t);
+    (!lines.is_empty()).then_some(lines)
+}
+
+/// Text in `encoding` up to its terminator, and the bytes after it
+fn text(encoding: u8, bytes: &[u8]) -> Option<(String, &[u8])> {
+    match encoding {
+        0 | 3 => {
+            let end = bytes.iter().position(|b| *b == 0)?;
+            let text = match encoding {
+                0 => bytes[..end].iter().map(|b| char::from(*b)).collect(),
+                _ => String::from_utf8_lossy(&bytes[..end]).into_owned(),
+            };
+            Some((text, &bytes[end + 1..]))
+        }
+        1 | 2 => {
+            let end = bytes
+                .chunks_exact(2)
+                .position(|c| c == [0, 0])
+                .map(|i| i * 2)?;
+            // a byte order mark decides, where there is one
+            let (big_endian, start) = match bytes[..end] {
+                [0xfe, 0xff, ..] => (true, 2),
+                [0xff, 0xfe, ..] => (false, 2),
+                _ => (encoding == 2, 0),
+            };
+            let units: Vec<u16> = bytes[start..end]
+                .chunks_exact(2)
+                .map(|c| match big_endian {
+                    true => u16::from_be_bytes([c[0], c[1]]),
+                    false => u16::from_le_bytes([c[0], c[1]]),
+                })
+                .collect();
+            Some((String::from_utf16_lossy(&units), &bytes[end + 2..]))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_syncsafe(n: usize) -> [u8; 4] {
+        [21, 14, 7, 0].map(|shift| (n >> shift) as u8 & 0x7f)
+    }
+
+    /// A tag of `version` holding one frame
+    fn tag(version: u8, id: &[u8], body: &[u8]) -> Vec<u8> {
+        let size = |n: usize| match version {
+            3 => (n as u32).to_be_bytes(),
+            _ => to_syncsafe(n),
+        };
+        let mut frames = id.to_vec();
+        frames.extend(size(body.len()));
+        frames.extend([0, 0]);
+        frames.extend(body);
+        // padding
+        frames.extend([0; 8]);
+        let mut tag = vec![b'I', b'D', b'3', version, 0, 0];
+        tag.extend(to_syncsafe(frames.len()));
+        tag.extend(frames);
+        tag
+    }
+
+    fn body(encoding: u8, format: u8, lines: &[(&[u8], u32)]) -> Vec<u8> {
+        let mut body = vec![encoding, b'e', b'n', b'g', format, 1];
+        // empty descriptor
+        body.extend(match encoding {
+            1 | 2 => &[0, 0][..],
+            _ => &[0][..],
+        });
+        for (text, time) in lines {
+            body.extend(*text);
+            body.extend(time.to_be_bytes());
+        }
+        body
+    }
+
+    #[test]
+    fn lines_are_read_in_order() {
+        let body = body(
+            3,
+            2,
+            &[
+                (b"second\0", 2500),
+                (b"first\0", 1000),
+                (b"\nthird\0", 4000),
+            ],
+        );
+        let lines = frames(&tag(4, b"SYLT", &body)).unwrap();
+        assert_eq!(
+            lines,
+            [
+                (Duration::from_millis(1000), String::from("first")),
+                (Duration::from_millis(2500), String::from("second")),
+                (Duration::from_millis(4000), String::from("third")),
+            ]
+        );
+    }
+
+    #[test]
+    fn utf16_with_a_byte_order_mark() {
+        let text = [0xff, 0xfe, b'h', 0, 0xe9, 0, b'!', 0, 0, 0];
+        let lines = frames(&tag(3, b"SYLT", &body(1, 2, &[(&text, 500)]))).unwrap();
+        assert_eq!(lines, [(Duration::from_millis(500), String::from("hé!"))]);
+    }
+
+    #[test]
+    fn latin1_in_version_three() {
+        let lines = frames(&tag(3, b"SYLT", &body(0, 2, &[(b"caf\xe9\0", 0)]))).unwrap();
+        assert_eq!(lines[0].1, "café");
+    }
+
+    #[test]
+    fn other_frames_are_passed_over() {
+        let mut tag = tag(4, b"TIT2", b"\x03Title\0");
+        let padding = tag.len() - 8;
+        let sylt = body(3, 2, &[(b"line\0", 0)]);
+        let mut frame = b"SYLT".to_vec();
+        frame.extend([0, 0, 0, sylt.len() as u8, 0, 0]);
+        frame.extend(sylt);
+        tag.splice(padding..padding, frame);
+        assert_eq!(frames(&tag).unwrap()[0].1, "line");
+    }
+
+    #[test]
+    fn mpeg_frame_times_are_not_read() {
+        assert!(frames(&tag(4, b"SYLT", &body(3, 1, &[(b"line\0", 40)]))).is_none());
+    }
+
+    #[test]
+    fn unsynchronised_frame() {
+        // the time 0x0000ff00 is written 00 00 ff 00 00
+        let mut sylt = body(3, 2, &[]);
+        sylt.extend(b"line\0\x00\x00\xff\x00\x00");
+        let mut tag = tag(4, b"SYLT", &sylt);
+        tag[10 + 9] = 0x02;
+        assert_eq!(frames(&tag).unwrap()[0].0, Duration::from_millis(0xff00));
+    }
+
+    #[test]
+    fn file_without_a_tag() {
+        let path = std::env::temp_dir().join("mplay-sylt-none.flac");
+        std::fs::write(&path, b"fLaC\0\0\0\0\0\0\0\0").unwrap();
+        assert!(read(&path).is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+}
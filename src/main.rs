@@ -1,16 +1,53 @@
 //! mplay: music player based in the terminal
 
-use std::path::PathBuf;
-
+mod art;
+mod autodj;
+mod config;
+mod http;
+mod keymap;
 mod loader;
+mod lyrics;
 mod playback;
+mod playlist;
+mod podcast;
+mod query;
+mod scrobble;
+mod stats;
+mod stream;
+mod tags;
 mod term;
+mod theme;
+mod watch;
+
+use std::{env, process::ExitCode};
 
-fn main() {
-    // todo: config file
-    let music_dir = format!("{}/Music/", env!("HOME"));
-    let library = loader::AudioLibrary::from_directory(PathBuf::from(music_dir));
+fn main() -> ExitCode {
+    let config = config::Config::load();
 
-    term::UserInterface::new(library.tracks).run(ratatui::init());
-    ratatui::restore();
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => {
+            let failures = term::UserInterface::new(&config).run(ratatui::init());
+            ratatui::restore();
+            failures.iter().for_each(|f| eprintln!("{f}"));
+        }
+        // scan without the interface, listing every problem found where asked
+        ["scan", ref rest @ ..] if rest.iter().all(|a| *a == "--report") => {
+            let (tracks, report) =
+                loader::scan_all(config.music_dir.clone(), config.follow_symlinks);
+            println!("scanned {} tracks", tracks.len());
+            match rest.is_empty() {
+                true => report
+                    .counts()
+                    .iter()
+                    .for_each(|(kind, n)| println!("{n:>6} {kind}")),
+                false => print!("{report}"),
+            }
+        }
+        _ => {
+            eprintln!("usage: mplay [scan [--report]]");
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,110 @@
+//! Tracks the media position of a source as it is read, independent of tempo changes further
+//! down the chain
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{Source, source::SeekError};
+
+/// Handle reading the position of a `Clocked` source from the UI thread
+#[derive(Clone)]
+pub struct Clock {
+    samples: Arc<AtomicU64>,
+    /// Samples per second across all channels
+    rate: u64,
+}
+
+/// Source counting the samples read from its inner source
+pub struct Clocked<S> {
+    inner: S,
+    samples: Arc<AtomicU64>,
+    count: u64,
+    rate: u64,
+}
+
+impl Clock {
+    /// Position within the media of the most recently read sample
+    pub fn position(&self) -> Duration {
+        let samples = self.samples.load(Ordering::Relaxed);
+        Duration::from_secs_f64(samples as f64 / self.rate.max(1) as f64)
+    }
+}
+
+impl<S> Clocked<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(inner: S) -> (Self, Clock) {
+        let samples = Arc::new(AtomicU64::new(0));
+        let rate = inner.sample_rate() as u64 * inner.channels() as u64;
+        let clock = Clock {
+            samples: samples.clone(),
+            rate,
+        };
+        let clocked = Self {
+            inner,
+            samples,
+            count: 0,
+            rate,
+        };
+        (clocked, clock)
+    }
+}
+
+impl<S> Iterator for Clocked<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        self.count += 1;
+        self.samples.store(self.count, Ordering::Relaxed);
+        Some(sample)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S> Source for Clocked<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)?;
+        self.count = (pos.as_secs_f64() * self.rate as f64) as u64;
+        self.samples.store(self.count, Ordering::Relaxed);
+        Ok(())
+    }
+}
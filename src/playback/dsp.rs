@@ -0,0 +1,470 @@
+//! Signal processing applied to every source between decoding and output
+//!
+//! The chain runs preamp, a ten band peaking equaliser, mono downmix, balance and finally a
+//! peak limiter. Settings are shared with the UI and picked up by playing sources on their next
+//! frame, without ever waiting on the UI from the audio thread
+
+use std::{
+    f32::consts::PI,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{Source, source::SeekError};
+
+pub const BANDS: usize = 10;
+
+/// Centre frequencies of a standard octave spaced graphic equaliser
+pub const DEFAULT_FREQUENCIES: [f32; BANDS] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+
+/// Q giving roughly one octave of bandwidth per band
+pub const DEFAULT_Q: f32 = 1.41;
+
+/// Named gain curves, in dB per band
+pub const PRESETS: [(&str, [f32; BANDS]); 7] = [
+    ("flat", [0.0; BANDS]),
+    ("bass", [6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+    ("treble", [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 4.0, 5.0, 6.0]),
+    (
+        "vocal",
+        [-2.0, -2.0, -1.0, 1.0, 3.0, 3.0, 2.0, 1.0, 0.0, -1.0],
+    ),
+    ("rock", [4.0, 3.0, 2.0, 0.0, -1.0, -1.0, 1.0, 2.0, 3.0, 4.0]),
+    (
+        "classical",
+        [3.0, 2.0, 1.0, 0.0, 0.0, 0.0, -1.0, -1.0, 1.0, 2.0],
+    ),
+    (
+        "loudness",
+        [5.0, 4.0, 2.0, 0.0, -1.0, 0.0, 0.0, 1.0, 3.0, 4.0],
+    ),
+];
+
+/// Level above which the limiter reduces gain, about -0.3 dBFS
+const LIMIT: f32 = 0.966;
+/// Time for the limiter to recover full gain once the peak has passed
+const RELEASE: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Band {
+    pub frequency: f32,
+    pub gain: f32,
+    pub q: f32,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct DspSettings {
+    /// Bypasses the whole chain when false
+    pub enabled: bool,
+    /// Name of the preset last applied, cleared once a band is edited by hand
+    pub preset: Option<String>,
+    /// Gain in dB applied ahead of the equaliser
+    pub preamp: f32,
+    pub bands: [Band; BANDS],
+    /// From -1.0 (left only) through 0.0 (centre) to 1.0 (right only)
+    pub balance: f32,
+    pub mono: bool,
+    pub limiter: bool,
+}
+
+/// Handle to the settings shared by every playing `Dsp`
+#[derive(Clone)]
+pub struct DspHandle {
+    settings: Arc<Mutex<DspSettings>>,
+    generation: Arc<AtomicU32>,
+}
+
+/// Source applying the current `DspSettings` to its inner source
+pub struct Dsp<S> {
+    inner: S,
+    handle: DspHandle,
+    /// Generation of the settings, sample rate and channels the chain was built for
+    built: (u32, u32, u16),
+    chain: Chain,
+    /// Interleaved samples of the frame being emitted
+    frame: Vec<f32>,
+    position: usize,
+}
+
+/// Per source state derived from the settings for a given sample rate
+struct Chain {
+    enabled: bool,
+    preamp: f32,
+    filters: Vec<Coefficients>,
+    /// Filter history, `filters.len()` entries per channel
+    history: Vec<History>,
+    balance: (f32, f32),
+    mono: bool,
+    limiter: bool,
+    limiter_gain: f32,
+    release: f32,
+}
+
+/// Normalised biquad coefficients
+#[derive(Clone, Copy)]
+struct Coefficients {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+#[derive(Clone, Copy, Default)]
+struct History {
+    z1: f32,
+    z2: f32,
+}
+
+impl Default for DspSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            preset: Some(String::from("flat")),
+            preamp: 0.0,
+            bands: DEFAULT_FREQUENCIES.map(|frequency| Band {
+                frequency,
+                gain: 0.0,
+                q: DEFAULT_Q,
+            }),
+            balance: 0.0,
+            mono: false,
+            limiter: true,
+        }
+    }
+}
+
+impl DspSettings {
+    /// Applies the gains of a named preset, returning false where no such preset exists
+    pub fn apply_preset(&mut self, name: &str) -> bool {
+        match PRESETS.iter().find(|(n, _)| *n == name) {
+            Some((n, gains)) => {
+                for (band, gain) in self.bands.iter_mut().zip(gains) {
+                    band.gain = *gain;
+                }
+                self.preset = Some(String::from(*n));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies the preset following the current one, wrapping back to the first
+    pub fn next_preset(&mut self) {
+        let i = self
+            .preset
+            .as_ref()
+            .and_then(|p| PRESETS.iter().position(|(n, _)| n == p))
+            .map_or(0, |i| (i + 1) % PRESETS.len());
+        self.apply_preset(PRESETS[i].0);
+    }
+}
+
+impl DspHandle {
+    pub fn new(settings: DspSettings) -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(settings)),
+            generation: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    pub fn settings(&self) -> DspSettings {
+        self.settings.lock().expect("dsp settings lock").clone()
+    }
+
+    /// The settings and their generation, or `None` while the UI holds them
+    fn try_settings(&self) -> Option<(DspSettings, u32)> {
+        let settings = self.settings.try_lock().ok()?;
+        Some((settings.clone(), self.generation.load(Ordering::Acquire)))
+    }
+
+    /// Edits the settings, which playing sources pick up on their next frame
+    pub fn update(&self, f: impl FnOnce(&mut DspSettings)) {
+        f(&mut self.settings.lock().expect("dsp settings lock"));
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+}
+
+impl Coefficients {
+    /// Peaking filter from the RBJ audio EQ cookbook
+    fn peaking(band: &Band, sample_rate: u32) -> Self {
+        let nyquist = sample_rate as f32 / 2.0;
+        let w0 = 2.0 * PI * band.frequency.clamp(1.0, nyquist * 0.99) / sample_rate as f32;
+        let a = 10f32.powf(band.gain / 40.0);
+        let alpha = w0.sin() / (2.0 * band.q.max(0.1));
+        let cos = w0.cos();
+
+        let a0 = 1.0 + alpha / a;
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: (-2.0 * cos) / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: (-2.0 * cos) / a0,
+            a2: (1.0 - alpha / a) / a0,
+        }
+    }
+
+    /// Transposed direct form II
+    #[inline]
+    fn process(&self, history: &mut History, x: f32) -> f32 {
+        let y = self.b0 * x + history.z1;
+        history.z1 = self.b1 * x - self.a1 * y + history.z2;
+        history.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+impl Chain {
+    fn new(settings: &DspSettings, sample_rate: u32, channels: u16) -> Self {
+        // a band at 0 dB passes its input unchanged, but is kept so the history of the others
+        // stays in place as bands are moved on and off 0 dB
+        let filters: Vec<Coefficients> = settings
+            .bands
+            .iter()
+            .map(|b| Coefficients::peaking(b, sample_rate))
+            .collect();
+        let balance = settings.balance.clamp(-1.0, 1.0);
+        let release_samples = RELEASE.as_secs_f32() * sample_rate as f32;
+
+        Self {
+            enabled: settings.enabled,
+            preamp: 10f32.powf(settings.preamp / 20.0),
+            history: vec![History::default(); filters.len() * channels as usize],
+            filters,
+            balance: ((1.0 - balance).min(1.0), (1.0 + balance).min(1.0)),
+            mono: settings.mono,
+            limiter: settings.limiter,
+            limiter_gain: 1.0,
+            release: 1.0 / release_samples.max(1.0),
+        }
+    }
+
+    /// Processes one interleaved frame in place
+    fn process(&mut self, frame: &mut [f32]) {
+        if !self.enabled {
+            return;
+        }
+        let channels = frame.len();
+        let stages = self.filters.len();
+        if self.history.len() < stages * channels {
+            self.history.resize(stages * channels, History::default());
+        }
+
+        for (c, sample) in frame.iter_mut().enumerate() {
+            let mut x = *sample * self.preamp;
+            let history = &mut self.history[c * stages..(c + 1) * stages];
+            for (filter, h) in self.filters.iter().zip(history) {
+                x = filter.process(h, x);
+            }
+            *sample = x;
+        }
+
+        if self.mono && channels > 1 {
+            let mean = frame.iter().sum::<f32>() / channels as f32;
+            frame.fill(mean);
+        }
+
+        if channels == 2 {
+            frame[0] *= self.balance.0;
+            frame[1] *= self.balance.1;
+        }
+
+        if self.limiter {
+            let peak = frame.iter().fold(0f32, |m, s| m.max(s.abs()));
+            if peak * self.limiter_gain > LIMIT {
+                self.limiter_gain = LIMIT / peak;
+            } else {
+                self.limiter_gain = (self.limiter_gain + self.release).min(1.0);
+            }
+            for sample in frame.iter_mut() {
+                *sample *= self.limiter_gain;
+            }
+        }
+    }
+}
+
+impl<S> Dsp<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(inner: S, handle: DspHandle) -> Self {
+        let generation = handle.generation.load(Ordering::Acquire);
+        let (rate, channels) = (inner.sample_rate(), inner.channels());
+        let chain = Chain::new(&handle.settings(), rate, channels);
+        Self {
+            inner,
+            handle,
+            built: (generation, rate, channels),
+            chain,
+            frame: Vec::with_capacity(8),
+            position: 0,
+        }
+    }
+
+    /// Reads and processes the next frame, returning false once the inner source is exhausted
+    fn fill(&mut self) -> bool {
+        let generation = self.handle.generation.load(Ordering::Acquire);
+        let (rate, channels) = (self.inner.sample_rate(), self.inner.channels());
+        // settings held by the UI are picked up on a later frame rather than waited for
+        if (generation, rate, channels) != self.built
+            && let Some((settings, generation)) = self.handle.try_settings()
+        {
+            let same_rate = rate == self.built.1;
+            self.built = (generation, rate, channels);
+            let chain = Chain::new(&settings, rate, channels);
+            let old = std::mem::replace(&mut self.chain, chain);
+            self.chain.limiter_gain = old.limiter_gain;
+            // keep filter history across gain edits to avoid a click while adjusting; it only
+            // applies at the rate it was built for
+            if old.history.len() == self.chain.history.len() && same_rate {
+                self.chain.history = old.history;
+            }
+        }
+
+        self.frame.clear();
+        self.position = 0;
+        for _ in 0..self.inner.channels() {
+            match self.inner.next() {
+                Some(s) => self.frame.push(s),
+                None => break,
+            }
+        }
+        self.chain.process(&mut self.frame);
+        !self.frame.is_empty()
+    }
+}
+
+impl<S> Iterator for Dsp<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.position == self.frame.len() && !self.fill() {
+            return None;
+        }
+        let sample = self.frame[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S> Source for Dsp<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        let buffered = self.frame.len() - self.position;
+        self.inner.current_frame_len().map(|n| n + buffered)
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        // drop the rest of the buffered frame so output resumes on a frame boundary
+        self.position = self.frame.len();
+        self.inner.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    /// Settings with only the limiter off, so a boost is measured rather than limited
+    fn settings() -> DspSettings {
+        DspSettings {
+            limiter: false,
+            ..DspSettings::default()
+        }
+    }
+
+    /// Gain in dB through `Dsp` of a stereo sine at `frequency`, measured once the filters
+    /// have settled
+    fn gain(settings: DspSettings, frequency: f32) -> f32 {
+        let samples: Vec<f32> = (0..RATE)
+            .flat_map(|i| {
+                let s = 0.25 * (2.0 * PI * frequency * i as f32 / RATE as f32).sin();
+                [s, s]
+            })
+            .collect();
+        let settle = samples.len() / 2;
+        let rms = |s: &[f32]| (s.iter().map(|x| x * x).sum::<f32>() / s.len() as f32).sqrt();
+        let input = rms(&samples[settle..]);
+        let dsp = Dsp::new(
+            SamplesBuffer::new(2, RATE, samples),
+            DspHandle::new(settings),
+        );
+        let output: Vec<f32> = dsp.collect();
+        20.0 * (rms(&output[settle..]) / input).log10()
+    }
+
+    #[test]
+    fn band_boosts_its_centre() {
+        let mut s = settings();
+        s.bands[5].gain = 6.0;
+        let db = gain(s, DEFAULT_FREQUENCIES[5]);
+        assert!((db - 6.0).abs() < 0.2, "{db} dB at the centre");
+    }
+
+    #[test]
+    fn band_cuts_its_centre() {
+        let mut s = settings();
+        s.bands[5].gain = -9.0;
+        let db = gain(s, DEFAULT_FREQUENCIES[5]);
+        assert!((db + 9.0).abs() < 0.2, "{db} dB at the centre");
+    }
+
+    #[test]
+    fn band_leaves_far_frequencies() {
+        let mut s = settings();
+        s.bands[5].gain = 6.0;
+        let db = gain(s, DEFAULT_FREQUENCIES[1]);
+        assert!(db.abs() < 0.2, "{db} dB far off the band");
+    }
+
+    #[test]
+    fn flat_is_transparent() {
+        let db = gain(settings(), 440.0);
+        assert!(db.abs() < 0.01, "{db} dB when flat");
+    }
+
+    #[test]
+    fn history_is_stable_across_gains() {
+        let mut s = settings();
+        let flat = Chain::new(&s, RATE, 2).history.len();
+        s.bands[3].gain = 4.0;
+        assert_eq!(Chain::new(&s, RATE, 2).history.len(), flat);
+    }
+}
@@ -0,0 +1,116 @@
+//! Playing part of a source as though it were the whole, as for a track of a CUE sheet within
+//! the file of its album. Positions are given from the start of the part
+
+use std::time::Duration;
+
+use rodio::{Source, source::SeekError};
+
+/// Source beginning at `start` in its inner source and ending at `end`, where set
+pub struct Excerpt<S> {
+    inner: S,
+    start: Duration,
+    /// Samples across all channels from the start to the end
+    len: Option<u64>,
+    count: u64,
+    rate: u64,
+}
+
+impl<S> Excerpt<S>
+where
+    S: Source<Item = f32>,
+{
+    /// Seeks `inner` to `start`, reading up to it where the format cannot seek
+    pub fn new(mut inner: S, start: Duration, end: Option<Duration>) -> Self {
+        let channels = inner.channels() as u64;
+        let sample_rate = inner.sample_rate();
+        let rate = sample_rate as u64 * channels;
+        // counted in whole frames, so channels stay in step
+        let frames = |d: Duration| (d.as_secs_f64() * sample_rate as f64) as u64;
+        let len = end
+            .map(|e| e.saturating_sub(start))
+            .map(|d| frames(d) * channels);
+        if !start.is_zero() && inner.try_seek(start).is_err() {
+            let skip = frames(start) * channels;
+            inner.by_ref().take(skip as usize).for_each(drop);
+        }
+        Self {
+            inner,
+            start,
+            len,
+            count: 0,
+            rate,
+        }
+    }
+}
+
+impl<S> Iterator for Excerpt<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.len.is_some_and(|len| self.count >= len) {
+            return None;
+        }
+        self.count += 1;
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (low, high) = self.inner.size_hint();
+        match self.len {
+            Some(len) => {
+                let left = len.saturating_sub(self.count) as usize;
+                (low.min(left), Some(high.map_or(left, |h| h.min(left))))
+            }
+            None => (low, high),
+        }
+    }
+}
+
+impl<S> Source for Excerpt<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        let frame = self.inner.current_frame_len();
+        match self.len {
+            Some(len) => {
+                let left = len.saturating_sub(self.count) as usize;
+                Some(frame.map_or(left, |f| f.min(left)))
+            }
+            None => frame,
+        }
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        match self.len {
+            Some(len) => Some(Duration::from_secs_f64(
+                len as f64 / self.rate.max(1) as f64,
+            )),
+            None => Some(self.inner.total_duration()?.saturating_sub(self.start)),
+        }
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(self.start + pos)?;
+        self.count = (pos.as_secs_f64() * self.inner.sample_rate() as f64) as u64
+            * self.inner.channels() as u64;
+        Ok(())
+    }
+}
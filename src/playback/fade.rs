@@ -0,0 +1,137 @@
+//! Gain ramping for sources, used to soften pause, resume, skip and crossfade transitions
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{Source, source::SeekError};
+
+/// Target shared between a `Fade` and its `Fader`. Each field is updated atomically; the
+/// generation is bumped last so the source only picks up a complete request
+struct Target {
+    gain: AtomicU32,
+    millis: AtomicU32,
+    generation: AtomicU32,
+}
+
+/// Handle for ramping the gain of a playing `Fade` from the UI thread
+#[derive(Clone)]
+pub struct Fader(Arc<Target>);
+
+/// Source whose gain linearly follows the most recent request made through its `Fader`
+pub struct Fade<S> {
+    inner: S,
+    target: Arc<Target>,
+    generation: u32,
+    gain: f32,
+    goal: f32,
+    step: f32,
+}
+
+impl Fader {
+    /// Ramps the gain towards `gain` over `duration`, starting from wherever it currently is
+    pub fn fade_to(&self, gain: f32, duration: Duration) {
+        let millis = duration.as_millis().min(u32::MAX as u128) as u32;
+        self.0.gain.store(gain.to_bits(), Ordering::Relaxed);
+        self.0.millis.store(millis, Ordering::Relaxed);
+        self.0.generation.fetch_add(1, Ordering::Release);
+    }
+}
+
+impl<S> Fade<S>
+where
+    S: Source<Item = f32>,
+{
+    /// Wraps `inner`, starting playback at `gain`
+    pub fn new(inner: S, gain: f32) -> (Self, Fader) {
+        let target = Arc::new(Target {
+            gain: AtomicU32::new(gain.to_bits()),
+            millis: AtomicU32::new(0),
+            generation: AtomicU32::new(0),
+        });
+        let fade = Self {
+            inner,
+            target: target.clone(),
+            generation: 0,
+            gain,
+            goal: gain,
+            step: 0.0,
+        };
+        (fade, Fader(target))
+    }
+
+    fn retarget(&mut self) {
+        self.goal = f32::from_bits(self.target.gain.load(Ordering::Relaxed));
+        let millis = self.target.millis.load(Ordering::Relaxed);
+        let samples =
+            millis as f32 / 1000.0 * self.inner.sample_rate() as f32 * self.inner.channels() as f32;
+        self.step = match samples >= 1.0 {
+            true => (self.goal - self.gain) / samples,
+            false => self.goal - self.gain,
+        };
+    }
+}
+
+impl<S> Iterator for Fade<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let generation = self.target.generation.load(Ordering::Acquire);
+        if generation != self.generation {
+            self.generation = generation;
+            self.retarget();
+        }
+        if self.gain != self.goal {
+            self.gain += self.step;
+            if (self.step > 0.0 && self.gain > self.goal)
+                || (self.step < 0.0 && self.gain < self.goal)
+            {
+                self.gain = self.goal;
+            }
+        }
+        self.inner.next().map(|s| s * self.gain)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S> Source for Fade<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)
+    }
+}
@@ -1,29 +1,378 @@
-use std::{collections::VecDeque, fs::File, io::BufReader, rc::Rc};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufReader, Read, Seek},
+    mem,
+    rc::Rc,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
 
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
+use rodio::{Decoder, Sink, Source};
 
+use crate::config::PlaybackConfig;
 use crate::loader::AudioTrack;
+use crate::stream::{self, Stream, StreamTitle};
+
+mod clock;
+mod dsp;
+mod excerpt;
+mod fade;
+mod output;
+mod segment;
+mod stretch;
+
+use clock::{Clock, Clocked};
+use dsp::Dsp;
+pub use dsp::{BANDS, Band, DspHandle, DspSettings};
+use excerpt::Excerpt;
+use fade::{Fade, Fader};
+use output::Output;
+pub use output::devices;
+use segment::{Bounds, Segment};
+use stretch::{Stretch, Tempo};
+
+/// How far ahead of the end of a track the next is appended, so that it begins without a gap
+const PRELOAD: Duration = Duration::from_secs(1);
+
+/// Limits of the playback speed multiplier
+pub const SPEED_RANGE: (f32, f32) = (0.5, 2.0);
+
+/// Interval between looks for a device to move to, while none is open or the one requested
+/// is not the one in use
+const DEVICE_PROBE: Duration = Duration::from_secs(3);
+
+/// Changes in what is playing, for whatever follows playback
+pub enum PlayerEvent {
+    /// A track began from the start
+    Started {
+        track: Rc<AudioTrack>,
+        duration: Option<Duration>,
+    },
+    /// The track last started stopped, having played to its end or been skipped or cleared
+    Ended { skipped: bool },
+    /// A track could not be opened, and was passed over
+    Failed {
+        track: Rc<AudioTrack>,
+        error: String,
+    },
+    /// Something went amiss that playback carried on through
+    Notice(String),
+}
 
 pub struct Player {
-    pub last_played: Option<Rc<AudioTrack>>, // think on implementation of this more
+    current: Option<Loaded>,
+    /// Next track, already appended to `sink` behind `current` for gapless playback
+    preloaded: Option<Loaded>,
+    /// Stream being connected to away from the UI thread, played once enough has arrived
+    opening: Option<Opening>,
+    /// Sink of the previous track while it fades out under the current one, and when to drop it
+    outgoing: Option<(Sink, Instant)>,
+    /// Action deferred until the current fade out completes
+    pending: Option<(Pending, Instant)>,
     sink: Sink,
-    _stream: OutputStream,
-    _stream_handle: OutputStreamHandle,
+    /// Open output device; without one, sinks are idle until a device becomes available
+    output: Option<Output>,
+    /// Device requested by the user, or `None` for the system default
+    device: Option<String>,
+    last_probe: Instant,
+    /// Devices being listed away from the UI thread, as listing opens each of them
+    probe: Option<Receiver<Vec<String>>>,
     queue: VecDeque<Rc<AudioTrack>>, // remove element from queue on completion or not? and what smart pointer can I use here to avoid unsafe
+    dsp: DspHandle,
+    /// Tempo shared by every source, used in place of the sink speed when preserving pitch
+    tempo: Tempo,
+    speed: f32,
+    preserve_pitch: bool,
+    config: PlaybackConfig,
+    /// Events since they were last taken
+    events: Vec<PlayerEvent>,
+}
+
+/// A track appended to a sink, with the controls needed to shape its playback
+struct Loaded {
+    track: Rc<AudioTrack>,
+    fader: Fader,
+    clock: Clock,
+    bounds: Bounds,
+    duration: Option<Duration>,
+    /// Song playing on a stream, where the track is one
+    title: Option<StreamTitle>,
+}
+
+/// A stream connecting in the background
+struct Opening {
+    track: Rc<AudioTrack>,
+    stream: Receiver<io::Result<(Stream, StreamTitle)>>,
+    /// Whether the stream takes over from the current track as it is, as on a new device,
+    /// rather than starting afresh
+    resume: bool,
+}
+
+/// Where the audio of a track is read from: a file, or a stream arriving over the network
+trait Media: Read + Seek + Send + Sync {}
+
+impl<T: Read + Seek + Send + Sync> Media for T {}
+
+#[derive(Clone, Copy)]
+enum Pending {
+    Pause,
+    Skip,
 }
 
 impl Player {
-    pub fn new() -> Self {
-        // wrap in result and propagate
-        let (stream, stream_handle) = OutputStream::try_default().expect("output stream init");
-        let sink = Sink::try_new(&stream_handle).expect("sink init");
-
-        Self {
-            last_played: None,
-            sink,
-            _stream: stream,
-            _stream_handle: stream_handle,
+    pub fn new(config: PlaybackConfig) -> Self {
+        let speed = config.speed.clamp(SPEED_RANGE.0, SPEED_RANGE.1);
+
+        let mut player = Self {
+            current: None,
+            preloaded: None,
+            opening: None,
+            outgoing: None,
+            pending: None,
+            sink: Sink::new_idle().0,
+            output: Output::open(config.device.as_deref()),
+            device: config.device.clone(),
+            last_probe: Instant::now(),
+            probe: None,
             queue: VecDeque::with_capacity(8),
+            dsp: DspHandle::new(config.dsp.clone()),
+            tempo: Tempo::new(1.0),
+            speed,
+            preserve_pitch: config.preserve_pitch,
+            config,
+            events: Vec::new(),
+        };
+        player.sink = player.new_sink();
+        player.apply_speed();
+        player
+    }
+
+    /// Takes the events since last called
+    pub fn events(&mut self) -> Vec<PlayerEvent> {
+        mem::take(&mut self.events)
+    }
+
+    /// Whether playback is paused, or fading out to pause
+    pub fn is_paused(&self) -> bool {
+        self.sink.is_paused() || matches!(self.pending, Some((Pending::Pause, _)))
+    }
+
+    /// Position within the current track
+    pub fn position(&self) -> Option<Duration> {
+        self.current.as_ref().map(|l| l.clock.position())
+    }
+
+    /// Length of the current track, where the format reports one
+    pub fn duration(&self) -> Option<Duration> {
+        self.current.as_ref().and_then(|l| l.duration)
+    }
+
+    /// Sets the start of the A-B loop at the current position
+    pub fn set_loop_start(&self) {
+        if let Some(current) = &self.current {
+            current.bounds.set_start(current.clock.position());
+        }
+    }
+
+    /// Sets the end of the A-B loop at the current position, after which playback repeats
+    /// from the start point
+    pub fn set_loop_end(&self) {
+        if let Some(current) = &self.current {
+            current.bounds.set_end(current.clock.position());
+        }
+    }
+
+    pub fn clear_loop(&self) {
+        if let Some(current) = &self.current {
+            current.bounds.clear();
+        }
+    }
+
+    /// A and B loop points of the current track, each where set
+    pub fn loop_points(&self) -> (Option<Duration>, Option<Duration>) {
+        match &self.current {
+            Some(current) => current.bounds.get(),
+            None => (None, None),
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Whether speed changes stretch time rather than resample, keeping pitch constant
+    pub fn preserve_pitch(&self) -> bool {
+        self.preserve_pitch
+    }
+
+    /// Sets the playback speed multiplier, clamped to `SPEED_RANGE`
+    pub fn set_speed(&mut self, speed: f32) {
+        // round away accumulated float error from repeated steps
+        self.speed = ((speed * 100.0).round() / 100.0).clamp(SPEED_RANGE.0, SPEED_RANGE.1);
+        self.apply_speed();
+    }
+
+    pub fn toggle_preserve_pitch(&mut self) {
+        self.preserve_pitch = !self.preserve_pitch;
+        self.apply_speed();
+    }
+
+    /// Splits the speed between the stretcher and the sink, depending on whether pitch is kept
+    fn apply_speed(&self) {
+        let (tempo, rate) = match self.preserve_pitch {
+            true => (self.speed, 1.0),
+            false => (1.0, self.speed),
+        };
+        self.tempo.set(tempo);
+        self.sink.set_speed(rate);
+        if let Some((sink, _)) = &self.outgoing {
+            sink.set_speed(rate);
+        }
+    }
+
+    /// The track presently loaded for playback, if any
+    pub fn playing(&self) -> Option<&Rc<AudioTrack>> {
+        self.current.as_ref().map(|l| &l.track)
+    }
+
+    /// Stream being connected to before it starts playing, if any
+    pub fn connecting(&self) -> Option<&Rc<AudioTrack>> {
+        self.opening
+            .as_ref()
+            .filter(|o| !o.resume)
+            .map(|o| &o.track)
+    }
+
+    /// Title of the song playing on a stream, where the station sends one
+    pub fn stream_title(&self) -> Option<String> {
+        self.current.as_ref()?.title.as_ref()?.get()
+    }
+
+    /// Tracks to play after the current one, in order
+    pub fn upcoming(&self) -> impl Iterator<Item = &Rc<AudioTrack>> {
+        self.preloaded
+            .iter()
+            .map(|l| &l.track)
+            .chain(self.queue.iter())
+    }
+
+    /// Settings of the effects chain applied to every track
+    pub fn dsp(&self) -> &DspHandle {
+        &self.dsp
+    }
+
+    /// Name of the output device being played to, if any is open
+    pub fn device(&self) -> Option<&str> {
+        self.output.as_ref().map(|o| o.name.as_str())
+    }
+
+    /// Moves playback to the named output device, or the system default for `None`, resuming
+    /// the current track from the same position
+    pub fn set_device(&mut self, name: Option<String>) {
+        self.device = name;
+        self.reconnect();
+    }
+
+    /// Reopens the output and rebuilds the current track on it, seeking back to where it was
+    fn reconnect(&mut self) {
+        let position = self.position().unwrap_or_default();
+        let paused = self.is_paused();
+
+        if let Some(next) = self.preloaded.take() {
+            self.queue.push_front(next.track);
+        }
+        self.outgoing = None;
+        self.pending = None;
+        self.sink.stop();
+
+        // drop the old stream before opening, as some devices only allow one at a time
+        self.output = None;
+        self.output = Output::open(self.device.as_deref());
+        self.sink = self.new_sink();
+        self.last_probe = Instant::now();
+
+        if let Some(current) = &self.current
+            && stream::is_stream(current.track.path())
+        {
+            if paused {
+                self.sink.pause();
+            }
+            // a stream goes on from wherever it has got to, so there is nothing to seek
+            self.opening = Some(Opening {
+                stream: Stream::open(current.track.path()),
+                track: current.track.clone(),
+                resume: true,
+            });
+            return;
+        }
+        if let Some(current) = self.current.take() {
+            let Some((source, loaded)) = self.load(current.track, 1.0) else {
+                self.events.push(PlayerEvent::Ended { skipped: true });
+                return;
+            };
+            if let (Some(a), b) = current.bounds.get() {
+                loaded.bounds.set_start(a);
+                if let Some(b) = b {
+                    loaded.bounds.set_end(b);
+                }
+            }
+            if paused {
+                self.sink.pause();
+                loaded.fader.fade_to(0.0, Duration::ZERO);
+            }
+            self.sink.append(source);
+            // seeking through the sink waits on the output callback, which never runs when
+            // idle
+            if self.output.is_some() && self.sink.try_seek(position).is_err() {
+                self.events.push(PlayerEvent::Notice(String::from(
+                    "cannot resume where the track was on the new device",
+                )));
+            }
+            self.current = Some(loaded);
+        }
+    }
+
+    /// Reconnects when the open device has failed, or when none is open or the requested
+    /// device has returned after a fallback. The devices are listed on a thread of their own,
+    /// and only while there is somewhere better to be
+    fn probe_device(&mut self, now: Instant) {
+        if self.output.as_ref().is_some_and(|o| o.is_lost()) {
+            self.probe = None;
+            self.reconnect();
+            return;
+        }
+
+        if let Some(rx) = &self.probe {
+            match rx.try_recv() {
+                Ok(available) => {
+                    self.probe = None;
+                    let found = match (&self.output, &self.device) {
+                        (None, _) => !available.is_empty(),
+                        (Some(o), Some(d)) => &o.name != d && available.contains(d),
+                        (Some(_), None) => false,
+                    };
+                    if found {
+                        self.reconnect();
+                    }
+                }
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Disconnected) => self.probe = None,
+            }
+            return;
+        }
+
+        let elsewhere = match (&self.output, &self.device) {
+            (None, _) => true,
+            (Some(o), Some(d)) => &o.name != d,
+            (Some(_), None) => false,
+        };
+        if elsewhere && now.duration_since(self.last_probe) >= DEVICE_PROBE {
+            self.last_probe = now;
+            let (tx, rx) = mpsc::channel();
+            thread::spawn(move || tx.send(devices()));
+            self.probe = Some(rx);
         }
     }
 
@@ -32,53 +381,345 @@ impl Player {
         self.queue.push_back(track);
     }
 
-    #[inline]
-    pub fn try_next(&mut self) {
+    /// Queues tracks ahead of the rest of the queue, in order. A track already preloaded for a
+    /// gapless start still plays first
+    pub fn queue_next(&mut self, tracks: Vec<Rc<AudioTrack>>) {
+        for track in tracks.into_iter().rev() {
+            self.queue.push_front(track);
+        }
+    }
+
+    /// Plays a track at once, fading out the current one, with the rest of the queue after it
+    pub fn play_now(&mut self, track: Rc<AudioTrack>) {
+        // the preloaded track would otherwise be put back ahead of this one
+        if let Some(next) = self.preloaded.take() {
+            self.queue.push_front(next.track);
+        }
+        self.queue.push_front(track);
+        if self.current.is_some() || self.opening.is_some() {
+            self.skip_one();
+        }
+    }
+
+    /// Moves playback within the current track, returning whether it could seek there
+    pub fn seek(&mut self, to: Duration) -> bool {
+        let Some(current) = &self.current else {
+            return false;
+        };
+        let to = match current.duration {
+            Some(d) => to.min(d),
+            None => to,
+        };
+        // seeking through the sink waits on the output callback, which never runs when idle
+        self.output.is_some() && self.sink.try_seek(to).is_ok()
+    }
+
+    /// Advances playback: completes deferred fades, moves between tracks and begins crossfades
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+
+        self.probe_device(now);
+
+        if let Some((action, at)) = self.pending
+            && now >= at
+        {
+            self.pending = None;
+            match action {
+                Pending::Pause => self.sink.pause(),
+                Pending::Skip => self.advance(),
+            }
+        }
+
+        if self.outgoing.as_ref().is_some_and(|(_, at)| now >= *at) {
+            self.outgoing = None;
+        }
+
+        // sink holds the current and preloaded sources; a single remaining means the
+        // preloaded source has begun
+        if self.sink.len() <= 1
+            && let Some(next) = self.preloaded.take()
+        {
+            self.begin(next);
+        }
+
+        if self.preloaded.is_none() && self.pending.is_none() && !self.sink.is_paused() {
+            self.prepare_next();
+        }
+
+        self.poll_opening();
+
         if self.sink.empty() {
             self.play_from_queue();
         }
     }
 
-    /// Plays the next track in the queue
+    /// Starts the next queued track early when the current one nears its end, either
+    /// crossfading into it or appending it behind the current source
+    fn prepare_next(&mut self) {
+        let (Some(current), Some(next)) = (&self.current, self.queue.front()) else {
+            return;
+        };
+        if current.bounds.looping() {
+            return;
+        }
+        // remaining wall clock time, accounting for speed
+        let Some(remaining) = current.duration.map(|d| {
+            d.saturating_sub(current.clock.position())
+                .div_f32(self.speed)
+        }) else {
+            return;
+        };
+
+        // a stream is connected to once the track before has ended
+        if stream::is_stream(next.path()) {
+            return;
+        }
+
+        let same_album = current.track.album().is_some() && current.track.album() == next.album();
+        let crossfade = self.config.crossfade;
+
+        if !crossfade.is_zero() && !same_album {
+            if remaining <= crossfade {
+                let next = self.queue.pop_front().expect("queue front");
+                let Some((source, loaded)) = self.load(next, 0.0) else {
+                    return;
+                };
+                let current = self.current.as_ref().expect("current track");
+                loaded.fader.fade_to(1.0, crossfade);
+                current.fader.fade_to(0.0, remaining);
+
+                let sink = self.new_sink();
+                sink.append(source);
+                let old = mem::replace(&mut self.sink, sink);
+                self.outgoing = Some((old, Instant::now() + remaining));
+                self.begin(loaded);
+            }
+        } else if remaining <= PRELOAD {
+            let next = self.queue.pop_front().expect("queue front");
+            if let Some((source, loaded)) = self.load(next, 1.0) {
+                self.sink.append(source);
+                self.preloaded = Some(loaded);
+            }
+        }
+    }
+
+    /// Plays the next track in the queue that opens, or begins connecting to it where it is a
+    /// stream
     fn play_from_queue(&mut self) {
-        match self.queue.pop_front() {
-            Some(i) => match &*i {
-                AudioTrack::Extended(a) => {
-                    let file = BufReader::new(File::open(&a.path).expect("read audio file"));
-                    let source = Decoder::new(file).expect("decode audio file");
-                    self.sink.append(source);
-                    self.last_played = Some(i.clone());
-                }
-                AudioTrack::Limited(a) => {
-                    let file = BufReader::new(File::open(&a.path).expect("read audio file"));
-                    let source = Decoder::new(file).expect("decode audio file");
-                    self.sink.append(source);
-                    self.last_played = Some(i.clone());
-                }
-            },
-            None => self.last_played = None, // curious if this writes every time or `None` abstraction knows not to
+        if self.opening.is_some() {
+            return;
+        }
+        while let Some(i) = self.queue.pop_front() {
+            if stream::is_stream(i.path()) {
+                self.end(false);
+                self.opening = Some(Opening {
+                    stream: Stream::open(i.path()),
+                    track: i,
+                    resume: false,
+                });
+                return;
+            }
+            if let Some((source, loaded)) = self.load(i, 0.0) {
+                loaded.fader.fade_to(1.0, self.config.fade);
+                self.sink.append(source);
+                self.begin(loaded);
+                return;
+            }
+        }
+        self.end(false);
+    }
+
+    /// Plays the stream being connected to once it has arrived, or reports it as failed
+    fn poll_opening(&mut self) {
+        let Some(opening) = &self.opening else {
+            return;
+        };
+        let opened = match opening.stream.try_recv() {
+            Ok(opened) => opened,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(io::Error::other("the stream stopped")),
+        };
+        let Opening { track, resume, .. } = self.opening.take().expect("opening stream");
+        let media = opened.map(|(s, title)| (Box::new(s) as Box<dyn Media>, Some(title)));
+        let Some((source, loaded)) = self.decode(media, track, 0.0) else {
+            if resume {
+                self.end(true);
+            }
+            return;
+        };
+        match resume && self.sink.is_paused() {
+            true => loaded.fader.fade_to(0.0, Duration::ZERO),
+            false => loaded.fader.fade_to(1.0, self.config.fade),
+        }
+        self.sink.append(source);
+        match resume {
+            true => self.current = Some(loaded),
+            false => self.begin(loaded),
+        }
+    }
+
+    /// Makes a loaded track the current one, the one before having played to its end
+    fn begin(&mut self, loaded: Loaded) {
+        self.end(false);
+        self.events.push(PlayerEvent::Started {
+            track: loaded.track.clone(),
+            duration: loaded.duration,
+        });
+        self.current = Some(loaded);
+    }
+
+    /// Drops the current track, if any
+    fn end(&mut self, skipped: bool) {
+        if self.current.take().is_some() {
+            self.events.push(PlayerEvent::Ended { skipped });
         }
     }
 
-    /// Toggles the sink between a `play` and `pause` state
+    /// Toggles the sink between a `play` and `pause` state, fading out before pausing and in
+    /// after resuming
     pub fn toggle_pause(&mut self) {
+        if let Some((Pending::Pause, _)) = self.pending {
+            // resumed while still fading out; fade back in from wherever the ramp reached
+            self.pending = None;
+            self.fade_current(1.0);
+            return;
+        }
         match self.sink.is_paused() {
-            true => self.sink.play(),
-            false => self.sink.pause(),
+            true => {
+                self.sink.play();
+                self.fade_current(1.0);
+            }
+            false => {
+                self.outgoing = None;
+                self.fade_current(0.0);
+                self.defer(Pending::Pause);
+            }
         }
     }
 
     /// Clears all sources from the sink and its queue, terminating playback
     pub fn clear_queue(&mut self) {
         self.sink.stop();
+        self.sink = self.new_sink();
         self.queue.clear();
-        self.last_played = None;
+        self.end(true);
+        self.preloaded = None;
+        self.opening = None;
+        self.outgoing = None;
+        self.pending = None;
     }
 
-    /// Skips to the next source in the sink queue
+    /// Skips to the next source in the sink queue, fading out the current one first
     pub fn skip_one(&mut self) {
-        self.sink.clear();
-        self.toggle_pause(); // `clear` pauses; set to play
+        if self.sink.is_paused() {
+            self.advance();
+            return;
+        }
+        self.fade_current(0.0);
+        self.defer(Pending::Skip);
+    }
+
+    /// Drops the current track and starts the one following it
+    fn advance(&mut self) {
+        if let Some(next) = self.preloaded.take() {
+            self.queue.push_front(next.track);
+        }
+        self.outgoing = None;
+        self.opening = None;
+        self.sink.stop();
+        self.sink = self.new_sink();
+        self.end(true);
         self.play_from_queue();
     }
+
+    fn fade_current(&self, gain: f32) {
+        if let Some(current) = &self.current {
+            current.fader.fade_to(gain, self.config.fade);
+        }
+    }
+
+    /// Runs `action` once the configured fade has elapsed, or immediately without one
+    fn defer(&mut self, action: Pending) {
+        self.pending = Some((action, Instant::now() + self.config.fade));
+        if self.config.fade.is_zero() {
+            self.tick();
+        }
+    }
+
+    /// Creates a sink on the current output at the current speed, or an idle one that never
+    /// plays without an output
+    fn new_sink(&self) -> Sink {
+        let sink = match &self.output {
+            Some(o) => o.sink(),
+            None => Sink::new_idle().0,
+        };
+        if !self.preserve_pitch {
+            sink.set_speed(self.speed);
+        }
+        sink
+    }
+
+    /// Opens and decodes the file of a track, passed through the stretcher and effects chain
+    /// and wrapped to start playback at the given gain. A track that cannot be opened is
+    /// reported as failed
+    fn load(
+        &mut self,
+        track: Rc<AudioTrack>,
+        gain: f32,
+    ) -> Option<(impl Source<Item = f32> + Send + 'static, Loaded)> {
+        let media = File::open(track.file()).map(|f| (Box::new(BufReader::new(f)) as _, None));
+        self.decode(media, track, gain)
+    }
+
+    /// Decodes opened media as `load` does, reporting media that failed to open
+    fn decode(
+        &mut self,
+        media: io::Result<(Box<dyn Media>, Option<StreamTitle>)>,
+        track: Rc<AudioTrack>,
+        gain: f32,
+    ) -> Option<(impl Source<Item = f32> + Send + 'static, Loaded)> {
+        match media {
+            Ok((media, title)) => match Decoder::new(media) {
+                Ok(source) => return Some(self.shape(source, track, gain, title)),
+                Err(e) => self.events.push(PlayerEvent::Failed {
+                    track,
+                    error: e.to_string(),
+                }),
+            },
+            Err(e) => self.events.push(PlayerEvent::Failed {
+                track,
+                error: e.to_string(),
+            }),
+        }
+        None
+    }
+
+    fn shape(
+        &self,
+        source: Decoder<Box<dyn Media>>,
+        track: Rc<AudioTrack>,
+        gain: f32,
+        title: Option<StreamTitle>,
+    ) -> (impl Source<Item = f32> + Send + 'static, Loaded) {
+        let (start, end) = track
+            .cue()
+            .map_or((Duration::ZERO, None), |c| (c.start, c.end));
+        let source = Excerpt::new(source.convert_samples(), start, end);
+        let duration = source.total_duration();
+        let (source, clock) = Clocked::new(source);
+        let (source, bounds) = Segment::new(source);
+        let source = Dsp::new(Stretch::new(source, self.tempo.clone()), self.dsp.clone());
+        let (source, fader) = Fade::new(source, gain);
+        (
+            source,
+            Loaded {
+                track,
+                fader,
+                clock,
+                bounds,
+                duration,
+                title,
+            },
+        )
+    }
 }
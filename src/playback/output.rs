@@ -0,0 +1,134 @@
+//! Opening and enumerating audio output devices
+//!
+//! The stream to the device is built here rather than by rodio, so that its errors reach the
+//! player rather than the terminal, and a device that has gone is noticed as it goes
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use rodio::{
+    DeviceTrait, Sink,
+    cpal::{
+        self, FromSample, SampleFormat, SizedSample, StreamConfig,
+        traits::{HostTrait, StreamTrait},
+    },
+    dynamic_mixer::{self, DynamicMixer, DynamicMixerController},
+};
+
+/// An open stream to an output device
+pub struct Output {
+    _stream: cpal::Stream,
+    mixer: Arc<DynamicMixerController<f32>>,
+    /// Set by the stream once the device has failed, as when it is unplugged
+    lost: Arc<AtomicBool>,
+    /// Name of the device the stream plays to
+    pub name: String,
+}
+
+impl Output {
+    /// Opens the named device, falling back to the system default where it cannot be found or
+    /// opened, and to any other device after that
+    pub fn open(name: Option<&str>) -> Option<Self> {
+        let host = cpal::default_host();
+
+        if let Some(name) = name
+            && let Some(output) = host
+                .output_devices()
+                .ok()
+                .and_then(|mut d| d.find(|d| d.name().is_ok_and(|n| n == name)))
+                .and_then(|d| Self::from_device(&d))
+        {
+            return Some(output);
+        }
+
+        host.default_output_device()
+            .and_then(|d| Self::from_device(&d))
+            .or_else(|| {
+                host.output_devices()
+                    .ok()?
+                    .find_map(|d| Self::from_device(&d))
+            })
+    }
+
+    /// Plays to the device in its default configuration, or in the first other it supports
+    fn from_device(device: &cpal::Device) -> Option<Self> {
+        let name = device.name().ok()?;
+        let default = device.default_output_config().ok();
+        let others = device
+            .supported_output_configs()
+            .ok()
+            .into_iter()
+            .flatten()
+            .map(|c| c.with_max_sample_rate());
+        default.into_iter().chain(others).find_map(|config| {
+            let (mixer, source) =
+                dynamic_mixer::mixer::<f32>(config.channels(), config.sample_rate().0);
+            let lost = Arc::new(AtomicBool::new(false));
+            let format = config.sample_format();
+            let config = config.config();
+            let stream = match format {
+                SampleFormat::F32 => build::<f32>(device, &config, source, lost.clone()),
+                SampleFormat::I16 => build::<i16>(device, &config, source, lost.clone()),
+                SampleFormat::I32 => build::<i32>(device, &config, source, lost.clone()),
+                SampleFormat::U16 => build::<u16>(device, &config, source, lost.clone()),
+                SampleFormat::U8 => build::<u8>(device, &config, source, lost.clone()),
+                _ => None,
+            }?;
+            stream.play().ok()?;
+            Some(Self {
+                _stream: stream,
+                mixer,
+                lost,
+                name: name.clone(),
+            })
+        })
+    }
+
+    pub fn sink(&self) -> Sink {
+        let (sink, queue) = Sink::new_idle();
+        self.mixer.add(queue);
+        sink
+    }
+
+    /// Whether the device has failed since the stream was opened
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+}
+
+/// Builds a stream feeding the device from `source`, marking `lost` should the device fail
+fn build<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut source: DynamicMixer<f32>,
+    lost: Arc<AtomicBool>,
+) -> Option<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    device
+        .build_output_stream::<T, _, _>(
+            config,
+            move |data: &mut [T], _| {
+                for d in data.iter_mut() {
+                    *d = T::from_sample(source.next().unwrap_or(0.0));
+                }
+            },
+            // an underrun is recovered from within the stream, so any error reported means
+            // the device cannot go on
+            move |_| lost.store(true, Ordering::Relaxed),
+            None,
+        )
+        .ok()
+}
+
+/// Names of every available output device. Some hosts list only the devices that can be
+/// opened, so one already in use may be left out
+pub fn devices() -> Vec<String> {
+    match cpal::default_host().output_devices() {
+        Ok(d) => d.filter_map(|d| d.name().ok()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
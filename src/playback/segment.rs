@@ -0,0 +1,175 @@
+//! Repeating a section of a source between two points, seeking back from the end of the section
+//! to its start on the exact sample it is reached
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{Source, source::SeekError};
+
+/// Marks an unset point
+const UNSET: u64 = u64::MAX;
+
+/// Loop points shared between a `Segment` and the UI, in samples across all channels
+struct Points {
+    start: AtomicU64,
+    end: AtomicU64,
+}
+
+/// Handle for setting the loop points of a playing `Segment`
+#[derive(Clone)]
+pub struct Bounds {
+    points: Arc<Points>,
+    sample_rate: u64,
+    channels: u64,
+}
+
+/// Source repeating the section between its loop points once both are set
+pub struct Segment<S> {
+    inner: S,
+    points: Arc<Points>,
+    count: u64,
+    rate: u64,
+}
+
+impl Bounds {
+    /// Converts to a sample count on a frame boundary, so a loop never splits a frame
+    fn to_samples(&self, d: Duration) -> u64 {
+        (d.as_secs_f64() * self.sample_rate as f64) as u64 * self.channels
+    }
+
+    fn to_duration(&self, samples: u64) -> Option<Duration> {
+        let rate = (self.sample_rate * self.channels).max(1);
+        match samples {
+            UNSET => None,
+            n => Some(Duration::from_secs_f64(n as f64 / rate as f64)),
+        }
+    }
+
+    /// Start and end of the loop, each where set
+    pub fn get(&self) -> (Option<Duration>, Option<Duration>) {
+        (
+            self.to_duration(self.points.start.load(Ordering::Relaxed)),
+            self.to_duration(self.points.end.load(Ordering::Relaxed)),
+        )
+    }
+
+    /// Whether both points are set, so playback repeats rather than reaching the end
+    pub fn looping(&self) -> bool {
+        matches!(self.get(), (Some(_), Some(_)))
+    }
+
+    /// Sets the start of the loop, clearing an end that would now precede it
+    pub fn set_start(&self, at: Duration) {
+        let at = self.to_samples(at);
+        if self.points.end.load(Ordering::Relaxed) <= at {
+            self.points.end.store(UNSET, Ordering::Relaxed);
+        }
+        self.points.start.store(at, Ordering::Relaxed);
+    }
+
+    /// Sets the end of the loop, starting it from the beginning of the track where no start
+    /// is set. An end at or before the start is ignored
+    pub fn set_end(&self, at: Duration) {
+        let at = self.to_samples(at);
+        if self.points.start.load(Ordering::Relaxed) == UNSET {
+            self.points.start.store(0, Ordering::Relaxed);
+        }
+        if at > self.points.start.load(Ordering::Relaxed) {
+            self.points.end.store(at, Ordering::Relaxed);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.points.end.store(UNSET, Ordering::Relaxed);
+        self.points.start.store(UNSET, Ordering::Relaxed);
+    }
+}
+
+impl<S> Segment<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(inner: S) -> (Self, Bounds) {
+        let points = Arc::new(Points {
+            start: AtomicU64::new(UNSET),
+            end: AtomicU64::new(UNSET),
+        });
+        let bounds = Bounds {
+            points: points.clone(),
+            sample_rate: inner.sample_rate() as u64,
+            channels: inner.channels() as u64,
+        };
+        let segment = Self {
+            inner,
+            points,
+            count: 0,
+            rate: bounds.sample_rate * bounds.channels,
+        };
+        (segment, bounds)
+    }
+}
+
+impl<S> Iterator for Segment<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let end = self.points.end.load(Ordering::Relaxed);
+        if self.count >= end {
+            let start = self.points.start.load(Ordering::Relaxed);
+            let to = Duration::from_secs_f64(start as f64 / self.rate.max(1) as f64);
+            match self.inner.try_seek(to) {
+                Ok(()) => self.count = start,
+                // the format cannot seek; play on rather than loop
+                Err(_) => self.points.end.store(UNSET, Ordering::Relaxed),
+            }
+        }
+        self.count += 1;
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S> Source for Segment<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)?;
+        self.count = (pos.as_secs_f64() * self.rate as f64) as u64;
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+//! Tempo change without pitch shift, by waveform similarity overlap-add (WSOLA)
+//!
+//! Windows of input are overlap-added at a fixed synthesis hop while the analysis position
+//! advances by the hop scaled by tempo. Each window is nudged within a small search range to
+//! the offset best matching the natural continuation of the previous one, which keeps the
+//! waveform coherent across joins
+
+use std::{
+    f32::consts::PI,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{Source, source::SeekError};
+
+/// Length of each overlap-added window
+const WINDOW: Duration = Duration::from_millis(40);
+/// Distance either side of the analysis position searched for the best matching window
+const SEARCH: Duration = Duration::from_millis(8);
+/// Only every nth frame is compared when searching, trading accuracy for speed
+const DECIMATION: usize = 4;
+
+/// Handle setting the tempo of every `Stretch` sharing it, where 1.0 is unchanged
+#[derive(Clone)]
+pub struct Tempo(Arc<AtomicU32>);
+
+/// Source playing its inner source at the shared tempo, with pitch unchanged
+pub struct Stretch<S> {
+    inner: S,
+    tempo: Tempo,
+    channels: usize,
+    /// Window length and synthesis hop, in frames
+    window: usize,
+    hop: usize,
+    search: usize,
+    hann: Vec<f32>,
+    /// Interleaved input not yet consumed by analysis
+    input: Vec<f32>,
+    /// Frame of `input` the next window is taken around
+    analysis: f64,
+    /// Frame of `input` continuing naturally from the previous window, which the next window
+    /// is matched against
+    continuation: Option<usize>,
+    /// Overlap-add buffer of one window
+    accumulator: Vec<f32>,
+    /// Finished samples awaiting output
+    output: Vec<f32>,
+    position: usize,
+    exhausted: bool,
+}
+
+impl Tempo {
+    pub fn new(tempo: f32) -> Self {
+        Self(Arc::new(AtomicU32::new(tempo.to_bits())))
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, tempo: f32) {
+        self.0.store(tempo.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl<S> Stretch<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(inner: S, tempo: Tempo) -> Self {
+        let rate = inner.sample_rate() as f32;
+        let channels = inner.channels().max(1) as usize;
+        let window = ((WINDOW.as_secs_f32() * rate) as usize).max(16) & !1;
+        let hann = (0..window)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / window as f32).cos())
+            .collect();
+
+        Self {
+            inner,
+            tempo,
+            channels,
+            window,
+            hop: window / 2,
+            search: (SEARCH.as_secs_f32() * rate) as usize,
+            hann,
+            input: Vec::new(),
+            analysis: 0.0,
+            continuation: None,
+            accumulator: vec![0.0; window * channels],
+            output: Vec::new(),
+            position: 0,
+            exhausted: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.input.clear();
+        self.analysis = 0.0;
+        self.continuation = None;
+        self.accumulator.fill(0.0);
+        self.output.clear();
+        self.position = 0;
+        self.exhausted = false;
+    }
+
+    fn reset_keeping_output(&mut self) {
+        let output = std::mem::take(&mut self.output);
+        self.reset();
+        self.output = output;
+    }
+
+    fn frames(&self) -> usize {
+        self.input.len() / self.channels
+    }
+
+    /// Sum of all channels of one input frame
+    #[inline]
+    fn mono(&self, frame: usize) -> f32 {
+        self.input[frame * self.channels..(frame + 1) * self.channels]
+            .iter()
+            .sum()
+    }
+
+    /// Reads input until `frames` are buffered or the inner source ends
+    fn buffer(&mut self, frames: usize) {
+        while !self.exhausted && self.frames() < frames {
+            match self.inner.next() {
+                Some(s) => self.input.push(s),
+                None => self.exhausted = true,
+            }
+        }
+        // pad a partial final frame so every frame has all channels
+        while !self.input.len().is_multiple_of(self.channels) {
+            self.input.push(0.0);
+        }
+    }
+
+    /// Offset from `target` whose window best matches the continuation of the previous window
+    fn best_offset(&self, target: usize, natural: usize) -> usize {
+        let low = target.saturating_sub(self.search);
+        let high = (target + self.search).min(self.frames().saturating_sub(self.window));
+        let span = self.window / 2;
+        if natural + span > self.frames() || low >= high {
+            return target.min(high);
+        }
+
+        let mut best = (f32::MIN, target.min(high));
+        for candidate in low..=high {
+            let score: f32 = (0..span)
+                .step_by(DECIMATION)
+                .map(|i| self.mono(candidate + i) * self.mono(natural + i))
+                .sum();
+            if score > best.0 {
+                best = (score, candidate);
+            }
+        }
+        best.1
+    }
+
+    /// Overlap-adds the next window, refilling `output` with one hop of samples. Returns false
+    /// once the input is spent and the accumulator flushed
+    fn synthesise(&mut self, tempo: f32) -> bool {
+        let target = self.analysis.round() as usize;
+        self.buffer(target + self.search + self.window);
+
+        if target + self.window > self.frames() {
+            if self.exhausted && self.continuation.is_some() {
+                // emit what remains of the final window once
+                self.continuation = None;
+                self.output.clear();
+                self.output.extend_from_slice(&self.accumulator);
+                self.accumulator.fill(0.0);
+                self.position = 0;
+                return !self.output.is_empty();
+            }
+            return false;
+        }
+
+        let start = match self.continuation {
+            Some(c) => self.best_offset(target, c),
+            None => target,
+        };
+
+        let ch = self.channels;
+        for i in 0..self.window {
+            let w = self.hann[i];
+            for c in 0..ch {
+                self.accumulator[i * ch + c] += self.input[(start + i) * ch + c] * w;
+            }
+        }
+
+        self.output.clear();
+        self.output
+            .extend_from_slice(&self.accumulator[..self.hop * ch]);
+        self.accumulator.copy_within(self.hop * ch.., 0);
+        let len = self.accumulator.len();
+        self.accumulator[len - self.hop * ch..].fill(0.0);
+        self.position = 0;
+
+        self.continuation = Some(start + self.hop);
+        self.analysis += self.hop as f64 * tempo as f64;
+
+        // discard input behind both the next analysis window and the next natural continuation
+        let spent = (self.analysis as usize)
+            .saturating_sub(self.search)
+            .min(start + self.hop);
+        if spent > self.window * 4 {
+            self.input.drain(..spent * ch);
+            self.analysis -= spent as f64;
+            self.continuation = Some(start + self.hop - spent);
+        }
+        true
+    }
+}
+
+impl<S> Iterator for Stretch<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.position < self.output.len() {
+            self.position += 1;
+            return Some(self.output[self.position - 1]);
+        }
+
+        let tempo = self.tempo.get();
+        if tempo == 1.0 {
+            if self.continuation.is_some() || !self.input.is_empty() {
+                // leaving stretched playback: emit the unread input, then pass through
+                let from = (self.analysis.round() as usize * self.channels).min(self.input.len());
+                self.output = self.input.split_off(from);
+                self.reset_keeping_output();
+                if !self.output.is_empty() {
+                    return self.next();
+                }
+            }
+            return self.inner.next();
+        }
+
+        if self.synthesise(tempo.clamp(0.25, 4.0)) {
+            self.next()
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+impl<S> Source for Stretch<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        match self.tempo.get() == 1.0 && self.position >= self.output.len() {
+            true => self.inner.current_frame_len(),
+            false => None,
+        }
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.reset();
+        self.inner.try_seek(pos)
+    }
+}
@@ -0,0 +1,74 @@
+//! Playlists, saved as M3U files of absolute paths under the configuration directory, and
+//! smart playlists defined in the configuration
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::config;
+
+mod smart;
+
+pub use smart::SmartPlaylist;
+
+const EXTENSION: &str = "m3u";
+const HEADER: &str = "#EXTM3U";
+
+fn dir() -> PathBuf {
+    config::config_dir().join("playlists")
+}
+
+/// Names of the saved playlists, sorted
+pub fn names() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == EXTENSION))
+        .filter_map(|p| Some(p.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Paths of the tracks of a playlist, in order
+pub fn read(name: &str) -> io::Result<Vec<String>> {
+    let text = fs::read_to_string(dir().join(format!("{name}.{EXTENSION}")))?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_owned)
+        .collect())
+}
+
+/// Adds tracks to the end of a playlist, creating it where it does not exist
+pub fn append<'a>(name: &str, paths: impl IntoIterator<Item = &'a str>) -> io::Result<()> {
+    if name.is_empty() || name.contains(['/', '\0']) || name.starts_with('.') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{name}` cannot name a playlist"),
+        ));
+    }
+    fs::create_dir_all(dir())?;
+    let path = dir().join(format!("{name}.{EXTENSION}"));
+
+    let mut out = String::new();
+    if !path.exists() {
+        out.push_str(HEADER);
+        out.push('\n');
+    }
+    for p in paths {
+        out.push_str(p);
+        out.push('\n');
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(out.as_bytes())
+}
@@ -0,0 +1,91 @@
+//! Playlists defined by a query rather than a list of tracks, evaluated against the library
+//! each time they are used
+
+use std::{rc::Rc, str::FromStr};
+
+use crate::loader::AudioTrack;
+use crate::query::{Query, Sort, SortKey};
+use crate::stats::TrackData;
+
+#[derive(Clone)]
+pub struct SmartPlaylist {
+    pub name: String,
+    /// Query as written in the configuration
+    pub rules: String,
+    /// Parsed rules, or what was not understood in them, in which case nothing matches
+    query: Result<Query, String>,
+    /// Most tracks taken, after ordering
+    pub limit: Option<usize>,
+    pub order: Order,
+}
+
+/// Order the tracks of a smart playlist are taken and queued in
+#[derive(Clone, Copy)]
+pub enum Order {
+    Random,
+    Sorted(Sort),
+}
+
+impl SmartPlaylist {
+    /// A playlist of the whole library, until given rules
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            rules: String::new(),
+            query: Query::parse(""),
+            limit: None,
+            order: Order::Sorted(Sort::default()),
+        }
+    }
+
+    pub fn set_rules(&mut self, rules: &str) -> Result<(), String> {
+        self.rules = rules.to_owned();
+        self.query = Query::parse(rules);
+        self.query.as_ref().map(|_| ()).map_err(Clone::clone)
+    }
+
+    /// What was not understood in the rules
+    pub fn error(&self) -> Option<&str> {
+        self.query.as_ref().err().map(String::as_str)
+    }
+
+    /// Tracks of the library matching the rules, ordered and limited
+    pub fn evaluate(
+        &self,
+        tracks: &[Rc<AudioTrack>],
+        data: impl Fn(&AudioTrack) -> TrackData,
+    ) -> Vec<Rc<AudioTrack>> {
+        let Ok(query) = &self.query else {
+            return Vec::new();
+        };
+        let mut rows = query.select(tracks, data);
+        match self.order {
+            Order::Random => fastrand::shuffle(&mut rows),
+            Order::Sorted(sort) => sort.apply(&mut rows),
+        }
+        if let Some(n) = self.limit {
+            rows.truncate(n);
+        }
+        rows.into_iter().map(|r| r.track).collect()
+    }
+}
+
+/// `random`, or a column such as `rating`, followed by `desc` for the greatest first
+impl FromStr for Order {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let mut words = s.split_whitespace();
+        let key = match words.next() {
+            Some("random") => return Ok(Order::Random),
+            Some(w) => w.parse::<SortKey>()?,
+            None => return Err(()),
+        };
+        let descending = match words.next() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(_) => return Err(()),
+        };
+        Ok(Order::Sorted(Sort { key, descending }))
+    }
+}
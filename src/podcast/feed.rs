@@ -0,0 +1,280 @@
+//! RSS 2.0 and Atom feeds, read for the episodes they offer
+//!
+//! Elements are matched by local name, so `itunes:duration` reads as `duration`. Dates are
+//! given back as ISO 8601, which the database reads as it stands
+
+use std::{io, time::Duration};
+
+use roxmltree::{Document, Node};
+
+/// A feed as last fetched
+pub struct Channel {
+    pub title: String,
+    pub items: Vec<Item>,
+}
+
+/// An episode as a feed lists it; entries without audio are left out
+pub struct Item {
+    /// Identifier kept across fetches, the audio URL where the feed gives none
+    pub guid: String,
+    pub title: String,
+    /// Where the audio is downloaded from
+    pub url: String,
+    /// When published, as ISO 8601
+    pub published: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Zones named in RFC 2822, other than those of Greenwich
+const ZONES: [(&str, &str); 8] = [
+    ("EST", "-05:00"),
+    ("EDT", "-04:00"),
+    ("CST", "-06:00"),
+    ("CDT", "-05:00"),
+    ("MST", "-07:00"),
+    ("MDT", "-06:00"),
+    ("PST", "-08:00"),
+    ("PDT", "-07:00"),
+];
+
+pub fn parse(text: &str) -> io::Result<Channel> {
+    let doc = Document::parse(text).map_err(|e| invalid(format!("not a feed: {e}")))?;
+    let root = doc.root_element();
+    match root.tag_name().name() {
+        "rss" => match child(root, "channel") {
+            Some(channel) => Ok(rss(channel)),
+            None => Err(invalid(String::from("not a feed: no channel"))),
+        },
+        "feed" => Ok(atom(root)),
+        other => Err(invalid(format!("not a feed: <{other}>"))),
+    }
+}
+
+fn rss(channel: Node) -> Channel {
+    let items = children(channel, "item")
+        .filter_map(|item| {
+            let url = child(item, "enclosure")?.attribute("url")?.to_owned();
+            Some(Item {
+                guid: text(item, "guid").unwrap_or_else(|| url.clone()),
+                title: text(item, "title").unwrap_or_else(|| url.clone()),
+                published: text(item, "pubDate").and_then(|d| rfc2822(&d)),
+                duration: text(item, "duration").and_then(|d| duration(&d)),
+                url,
+            })
+        })
+        .collect();
+    Channel {
+        title: text(channel, "title").unwrap_or_default(),
+        items,
+    }
+}
+
+fn atom(feed: Node) -> Channel {
+    let items = children(feed, "entry")
+        .filter_map(|entry| {
+            let enclosure =
+                children(entry, "link").find(|l| l.attribute("rel") == Some("enclosure"))?;
+            let url = enclosure.attribute("href")?.to_owned();
+            Some(Item {
+                guid: text(entry, "id").unwrap_or_else(|| url.clone()),
+                title: text(entry, "title").unwrap_or_else(|| url.clone()),
+                published: text(entry, "published").or_else(|| text(entry, "updated")),
+                duration: text(entry, "duration").and_then(|d| duration(&d)),
+                url,
+            })
+        })
+        .collect();
+    Channel {
+        title: text(feed, "title").unwrap_or_default(),
+        items,
+    }
+}
+
+fn children<'a, 'i>(node: Node<'a, 'i>, name: &'a str) -> impl Iterator<Item = Node<'a, 'i>> {
+    node.children()
+        .filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, name: &'a str) -> Option<Node<'a, 'i>> {
+    children(node, name).next()
+}
+
+/// Text of the first child element named, trimmed, where it holds any
+fn text(node: Node, name: &str) -> Option<String> {
+    let text: String = child(node, name)?
+        .descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+/// Reads an RFC 2822 date, such as `Tue, 10 Jun 2003 04:00:00 GMT`, as ISO 8601
+fn rfc2822(date: &str) -> Option<String> {
+    let date = date.split_once(',').map_or(date, |(_, rest)| rest);
+    let mut parts = date.split_whitespace();
+    let day: u32 = parts.next()?.parse().ok()?;
+    let month = parts.next()?.to_lowercase();
+    let month = MONTHS.iter().position(|m| month.starts_with(m))? + 1;
+    let year: u32 = match parts.next()?.parse().ok()? {
+        y @ 0..50 => y + 2000,
+        y @ 50..100 => y + 1900,
+        y => y,
+    };
+    let time = parts.next().unwrap_or("00:00:00");
+    let time = match time.len() {
+        5 => format!("{time}:00"),
+        _ => time.to_owned(),
+    };
+    let zone = parts.next().unwrap_or("GMT");
+    let zone = match ZONES.iter().find(|(name, _)| *name == zone) {
+        Some((_, offset)) => (*offset).to_owned(),
+        None if zone.len() == 5 && zone.is_ascii() && zone.starts_with(['+', '-']) => {
+            format!("{}:{}", &zone[..3], &zone[3..])
+        }
+        // GMT, UT and anything unknown
+        None => String::from("+00:00"),
+    };
+    Some(format!("{year:04}-{month:02}-{day:02}T{time}{zone}"))
+}
+
+/// Reads a running time given as seconds or as `[hours:]minutes:seconds`
+fn duration(text: &str) -> Option<Duration> {
+    let seconds = text.split(':').try_fold(0.0, |total, part| {
+        Some(total * 60.0 + part.trim().parse::<f64>().ok()?)
+    })?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rss_items_with_audio() {
+        let channel = parse(include_str!("fixtures/feed.rss")).unwrap();
+        assert_eq!(channel.title, "Talking Shop");
+        let items: Vec<_> = channel
+            .items
+            .iter()
+            .map(|i| {
+                (
+                    i.guid.as_str(),
+                    i.title.as_str(),
+                    i.url.as_str(),
+                    i.published.as_deref(),
+                    i.duration.map(|d| d.as_secs()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            items,
+            [
+                (
+                    "ep-1",
+                    "First & Foremost",
+                    "https://example.com/1.mp3",
+                    Some("2003-06-10T12:00:00+00:00"),
+                    Some(3723),
+                ),
+                (
+                    "https://example.com/2.ogg?from=feed",
+                    "Second <draft>",
+                    "https://example.com/2.ogg?from=feed",
+                    Some("2003-06-11T15:00:00+02:00"),
+                    Some(75),
+                ),
+                (
+                    "ep-3",
+                    "https://example.com/3.mp3",
+                    "https://example.com/3.mp3",
+                    Some("2003-06-12T08:00:00-05:00"),
+                    Some(2730),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn atom_entries_with_audio() {
+        let channel = parse(include_str!("fixtures/feed.atom")).unwrap();
+        assert_eq!(channel.title, "Atomic Hour");
+        let items: Vec<_> = channel
+            .items
+            .iter()
+            .map(|i| {
+                (
+                    i.guid.as_str(),
+                    i.title.as_str(),
+                    i.url.as_str(),
+                    i.published.as_deref(),
+                    i.duration.map(|d| d.as_secs()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            items,
+            [
+                (
+                    "urn:example:atomic:1",
+                    "Split",
+                    "https://example.com/atomic/1.mp3",
+                    Some("2024-03-01T18:00:00+01:00"),
+                    Some(3600),
+                ),
+                (
+                    "https://example.com/atomic/2.mp3",
+                    "Fused",
+                    "https://example.com/atomic/2.mp3",
+                    Some("2024-02-01T18:00:00Z"),
+                    None,
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn not_a_feed() {
+        assert!(parse("<html><body/></html>").is_err());
+        assert!(parse("<rss version=\"2.0\"/>").is_err());
+        assert!(parse("plain text").is_err());
+    }
+
+    #[test]
+    fn durations() {
+        let secs = |t| duration(t).map(|d: Duration| d.as_secs_f64());
+        assert_eq!(secs("90"), Some(90.0));
+        assert_eq!(secs("12.5"), Some(12.5));
+        assert_eq!(secs("3:05"), Some(185.0));
+        assert_eq!(secs("01:00:01"), Some(3601.0));
+        assert_eq!(secs("half an hour"), None);
+        assert_eq!(secs("-5"), None);
+    }
+
+    #[test]
+    fn rfc2822_dates() {
+        assert_eq!(
+            rfc2822("Sat, 01 Feb 2025 23:59:59 PDT").as_deref(),
+            Some("2025-02-01T23:59:59-07:00")
+        );
+        assert_eq!(
+            rfc2822("5 January 1999 10:15 -0330").as_deref(),
+            Some("1999-01-05T10:15:00-03:30")
+        );
+        assert_eq!(
+            rfc2822("Mon, 7 Jul 69 00:00:00 UT").as_deref(),
+            Some("1969-07-07T00:00:00+00:00")
+        );
+        assert_eq!(rfc2822("yesterday"), None);
+        assert_eq!(rfc2822("Mon, 7 Smarch 2020"), None);
+    }
+}
@@ -0,0 +1,715 @@
+//! Podcasts: feeds subscribed to, the episodes they list and how far each was heard
+//!
+//! Feeds are fetched and episodes downloaded on threads of their own, through a `Fetch`, so
+//! anything able to serve files may stand in for the network. Subscriptions, episodes and where
+//! playback of each stopped are kept in a local SQLite database
+
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use rusqlite::{Connection, params};
+
+use crate::config;
+use crate::http;
+use crate::loader::AudioTrack;
+use crate::playback::PlayerEvent;
+use crate::stream;
+
+mod feed;
+
+use feed::Channel;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS feeds (
+        id INTEGER PRIMARY KEY,
+        url TEXT NOT NULL UNIQUE,
+        title TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS episodes (
+        id INTEGER PRIMARY KEY,
+        feed INTEGER NOT NULL,
+        guid TEXT NOT NULL,
+        title TEXT NOT NULL,
+        url TEXT NOT NULL,
+        published INTEGER,
+        duration REAL,
+        file TEXT,
+        position REAL NOT NULL DEFAULT 0,
+        finished INTEGER NOT NULL DEFAULT 0,
+        UNIQUE (feed, guid)
+    );
+";
+
+/// How often the position of the episode playing is saved
+const SAVE_EVERY: Duration = Duration::from_secs(10);
+
+pub struct Feed {
+    pub id: i64,
+    pub url: String,
+    pub title: String,
+    /// Episodes listed and not yet finished
+    pub unfinished: u32,
+}
+
+pub struct Episode {
+    pub id: i64,
+    pub title: String,
+    /// Where the audio is downloaded or streamed from
+    pub url: String,
+    /// Day published, as `YYYY-MM-DD`
+    pub published: Option<String>,
+    pub duration: Option<Duration>,
+    /// Where the episode was downloaded to
+    pub file: Option<PathBuf>,
+    /// Where playback last stopped
+    pub position: Duration,
+    pub finished: bool,
+}
+
+/// Where feeds and episodes are read from
+pub trait Fetch: Send + Sync {
+    /// Reads a whole feed
+    fn feed(&self, url: &str) -> io::Result<Vec<u8>>;
+    /// Copies the audio of an episode into `to`, returning the bytes written
+    fn episode(&self, url: &str, to: &mut dyn Write) -> io::Result<u64>;
+}
+
+/// Fetches over HTTP and HTTPS, reading `file://` URLs and paths from disk
+pub struct Web;
+
+/// A fetch or download completed on its thread
+enum Done {
+    Fetched {
+        feed: i64,
+        url: String,
+        channel: io::Result<Channel>,
+    },
+    Downloaded {
+        episode: i64,
+        file: io::Result<PathBuf>,
+    },
+}
+
+pub struct Podcasts {
+    db: Connection,
+    /// Directory episodes are downloaded into, in a directory for each feed
+    directory: PathBuf,
+    fetch: Arc<dyn Fetch>,
+    sender: Sender<Done>,
+    done: Receiver<Done>,
+    /// Feeds being fetched
+    fetching: HashSet<i64>,
+    /// Episodes being downloaded
+    downloading: HashSet<i64>,
+    playing: Option<Playing>,
+    /// Changes made since opening, to tell when what was read before is out of date
+    pub changes: u64,
+}
+
+/// The episode played, from when it is asked for until it stops
+struct Playing {
+    episode: i64,
+    /// Path or URL of the track playing it
+    path: String,
+    /// Where playback resumes once it starts
+    resume: Duration,
+    started: bool,
+    position: Duration,
+    saved: Instant,
+}
+
+impl Podcasts {
+    /// Opens the database in the data directory, downloading into `directory`
+    pub fn open(directory: PathBuf) -> rusqlite::Result<Self> {
+        let dir = config::data_dir();
+        // a missing directory shows up as the database failing to open
+        let _ = fs::create_dir_all(&dir);
+        Self::open_at(&dir.join("podcasts.db"), directory, Arc::new(Web))
+    }
+
+    /// Opens the database at `path`, fetching feeds and episodes through `fetch`
+    pub fn open_at(
+        path: &Path,
+        directory: PathBuf,
+        fetch: Arc<dyn Fetch>,
+    ) -> rusqlite::Result<Self> {
+        let db = Connection::open(path)?;
+        db.execute_batch(SCHEMA)?;
+        let (sender, done) = mpsc::channel();
+        Ok(Self {
+            db,
+            directory,
+            fetch,
+            sender,
+            done,
+            fetching: HashSet::new(),
+            downloading: HashSet::new(),
+            playing: None,
+            changes: 0,
+        })
+    }
+
+    /// Feeds subscribed to, by title
+    pub fn feeds(&self) -> rusqlite::Result<Vec<Feed>> {
+        let mut stmt = self.db.prepare(
+            "SELECT feeds.id, feeds.url, feeds.title, coalesce(sum(episodes.finished = 0), 0)
+             FROM feeds LEFT JOIN episodes ON episodes.feed = feeds.id
+             GROUP BY feeds.id ORDER BY feeds.title COLLATE NOCASE",
+        )?;
+        stmt.query_map([], |row| {
+            Ok(Feed {
+                id: row.get(0)?,
+                url: row.get(1)?,
+                title: row.get(2)?,
+                unfinished: row.get(3)?,
+            })
+        })?
+        .collect()
+    }
+
+    /// Episodes of a feed, the latest first
+    pub fn episodes(&self, feed: i64) -> rusqlite::Result<Vec<Episode>> {
+        let mut stmt = self.db.prepare(
+            "SELECT id, title, url, date(published, 'unixepoch', 'localtime'), duration, file,
+                 position, finished
+             FROM episodes WHERE feed = ?1 ORDER BY published DESC, id",
+        )?;
+        stmt.query_map([feed], |row| {
+            Ok(Episode {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                url: row.get(2)?,
+                published: row.get(3)?,
+                duration: row.get::<_, Option<f64>>(4)?.map(Duration::from_secs_f64),
+                file: row.get::<_, Option<String>>(5)?.map(PathBuf::from),
+                position: Duration::from_secs_f64(row.get(6)?),
+                finished: row.get(7)?,
+            })
+        })?
+        .collect()
+    }
+
+    /// Subscribes to the feed at `url`, fetching it straight away
+    pub fn subscribe(&mut self, url: &str) -> rusqlite::Result<()> {
+        self.db.execute(
+            "INSERT OR IGNORE INTO feeds (url, title) VALUES (?1, ?1)",
+            [url],
+        )?;
+        let feed = self
+            .db
+            .query_row("SELECT id FROM feeds WHERE url = ?1", [url], |row| {
+                row.get(0)
+            })?;
+        self.changes += 1;
+        self.fetch_feed(feed, url.to_owned());
+        Ok(())
+    }
+
+    /// Forgets a feed and its episodes, leaving any downloads on disk
+    pub fn unsubscribe(&mut self, feed: i64) -> rusqlite::Result<()> {
+        let tx = self.db.transaction()?;
+        tx.execute("DELETE FROM episodes WHERE feed = ?1", [feed])?;
+        tx.execute("DELETE FROM feeds WHERE id = ?1", [feed])?;
+        tx.commit()?;
+        self.changes += 1;
+        Ok(())
+    }
+
+    /// Fetches every feed again, returning how many there are
+    pub fn refresh(&mut self) -> rusqlite::Result<usize> {
+        let feeds = self.feeds()?;
+        let count = feeds.len();
+        feeds.into_iter().for_each(|f| self.fetch_feed(f.id, f.url));
+        Ok(count)
+    }
+
+    fn fetch_feed(&mut self, feed: i64, url: String) {
+        if !self.fetching.insert(feed) {
+            return;
+        }
+        let (fetch, sender) = (self.fetch.clone(), self.sender.clone());
+        thread::spawn(move || {
+            let channel = fetch
+                .feed(&url)
+                .and_then(|bytes| feed::parse(&String::from_utf8_lossy(&bytes)));
+            let _ = sender.send(Done::Fetched { feed, url, channel });
+        });
+    }
+
+    /// Downloads an episode into the directory of its feed
+    pub fn download(&mut self, feed: &Feed, episode: &Episode) {
+        if !self.downloading.insert(episode.id) {
+            return;
+        }
+        // episodes of a feed may share a title, so each is named for its id as well
+        let name = format!(
+            "{} [{}].{}",
+            file_name(&episode.title),
+            episode.id,
+            extension(&episode.url)
+        );
+        let to = self.directory.join(file_name(&feed.title)).join(name);
+        let (fetch, sender) = (self.fetch.clone(), self.sender.clone());
+        let (id, url) = (episode.id, episode.url.clone());
+        thread::spawn(move || {
+            let file = download(fetch.as_ref(), &url, &to).map(|()| to);
+            let _ = sender.send(Done::Downloaded { episode: id, file });
+        });
+    }
+
+    pub fn is_fetching(&self, feed: i64) -> bool {
+        self.fetching.contains(&feed)
+    }
+
+    pub fn is_downloading(&self, episode: i64) -> bool {
+        self.downloading.contains(&episode)
+    }
+
+    /// Fetches and downloads in progress
+    pub fn busy(&self) -> (usize, usize) {
+        (self.fetching.len(), self.downloading.len())
+    }
+
+    /// Takes in the fetches and downloads completed since last called, returning any failure
+    /// to tell of
+    pub fn poll(&mut self) -> Option<String> {
+        let mut notice = None;
+        while let Ok(done) = self.done.try_recv() {
+            let failed = match done {
+                Done::Fetched { feed, url, channel } => {
+                    self.fetching.remove(&feed);
+                    match channel {
+                        Ok(c) => self.store(feed, &c).err().map(|e| e.to_string()),
+                        Err(e) => Some(format!("cannot fetch {url}: {e}")),
+                    }
+                }
+                Done::Downloaded { episode, file } => {
+                    self.downloading.remove(&episode);
+                    match file {
+                        Ok(f) => self.set_file(episode, &f).err().map(|e| e.to_string()),
+                        Err(e) => Some(format!("cannot download episode: {e}")),
+                    }
+                }
+            };
+            notice = failed.or(notice);
+        }
+        notice
+    }
+
+    /// Keeps the episodes of a feed as last fetched, with where each was heard to
+    fn store(&mut self, feed: i64, channel: &Channel) -> rusqlite::Result<()> {
+        let tx = self.db.transaction()?;
+        let subscribed: bool =
+            tx.query_row("SELECT count(*) FROM feeds WHERE id = ?1", [feed], |row| {
+                row.get(0)
+            })?;
+        // unsubscribed from while being fetched
+        if !subscribed {
+            return Ok(());
+        }
+        if !channel.title.is_empty() {
+            tx.execute(
+                "UPDATE feeds SET title = ?2 WHERE id = ?1",
+                params![feed, channel.title],
+            )?;
+        }
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO episodes (feed, guid, title, url, published, duration)
+                 VALUES (?1, ?2, ?3, ?4, unixepoch(?5), ?6)
+                 ON CONFLICT (feed, guid) DO UPDATE SET title = excluded.title,
+                     url = excluded.url, published = excluded.published,
+                     duration = excluded.duration",
+            )?;
+            for item in &channel.items {
+                insert.execute(params![
+                    feed,
+                    item.guid,
+                    item.title,
+                    item.url,
+                    item.published,
+                    item.duration.map(|d| d.as_secs_f64()),
+                ])?;
+            }
+        }
+        tx.commit()?;
+        self.changes += 1;
+        Ok(())
+    }
+
+    fn set_file(&mut self, episode: i64, file: &Path) -> rusqlite::Result<()> {
+        self.db.execute(
+            "UPDATE episodes SET file = ?2 WHERE id = ?1",
+            params![episode, file.to_string_lossy()],
+        )?;
+        self.changes += 1;
+        Ok(())
+    }
+
+    pub fn set_position(&mut self, episode: i64, position: Duration) -> rusqlite::Result<()> {
+        self.db.execute(
+            "UPDATE episodes SET position = ?2 WHERE id = ?1",
+            params![episode, position.as_secs_f64()],
+        )?;
+        self.changes += 1;
+        Ok(())
+    }
+
+    /// Marks an episode finished or not, either way to be heard from the start
+    pub fn set_finished(&mut self, episode: i64, finished: bool) -> rusqlite::Result<()> {
+        self.db.execute(
+            "UPDATE episodes SET finished = ?2, position = 0 WHERE id = ?1",
+            params![episode, finished],
+        )?;
+        self.changes += 1;
+        Ok(())
+    }
+
+    /// Track playing an episode, from its download where there is one and otherwise from its
+    /// URL. Its position is followed once it starts
+    pub fn play(&mut self, episode: &Episode) -> rusqlite::Result<Rc<AudioTrack>> {
+        self.save()?;
+        let path = match &episode.file {
+            Some(f) if f.exists() => f.to_string_lossy().into_owned(),
+            _ => episode.url.clone(),
+        };
+        self.playing = Some(Playing {
+            episode: episode.id,
+            path: path.clone(),
+            resume: match episode.finished {
+                true => Duration::ZERO,
+                false => episode.position,
+            },
+            started: false,
+            position: Duration::ZERO,
+            saved: Instant::now(),
+        });
+        Ok(Rc::new(AudioTrack::episode(&episode.title, &path)))
+    }
+
+    /// Follows the episode played through playback, returning where to seek to as it starts.
+    /// An episode played to its end is finished
+    pub fn handle(&mut self, event: &PlayerEvent) -> rusqlite::Result<Option<Duration>> {
+        let Some(p) = &mut self.playing else {
+            return Ok(None);
+        };
+        match event {
+            PlayerEvent::Started { track, .. } if !p.started && track.path() == p.path => {
+                p.started = true;
+                p.position = p.resume;
+                p.saved = Instant::now();
+                Ok((p.resume > Duration::ZERO).then_some(p.resume))
+            }
+            PlayerEvent::Ended { skipped } if p.started => {
+                let (episode, position) = (p.episode, p.position);
+                self.playing = None;
+                match skipped {
+                    true => self.set_position(episode, position)?,
+                    false => self.set_finished(episode, true)?,
+                }
+                Ok(None)
+            }
+            PlayerEvent::Failed { track, .. } if track.path() == p.path => {
+                self.playing = None;
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Notes how far the episode playing has got, saving it now and then
+    pub fn tick(&mut self, position: Option<Duration>) -> rusqlite::Result<()> {
+        let Some(p) = self.playing.as_mut().filter(|p| p.started) else {
+            return Ok(());
+        };
+        if let Some(position) = position {
+            p.position = position;
+        }
+        if p.saved.elapsed() < SAVE_EVERY {
+            return Ok(());
+        }
+        p.saved = Instant::now();
+        let (episode, position) = (p.episode, p.position);
+        self.set_position(episode, position)
+    }
+
+    /// Saves where the episode playing has got to, as when quitting
+    pub fn save(&mut self) -> rusqlite::Result<()> {
+        match &self.playing {
+            Some(p) if p.started => self.set_position(p.episode, p.position),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Web {
+    /// Opens a URL or path, with the length it gives where it gives one
+    fn open(url: &str) -> io::Result<(Box<dyn Read>, Option<u64>)> {
+        if stream::is_stream(url) {
+            let body = http::get(url, &[])?;
+            let length = body.header("content-length").and_then(|v| v.parse().ok());
+            return Ok((Box::new(body.reader), length));
+        }
+        let path = url.strip_prefix("file://").unwrap_or(url);
+        Ok((Box::new(File::open(path)?), None))
+    }
+}
+
+impl Fetch for Web {
+    fn feed(&self, url: &str) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        Self::open(url)?.0.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Fails where fewer bytes arrive than the server said to expect, as a connection that
+    /// drops part way can look like the end of the body
+    fn episode(&self, url: &str, to: &mut dyn Write) -> io::Result<u64> {
+        let (mut reader, length) = Self::open(url)?;
+        let copied = io::copy(&mut reader, to)?;
+        match length {
+            Some(length) if length != copied => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{copied} of {length} bytes arrived"),
+            )),
+            _ => Ok(copied),
+        }
+    }
+}
+
+/// Writes an episode to `to` by way of a partial file, so a download cut short is never taken
+/// for a whole one
+fn download(fetch: &dyn Fetch, url: &str, to: &Path) -> io::Result<()> {
+    if let Some(dir) = to.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = to.with_extension("part");
+    let written = File::create(&partial).and_then(|mut file| {
+        fetch.episode(url, &mut file)?;
+        file.sync_all()
+    });
+    match written {
+        Ok(()) => fs::rename(&partial, to),
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// A title made fit to name a file
+fn file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(120)
+        .collect();
+    let name = name.trim().trim_start_matches('.');
+    match name.is_empty() {
+        true => String::from("untitled"),
+        false => name.to_owned(),
+    }
+}
+
+/// Extension of the file a URL points at, ignoring any query; mp3 where there is none
+fn extension(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name.rsplit_once('.') {
+        Some((_, ext))
+            if (1..=4).contains(&ext.len()) && ext.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            ext
+        }
+        _ => "mp3",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::http::stand_in::{self, StandIn};
+
+    /// Serves the RSS fixture at any URL ending `.rss`, and as the audio of an episode its own
+    /// URL
+    struct Fixture;
+
+    impl Fetch for Fixture {
+        fn feed(&self, url: &str) -> io::Result<Vec<u8>> {
+            match url.ends_with(".rss") {
+                true => Ok(include_bytes!("fixtures/feed.rss").to_vec()),
+                false => Err(io::Error::new(io::ErrorKind::NotFound, "no such feed")),
+            }
+        }
+
+        fn episode(&self, url: &str, to: &mut dyn Write) -> io::Result<u64> {
+            to.write_all(url.as_bytes())?;
+            Ok(url.len() as u64)
+        }
+    }
+
+    /// Podcasts in a database and download directory of their own, named for the test
+    fn podcasts(name: &str) -> (Podcasts, PathBuf) {
+        let dir = env::temp_dir().join(format!("mplay-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let podcasts = Podcasts::open_at(
+            &dir.join("podcasts.db"),
+            dir.join("episodes"),
+            Arc::new(Fixture),
+        )
+        .unwrap();
+        (podcasts, dir)
+    }
+
+    /// Waits for fetches and downloads to complete, returning the last failure told of
+    fn settle(podcasts: &mut Podcasts) -> Option<String> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut notice = None;
+        while podcasts.busy() != (0, 0) {
+            assert!(Instant::now() < deadline, "fetch did not complete");
+            thread::sleep(Duration::from_millis(5));
+            notice = podcasts.poll().or(notice);
+        }
+        notice
+    }
+
+    fn started(track: &Rc<AudioTrack>) -> PlayerEvent {
+        PlayerEvent::Started {
+            track: track.clone(),
+            duration: None,
+        }
+    }
+
+    #[test]
+    fn fetched_feed_is_stored() {
+        let (mut podcasts, dir) = podcasts("stored");
+        podcasts.subscribe("https://example.com/feed.rss").unwrap();
+        assert!(podcasts.is_fetching(1));
+        assert_eq!(settle(&mut podcasts), None);
+
+        let feeds = podcasts.feeds().unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].title, "Talking Shop");
+        assert_eq!(feeds[0].unfinished, 3);
+        let episodes = podcasts.episodes(feeds[0].id).unwrap();
+        let titles: Vec<_> = episodes.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            [
+                "https://example.com/3.mp3",
+                "Second <draft>",
+                "First & Foremost"
+            ]
+        );
+        assert_eq!(episodes[0].duration, Some(Duration::from_secs(2730)));
+
+        // fetching again adds nothing new
+        assert_eq!(podcasts.refresh().unwrap(), 1);
+        assert_eq!(settle(&mut podcasts), None);
+        assert_eq!(podcasts.episodes(feeds[0].id).unwrap().len(), 3);
+
+        podcasts.download(&feeds[0], &episodes[1]);
+        assert_eq!(settle(&mut podcasts), None);
+        let file = podcasts.episodes(feeds[0].id).unwrap()[1]
+            .file
+            .clone()
+            .unwrap();
+        assert_eq!(
+            file,
+            dir.join("episodes/Talking Shop/Second _draft_ [2].ogg")
+        );
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "https://example.com/2.ogg?from=feed"
+        );
+
+        podcasts.subscribe("https://example.com/missing").unwrap();
+        assert!(settle(&mut podcasts).unwrap().starts_with("cannot fetch"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cut_short_download_is_discarded() {
+        let mut response = b"HTTP/1.0 200 OK\r\nContent-Length: 100\r\n\r\n".to_vec();
+        response.extend([0; 60]);
+        let server = StandIn::serve(vec![response, stand_in::response(200, &[1; 60])]);
+        let to = env::temp_dir().join(format!("mplay-{}-cut.mp3", std::process::id()));
+        let url = format!("{}/episode.mp3", server.url);
+
+        let error = download(&Web, &url, &to).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(!to.exists() && !to.with_extension("part").exists());
+
+        // without a length, whatever arrives is taken as the whole
+        download(&Web, &url, &to).unwrap();
+        assert_eq!(fs::read(&to).unwrap(), [1; 60]);
+        fs::remove_file(to).unwrap();
+    }
+
+    #[test]
+    fn episodes_resume_until_finished() {
+        let (mut podcasts, dir) = podcasts("resume");
+        podcasts.subscribe("https://example.com/feed.rss").unwrap();
+        settle(&mut podcasts);
+        let episode = |podcasts: &Podcasts| podcasts.episodes(1).unwrap().remove(2);
+
+        let track = podcasts.play(&episode(&podcasts)).unwrap();
+        assert_eq!(track.path(), "https://example.com/1.mp3");
+        // another track starting is not the episode
+        let other = Rc::new(AudioTrack::episode(
+            "other",
+            "https://example.com/other.mp3",
+        ));
+        assert_eq!(podcasts.handle(&started(&other)).unwrap(), None);
+        assert_eq!(podcasts.handle(&started(&track)).unwrap(), None);
+        podcasts.tick(Some(Duration::from_secs(90))).unwrap();
+        podcasts.save().unwrap();
+        assert_eq!(episode(&podcasts).position, Duration::from_secs(90));
+        podcasts.tick(Some(Duration::from_secs(95))).unwrap();
+        podcasts
+            .handle(&PlayerEvent::Ended { skipped: true })
+            .unwrap();
+        assert_eq!(episode(&podcasts).position, Duration::from_secs(95));
+        assert!(!episode(&podcasts).finished);
+
+        // played again, it picks up where it stopped and is finished at its end
+        let track = podcasts.play(&episode(&podcasts)).unwrap();
+        assert_eq!(
+            podcasts.handle(&started(&track)).unwrap(),
+            Some(Duration::from_secs(95))
+        );
+        podcasts
+            .handle(&PlayerEvent::Ended { skipped: false })
+            .unwrap();
+        let finished = episode(&podcasts);
+        assert!(finished.finished);
+        assert_eq!(finished.position, Duration::ZERO);
+        assert_eq!(podcasts.feeds().unwrap()[0].unfinished, 2);
+
+        // a finished episode is heard again from the start, and stays finished on a refetch
+        podcasts.refresh().unwrap();
+        settle(&mut podcasts);
+        assert!(episode(&podcasts).finished);
+        let track = podcasts.play(&finished).unwrap();
+        assert_eq!(podcasts.handle(&started(&track)).unwrap(), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
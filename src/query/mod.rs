@@ -0,0 +1,419 @@
+//! Which tracks a search or a smart playlist holds, and in what order
+//!
+//! A query is words looked for in the title, artist and album, along with filters on tags, as
+//! in `genre:jazz` or `year<1970`, and on what the user has made of a track: `rating>=4`,
+//! `plays<3` or `is:fav`. Unrated tracks have a rating of 0. Every part must match, so an `AND`
+//! between them reads as it would and is passed over. Double quotes hold a value together
+//! across spaces, as in `genre:"hip hop"`
+
+use std::{mem, rc::Rc, str::FromStr};
+
+use crate::loader::AudioTrack;
+use crate::stats::TrackData;
+
+#[derive(Clone)]
+pub struct Query {
+    /// Words of the search that are not filters, lowercased
+    words: String,
+    filters: Vec<Filter>,
+}
+
+#[derive(Clone)]
+enum Filter {
+    /// Tag containing the lowercase text
+    Tag(Tag, String),
+    Year(Op, u32),
+    Rating(Op, u32),
+    Plays(Op, u32),
+    Favourite,
+}
+
+#[derive(Clone, Copy)]
+enum Tag {
+    Artist,
+    Album,
+    Genre,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Less,
+    AtMost,
+    Equal,
+    AtLeast,
+    Greater,
+}
+
+/// Column the list is ordered by
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortKey {
+    /// The order tracks were read in
+    Library,
+    Title,
+    Artist,
+    Album,
+    Date,
+    Rating,
+    Plays,
+}
+
+/// A row of the list: a track, where it lies in the library and what the user made of it
+pub struct Listed {
+    pub index: usize,
+    pub track: Rc<AudioTrack>,
+    pub data: TrackData,
+}
+
+#[derive(Clone, Copy)]
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Query {
+    /// Parses rules, failing on a filter whose name or value is not understood
+    pub fn parse(rules: &str) -> Result<Self, String> {
+        Self::read(rules, true)
+    }
+
+    /// Parses a search as it is typed, looking for anything not understood as words
+    pub fn search(search: &str) -> Self {
+        Self::read(search, false).expect("lenient query")
+    }
+
+    fn read(text: &str, strict: bool) -> Result<Self, String> {
+        let mut words = Vec::new();
+        let mut filters = Vec::new();
+        for token in tokens(text).into_iter().filter(|t| t != "AND") {
+            match Filter::parse(&token) {
+                Ok(Some(f)) => filters.push(f),
+                Err(e) if strict => return Err(e),
+                Ok(None) | Err(_) => words.push(token.to_lowercase()),
+            }
+        }
+        Ok(Self {
+            words: words.join(" "),
+            filters,
+        })
+    }
+
+    pub fn matches(&self, track: &AudioTrack, data: &TrackData) -> bool {
+        let q = &self.words;
+        let found = match track {
+            AudioTrack::Extended(x) => {
+                x.title.to_lowercase().contains(q)
+                    || x.album.to_lowercase().contains(q)
+                    || x.artists.to_lowercase().contains(q)
+            }
+            AudioTrack::Limited(x) => x.title.to_lowercase().contains(q),
+        };
+        found && self.filters.iter().all(|f| f.matches(track, data))
+    }
+
+    /// Tracks of the library matching the query, in library order
+    pub fn select(
+        &self,
+        tracks: &[Rc<AudioTrack>],
+        data: impl Fn(&AudioTrack) -> TrackData,
+    ) -> Vec<Listed> {
+        tracks
+            .iter()
+            .enumerate()
+            .map(|(index, track)| Listed {
+                index,
+                data: data(track),
+                track: track.clone(),
+            })
+            .filter(|r| self.matches(&r.track, &r.data))
+            .collect()
+    }
+}
+
+/// Splits on whitespace outside double quotes, taking the quotes out
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+impl Filter {
+    /// A filter where the token is `name<op>value`, or `None` for a word to look for. A name
+    /// of letters with a value is taken to be meant as a filter, and fails where it is not one
+    fn parse(token: &str) -> Result<Option<Self>, String> {
+        let Some(at) = token.find(['<', '>', '=', ':']) else {
+            return Ok(None);
+        };
+        let (name, rest) = token.split_at(at);
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Ok(None);
+        }
+        let name = name.to_lowercase();
+        if name == "is" {
+            return match rest[1..].eq_ignore_ascii_case("fav") && rest.starts_with(':') {
+                true => Ok(Some(Filter::Favourite)),
+                false => Err(format!("unknown filter `{token}`, only `is:fav` is known")),
+            };
+        }
+        let tag = match name.as_str() {
+            "artist" => Some(Tag::Artist),
+            "album" => Some(Tag::Album),
+            "genre" => Some(Tag::Genre),
+            _ => None,
+        };
+        if let Some(tag) = tag {
+            return match rest.strip_prefix([':', '=']) {
+                Some(text) if !text.is_empty() => Ok(Some(Filter::Tag(tag, text.to_lowercase()))),
+                _ => Err(format!("`{token}` should be `{name}:text`")),
+            };
+        }
+        let op = [
+            ("<=", Op::AtMost),
+            (">=", Op::AtLeast),
+            ("<", Op::Less),
+            (">", Op::Greater),
+            ("=", Op::Equal),
+            (":", Op::Equal),
+        ]
+        .into_iter()
+        .find_map(|(s, op)| Some((op, rest.strip_prefix(s)?)));
+        let filter = match name.as_str() {
+            "year" => Filter::Year,
+            "rating" => Filter::Rating,
+            "plays" => Filter::Plays,
+            _ => return Err(format!("unknown filter `{name}` in `{token}`")),
+        };
+        match op.map(|(op, value)| (op, value.parse())) {
+            Some((op, Ok(value))) => Ok(Some(filter(op, value))),
+            _ => Err(format!(
+                "`{token}` should compare `{name}` with a whole number"
+            )),
+        }
+    }
+
+    fn matches(&self, track: &AudioTrack, data: &TrackData) -> bool {
+        match *self {
+            Filter::Tag(tag, ref text) => {
+                let value = match tag {
+                    Tag::Artist => track.artists(),
+                    Tag::Album => track.album(),
+                    Tag::Genre => track.genre(),
+                };
+                value.is_some_and(|v| v.to_lowercase().contains(text))
+            }
+            Filter::Year(op, v) => track.year().is_some_and(|y| op.holds(y, v)),
+            Filter::Rating(op, v) => op.holds(data.rating.map_or(0, u32::from), v),
+            Filter::Plays(op, v) => op.holds(data.plays, v),
+            Filter::Favourite => data.favourite,
+        }
+    }
+}
+
+impl Op {
+    fn holds(self, a: u32, b: u32) -> bool {
+        match self {
+            Op::Less => a < b,
+            Op::AtMost => a <= b,
+            Op::Equal => a == b,
+            Op::AtLeast => a >= b,
+            Op::Greater => a > b,
+        }
+    }
+}
+
+impl SortKey {
+    const ALL: [SortKey; 7] = [
+        SortKey::Library,
+        SortKey::Title,
+        SortKey::Artist,
+        SortKey::Album,
+        SortKey::Date,
+        SortKey::Rating,
+        SortKey::Plays,
+    ];
+
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|k| *k == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "library" => Ok(SortKey::Library),
+            "title" => Ok(SortKey::Title),
+            "artist" => Ok(SortKey::Artist),
+            "album" => Ok(SortKey::Album),
+            "date" => Ok(SortKey::Date),
+            "rating" => Ok(SortKey::Rating),
+            "plays" => Ok(SortKey::Plays),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self {
+            key: SortKey::Library,
+            descending: false,
+        }
+    }
+}
+
+impl Sort {
+    /// Orders the rows of the list, tracks equal in the column keeping their library order
+    pub fn apply(self, rows: &mut [Listed]) {
+        let text = |t: &AudioTrack| -> String {
+            match (self.key, t) {
+                (SortKey::Title, t) => t.title().to_lowercase(),
+                (SortKey::Artist, t) => t.artists().unwrap_or_default().to_lowercase(),
+                (SortKey::Album, t) => t.album().unwrap_or_default().to_lowercase(),
+                (SortKey::Date, AudioTrack::Extended(x)) => x.date.clone(),
+                _ => String::new(),
+            }
+        };
+        // reversed around a stable sort, ties come out in their first order
+        if self.descending {
+            rows.reverse();
+        }
+        match self.key {
+            SortKey::Library => (),
+            SortKey::Rating => rows.sort_by_key(|r| r.data.rating),
+            SortKey::Plays => rows.sort_by_key(|r| r.data.plays),
+            _ => rows.sort_by_cached_key(|r| text(&r.track)),
+        }
+        if self.descending {
+            rows.reverse();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::FullAudioTrack;
+
+    fn track(title: &str, genre: &str, date: &str) -> AudioTrack {
+        let mut track = FullAudioTrack::default();
+        track.title = title.to_owned();
+        track.artists = String::from("The Band");
+        track.genre = genre.to_owned();
+        track.date = date.to_owned();
+        AudioTrack::Extended(track)
+    }
+
+    fn rated(rating: u8, plays: u32) -> TrackData {
+        TrackData {
+            rating: Some(rating),
+            plays,
+            ..Default::default()
+        }
+    }
+
+    /// Ratings of three tracks rated one to three that the rules keep
+    fn ratings_kept(rules: &str) -> Vec<u8> {
+        let query = Query::parse(rules).unwrap();
+        let song = track("Song", "Rock", "1990");
+        (1..=3)
+            .filter(|r| query.matches(&song, &rated(*r, 0)))
+            .collect()
+    }
+
+    #[test]
+    fn each_operator_compares() {
+        assert_eq!(ratings_kept("rating<2"), [1]);
+        assert_eq!(ratings_kept("rating<=2"), [1, 2]);
+        assert_eq!(ratings_kept("rating=2"), [2]);
+        assert_eq!(ratings_kept("rating:2"), [2]);
+        assert_eq!(ratings_kept("rating>=2"), [2, 3]);
+        assert_eq!(ratings_kept("rating>2"), [3]);
+    }
+
+    #[test]
+    fn year_and_plays_are_filtered() {
+        let query = Query::parse("year<1970 plays>=3").unwrap();
+        let data = rated(1, 3);
+        assert!(query.matches(&track("Old", "Jazz", "1965-04-01"), &data));
+        assert!(!query.matches(&track("New", "Jazz", "1999"), &data));
+        assert!(!query.matches(&track("Old", "Jazz", "1965"), &rated(1, 2)));
+    }
+
+    #[test]
+    fn favourites_only() {
+        let query = Query::parse("IS:FAV").unwrap();
+        let song = track("Song", "Rock", "1990");
+        let favourite = TrackData {
+            favourite: true,
+            ..Default::default()
+        };
+        assert!(query.matches(&song, &favourite));
+        assert!(!query.matches(&song, &TrackData::default()));
+    }
+
+    #[test]
+    fn and_is_passed_over() {
+        let query = Query::parse("genre:rock AND rating>=2").unwrap();
+        assert!(query.words.is_empty());
+        assert_eq!(query.filters.len(), 2);
+        let song = track("Song", "Hard Rock", "1990");
+        assert!(query.matches(&song, &rated(2, 0)));
+        assert!(!query.matches(&song, &rated(1, 0)));
+        assert!(!query.matches(&track("Song", "Jazz", "1990"), &rated(2, 0)));
+    }
+
+    #[test]
+    fn quotes_hold_a_value_together() {
+        let query = Query::parse(r#"genre:"hip hop" "the song""#).unwrap();
+        assert_eq!(query.words, "the song");
+        let data = TrackData::default();
+        assert!(query.matches(&track("The Song", "Hip Hop", ""), &data));
+        assert!(!query.matches(&track("The Song", "Hip", ""), &data));
+        assert!(!query.matches(&track("Song", "Hip Hop", ""), &data));
+    }
+
+    #[test]
+    fn rules_not_understood_fail() {
+        for rules in [
+            "rating>=four",
+            "yeer<1970",
+            "is:new",
+            "genre>rock",
+            "plays=",
+            "album:",
+        ] {
+            assert!(Query::parse(rules).is_err(), "{rules}");
+        }
+        assert!(
+            Query::parse("yeer<1970")
+                .err()
+                .unwrap()
+                .contains("unknown filter `yeer`")
+        );
+    }
+
+    #[test]
+    fn searches_take_what_is_not_understood_as_words() {
+        let query = Query::search("rating>=four 12:30");
+        assert!(query.filters.is_empty());
+        assert_eq!(query.words, "rating>=four 12:30");
+        assert!(Query::parse("12:30 c++").unwrap().filters.is_empty());
+    }
+}
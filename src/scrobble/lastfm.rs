@@ -0,0 +1,294 @@
+//! Last.fm, calling signed methods of the 2.0 API with a session key
+
+use super::{Backend, Error, Listen};
+use crate::http;
+
+pub struct LastFm {
+    key: String,
+    secret: String,
+    session: String,
+    /// Root of the API, without the version
+    url: String,
+}
+
+/// Error codes of failures worth retrying: service offline, temporarily unavailable and rate
+/// limited
+const TEMPORARY: [u32; 3] = [11, 16, 29];
+
+impl LastFm {
+    pub fn new(key: String, secret: String, session: String, url: String) -> Self {
+        Self {
+            key,
+            secret,
+            session,
+            url,
+        }
+    }
+
+    /// Calls a method, signing its parameters as the API requires
+    fn call(&self, method: &str, mut params: Vec<(String, String)>) -> Result<(), Error> {
+        params.extend([
+            (String::from("method"), method.to_owned()),
+            (String::from("api_key"), self.key.clone()),
+            (String::from("sk"), self.session.clone()),
+        ]);
+        params.sort();
+        let mut signed: String = params.iter().map(|(k, v)| format!("{k}{v}")).collect();
+        signed.push_str(&self.secret);
+        let signature: String = md5(signed.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        params.push((String::from("api_sig"), signature));
+        params.push((String::from("format"), String::from("json")));
+
+        let url = format!("{}/2.0/", self.url.trim_end_matches('/'));
+        let response = http::post(
+            &url,
+            &[],
+            "application/x-www-form-urlencoded",
+            &http::form(&params),
+        )?;
+        match error_code(&response.body) {
+            None if (200..300).contains(&response.status) => Ok(()),
+            Some(code) if TEMPORARY.contains(&code) => {
+                Err(Error::Unreachable(format!("error {code}")))
+            }
+            None if response.status >= 500 => {
+                Err(Error::Unreachable(format!("status {}", response.status)))
+            }
+            _ => Err(Error::Rejected(response.body.trim().to_owned())),
+        }
+    }
+}
+
+impl Backend for LastFm {
+    fn name(&self) -> &'static str {
+        "last.fm"
+    }
+
+    fn now_playing(&mut self, listen: &Listen) -> Result<(), Error> {
+        self.call("track.updateNowPlaying", fields(listen, None))
+    }
+
+    fn submit(&mut self, listens: &[Listen]) -> Result<(), Error> {
+        let params = listens
+            .iter()
+            .enumerate()
+            .flat_map(|(i, l)| {
+                let mut f = fields(l, Some(i));
+                f.push((format!("timestamp[{i}]"), l.listened_at.to_string()));
+                f
+            })
+            .collect();
+        self.call("track.scrobble", params)
+    }
+}
+
+/// Parameters describing a track, indexed as one of a batch where `index` is given
+fn fields(listen: &Listen, index: Option<usize>) -> Vec<(String, String)> {
+    let name = |n: &str| match index {
+        Some(i) => format!("{n}[{i}]"),
+        None => n.to_owned(),
+    };
+    let mut f = vec![
+        (name("artist"), listen.artist.clone()),
+        (name("track"), listen.title.clone()),
+    ];
+    if let Some(album) = &listen.album {
+        f.push((name("album"), album.clone()));
+    }
+    if let Some(d) = listen.duration {
+        f.push((name("duration"), d.as_secs().to_string()));
+    }
+    f
+}
+
+/// Code of an error reported in a JSON response, as in `{"error":16,"message":"..."}`
+fn error_code(body: &str) -> Option<u32> {
+    let rest = &body[body.find("\"error\"")? + "\"error\"".len()..];
+    let digits: String = rest
+        .trim_start_matches([':', ' '])
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+/// MD5 digest, which the API signs requests with
+fn md5(data: &[u8]) -> [u8; 16] {
+    const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+    let constants: Vec<u32> = (1..=64)
+        .map(|i| (f64::from(i).sin().abs() * 4_294_967_296.0) as u32)
+        .collect();
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for block in message.chunks(64) {
+        let words: Vec<u32> = block
+            .chunks(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f
+                .wrapping_add(a)
+                .wrapping_add(constants[i])
+                .wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(SHIFTS[i / 16 * 4 + i % 4]));
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0; 16];
+    for (chunk, s) in digest.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&s.to_le_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::http::stand_in::{self, StandIn};
+
+    fn hex(digest: [u8; 16]) -> String {
+        digest.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Pairs of a form body, decoded
+    fn decode(form: &str) -> Vec<(String, String)> {
+        let unescape = |s: &str| {
+            let mut bytes = Vec::new();
+            let mut rest = s.as_bytes();
+            while let Some((&b, tail)) = rest.split_first() {
+                match b {
+                    b'%' => {
+                        let code = std::str::from_utf8(&tail[..2]).unwrap();
+                        bytes.push(u8::from_str_radix(code, 16).unwrap());
+                        rest = &tail[2..];
+                    }
+                    _ => {
+                        bytes.push(b);
+                        rest = tail;
+                    }
+                }
+            }
+            String::from_utf8(bytes).unwrap()
+        };
+        form.split('&')
+            .map(|p| {
+                let (k, v) = p.split_once('=').unwrap();
+                (unescape(k), unescape(v))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn md5_matches_known_digests() {
+        assert_eq!(hex(md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex(md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+        // long enough to pad into a second block
+        assert_eq!(
+            hex(md5(b"1234567890".repeat(8).as_slice())),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+
+    #[test]
+    fn submission_is_signed() {
+        let server = StandIn::serve(vec![stand_in::response(200, b"{\"scrobbles\":{}}")]);
+        let mut backend = LastFm::new(
+            String::from("key"),
+            String::from("shh"),
+            String::from("session"),
+            server.url.clone(),
+        );
+        let listen = Listen {
+            artist: String::from("Artist & Co"),
+            title: String::from("Title"),
+            album: None,
+            duration: Some(Duration::from_secs(180)),
+            listened_at: 1_700_000_000,
+        };
+        assert!(backend.submit(&[listen]).is_ok());
+
+        let request = server.request();
+        assert!(request.head.starts_with("POST /2.0/ HTTP/1.0\r\n"));
+        let mut params = decode(&request.body);
+        assert_eq!(
+            params.pop(),
+            Some((String::from("format"), String::from("json")))
+        );
+        let (name, signature) = params.pop().unwrap();
+        assert_eq!(name, "api_sig");
+        assert_eq!(
+            params,
+            [
+                ("api_key", "key"),
+                ("artist[0]", "Artist & Co"),
+                ("duration[0]", "180"),
+                ("method", "track.scrobble"),
+                ("sk", "session"),
+                ("timestamp[0]", "1700000000"),
+                ("track[0]", "Title"),
+            ]
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        );
+        assert_eq!(
+            signature,
+            hex(md5(
+                b"api_keykeyartist[0]Artist & Coduration[0]180methodtrack.scrobble\
+                sksessiontimestamp[0]1700000000track[0]Titleshh"
+            ))
+        );
+    }
+
+    #[test]
+    fn temporary_errors_are_retried() {
+        let server = StandIn::serve(vec![
+            stand_in::response(200, br#"{"error":16,"message":"try again"}"#),
+            stand_in::response(403, br#"{"error":9,"message":"bad session"}"#),
+        ]);
+        let mut backend = LastFm::new(
+            String::from("key"),
+            String::from("shh"),
+            String::from("session"),
+            server.url.clone(),
+        );
+        let listen = Listen {
+            artist: String::from("A"),
+            title: String::from("T"),
+            album: None,
+            duration: None,
+            listened_at: 0,
+        };
+        assert!(matches!(
+            backend.submit(std::slice::from_ref(&listen)),
+            Err(Error::Unreachable(_))
+        ));
+        assert!(matches!(
+            backend.now_playing(&listen),
+            Err(Error::Rejected(_))
+        ));
+    }
+}
@@ -0,0 +1,165 @@
+//! ListenBrainz, submitting JSON documents authorised by a user token
+
+use super::{Backend, Error, Listen};
+use crate::http;
+
+pub struct ListenBrainz {
+    token: String,
+    /// Root of the API, without the version
+    url: String,
+}
+
+impl ListenBrainz {
+    pub fn new(token: String, url: String) -> Self {
+        Self { token, url }
+    }
+
+    fn post(&self, kind: &str, listens: &[Listen], timed: bool) -> Result<(), Error> {
+        let payload: Vec<String> = listens.iter().map(|l| listen_json(l, timed)).collect();
+        let body = format!(
+            r#"{{"listen_type":"{kind}","payload":[{}]}}"#,
+            payload.join(",")
+        );
+        let url = format!("{}/1/submit-listens", self.url.trim_end_matches('/'));
+        let authorization = format!("Token {}", self.token);
+        let response = http::post(
+            &url,
+            &[("Authorization", &authorization)],
+            "application/json",
+            &body,
+        )?;
+        match response.status {
+            200..300 => Ok(()),
+            429 | 500.. => Err(Error::Unreachable(format!("status {}", response.status))),
+            s => Err(Error::Rejected(format!(
+                "status {s}: {}",
+                response.body.trim()
+            ))),
+        }
+    }
+}
+
+impl Backend for ListenBrainz {
+    fn name(&self) -> &'static str {
+        "listenbrainz"
+    }
+
+    fn now_playing(&mut self, listen: &Listen) -> Result<(), Error> {
+        self.post("playing_now", std::slice::from_ref(listen), false)
+    }
+
+    fn submit(&mut self, listens: &[Listen]) -> Result<(), Error> {
+        let kind = match listens.len() {
+            1 => "single",
+            _ => "import",
+        };
+        self.post(kind, listens, true)
+    }
+}
+
+/// A listen as an entry of the payload; now playing has no time
+fn listen_json(listen: &Listen, timed: bool) -> String {
+    let mut metadata = format!(
+        r#""artist_name":{},"track_name":{}"#,
+        json_string(&listen.artist),
+        json_string(&listen.title)
+    );
+    if let Some(album) = &listen.album {
+        metadata.push_str(&format!(r#","release_name":{}"#, json_string(album)));
+    }
+    if let Some(d) = listen.duration {
+        metadata.push_str(&format!(
+            r#","additional_info":{{"duration_ms":{},"media_player":"mplay"}}"#,
+            d.as_millis()
+        ));
+    }
+    match timed {
+        true => format!(
+            r#"{{"listened_at":{},"track_metadata":{{{metadata}}}}}"#,
+            listen.listened_at
+        ),
+        false => format!(r#"{{"track_metadata":{{{metadata}}}}}"#),
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::http::stand_in::{self, StandIn};
+
+    fn listen() -> Listen {
+        Listen {
+            artist: String::from("The \"Band\""),
+            title: String::from("Song"),
+            album: Some(String::from("Album")),
+            duration: Some(Duration::from_secs(200)),
+            listened_at: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn submits_a_listen() {
+        let server = StandIn::serve(vec![stand_in::response(200, b"{}")]);
+        let mut backend = ListenBrainz::new(String::from("secret"), server.url.clone());
+        assert!(backend.submit(&[listen()]).is_ok());
+
+        let request = server.request();
+        assert!(
+            request
+                .head
+                .starts_with("POST /1/submit-listens HTTP/1.0\r\n")
+        );
+        assert!(request.head.contains("\r\nAuthorization: Token secret\r\n"));
+        assert_eq!(
+            request.body,
+            r#"{"listen_type":"single","payload":[{"listened_at":1700000000,"track_metadata":{"artist_name":"The \"Band\"","track_name":"Song","release_name":"Album","additional_info":{"duration_ms":200000,"media_player":"mplay"}}}]}"#
+        );
+    }
+
+    #[test]
+    fn status_decides_whether_to_retry() {
+        let server = StandIn::serve(vec![
+            stand_in::response(503, b""),
+            stand_in::response(401, b"bad token"),
+        ]);
+        let mut backend = ListenBrainz::new(String::from("secret"), server.url.clone());
+        assert!(matches!(
+            backend.submit(&[listen()]),
+            Err(Error::Unreachable(_))
+        ));
+        assert!(matches!(
+            backend.now_playing(&listen()),
+            Err(Error::Rejected(e)) if e == "status 401: bad token"
+        ));
+        assert!(
+            server
+                .request()
+                .body
+                .starts_with(r#"{"listen_type":"single""#)
+        );
+        assert!(
+            server
+                .request()
+                .body
+                .starts_with(r#"{"listen_type":"playing_now""#)
+        );
+    }
+}
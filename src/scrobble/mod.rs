@@ -0,0 +1,319 @@
+//! Submits what is played to listening history services: a track as now playing once it
+//! starts, and as a listen once half of it or four minutes of it have been heard
+//!
+//! Services are reached on a thread of their own. Listens a service cannot take are queued on
+//! disk and sent again with the next listen, or on the next launch
+
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::config::{self, ScrobbleConfig};
+use crate::loader::AudioTrack;
+use crate::playback::PlayerEvent;
+
+mod lastfm;
+mod listenbrainz;
+mod queue;
+
+use lastfm::LastFm;
+use listenbrainz::ListenBrainz;
+use queue::Queue;
+
+/// Tracks shorter than this are never submitted as listens
+const MIN_LENGTH: Duration = Duration::from_secs(30);
+
+/// Time heard after which a track counts as listened to, however long it is
+const MAX_WAIT: Duration = Duration::from_secs(4 * 60);
+
+/// Most listens sent in one request
+const BATCH: usize = 50;
+
+/// A track heard, as the services describe it
+#[derive(Clone)]
+pub struct Listen {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub duration: Option<Duration>,
+    /// When the track began, in seconds since the Unix epoch
+    pub listened_at: u64,
+}
+
+pub enum Error {
+    /// The service could not be reached or is briefly unavailable; worth trying again
+    Unreachable(String),
+    /// The service refused the request, and would again
+    Rejected(String),
+}
+
+/// A listening history service
+pub trait Backend: Send {
+    /// Name shown in messages and kept with queued listens
+    fn name(&self) -> &'static str;
+    fn now_playing(&mut self, listen: &Listen) -> Result<(), Error>;
+    /// Submits listens, oldest first, at most `BATCH` at once
+    fn submit(&mut self, listens: &[Listen]) -> Result<(), Error>;
+}
+
+enum Job {
+    NowPlaying(Listen),
+    Submit(Listen),
+}
+
+/// Follows playback, deciding when a track has been listened to
+pub struct Scrobbler {
+    jobs: Sender<Job>,
+    /// Failures to report to the user
+    notices: Receiver<String>,
+    playing: Option<Playing>,
+}
+
+struct Playing {
+    listen: Listen,
+    /// Time spent playing rather than paused
+    heard: Duration,
+    last_tick: Instant,
+    submitted: bool,
+}
+
+impl Scrobbler {
+    /// Starts submitting to every service configured, or `None` where there are none
+    pub fn new(config: &ScrobbleConfig) -> Option<Self> {
+        let mut backends: Vec<Box<dyn Backend>> = Vec::new();
+        if let Some(token) = &config.listenbrainz_token {
+            backends.push(Box::new(ListenBrainz::new(
+                token.clone(),
+                config.listenbrainz_url.clone(),
+            )));
+        }
+        if let (Some(key), Some(secret), Some(session)) = (
+            &config.lastfm_key,
+            &config.lastfm_secret,
+            &config.lastfm_session,
+        ) {
+            backends.push(Box::new(LastFm::new(
+                key.clone(),
+                secret.clone(),
+                session.clone(),
+                config.lastfm_url.clone(),
+            )));
+        }
+        match backends.is_empty() {
+            true => None,
+            false => Some(Self::with_backends(
+                backends,
+                config::data_dir().join("scrobbles"),
+            )),
+        }
+    }
+
+    /// Starts submitting to the given services, queuing listens they cannot take at `queue`.
+    /// Queued listens are sent first
+    pub fn with_backends(backends: Vec<Box<dyn Backend>>, queue: PathBuf) -> Self {
+        let (jobs, rx) = mpsc::channel();
+        let (tx, notices) = mpsc::channel();
+        thread::spawn(move || run(backends, Queue::load(queue), rx, tx));
+        Self {
+            jobs,
+            notices,
+            playing: None,
+        }
+    }
+
+    pub fn handle(&mut self, event: &PlayerEvent) {
+        self.playing = match event {
+            PlayerEvent::Started { track, duration } => Listen::new(track, *duration).map(|l| {
+                // the worker only stops once this is dropped
+                let _ = self.jobs.send(Job::NowPlaying(l.clone()));
+                Playing {
+                    listen: l,
+                    heard: Duration::ZERO,
+                    last_tick: Instant::now(),
+                    submitted: false,
+                }
+            }),
+            PlayerEvent::Ended { .. } => None,
+            PlayerEvent::Failed { .. } | PlayerEvent::Notice(_) => return,
+        };
+    }
+
+    /// Counts time heard since last called, submitting the track once it has been heard enough
+    pub fn tick(&mut self, paused: bool) {
+        let Some(p) = &mut self.playing else {
+            return;
+        };
+        let now = Instant::now();
+        if !paused {
+            p.heard += now.duration_since(p.last_tick);
+        }
+        p.last_tick = now;
+
+        let needed = match p.listen.duration {
+            Some(d) if d < MIN_LENGTH => return,
+            Some(d) => (d / 2).min(MAX_WAIT),
+            None => MAX_WAIT,
+        };
+        if !p.submitted && p.heard >= needed {
+            p.submitted = true;
+            let _ = self.jobs.send(Job::Submit(p.listen.clone()));
+        }
+    }
+
+    /// A failure to tell the user of, if any
+    pub fn notice(&self) -> Option<String> {
+        self.notices.try_recv().ok()
+    }
+}
+
+impl Listen {
+    /// A listen of a track beginning now, where it is tagged with an artist
+    fn new(track: &AudioTrack, duration: Option<Duration>) -> Option<Self> {
+        let listened_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Some(Self {
+            artist: track.artists()?.to_owned(),
+            title: track.title().to_owned(),
+            album: track.album().map(str::to_owned),
+            duration,
+            listened_at,
+        })
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Unreachable(e) | Error::Rejected(e) => f.write_str(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Unreachable(e.to_string())
+    }
+}
+
+/// Submits listens until the scrobbler is dropped
+fn run(
+    mut backends: Vec<Box<dyn Backend>>,
+    mut queue: Queue,
+    jobs: Receiver<Job>,
+    notices: Sender<String>,
+) {
+    flush(&mut backends, &mut queue, &notices);
+    for job in jobs {
+        match job {
+            Job::NowPlaying(listen) => {
+                for b in &mut backends {
+                    // a missed now playing is of no use later, so only refusals are told
+                    if let Err(Error::Rejected(e)) = b.now_playing(&listen) {
+                        let _ = notices.send(format!("{}: {e}", b.name()));
+                    }
+                }
+            }
+            Job::Submit(listen) => {
+                for b in &backends {
+                    queue.push(b.name(), listen.clone());
+                }
+                // kept before sending, in case the player quits part way through
+                save(&queue, &notices);
+                flush(&mut backends, &mut queue, &notices);
+            }
+        }
+    }
+}
+
+/// Sends every queued listen each service will take
+fn flush(backends: &mut [Box<dyn Backend>], queue: &mut Queue, notices: &Sender<String>) {
+    for b in backends {
+        loop {
+            let batch = queue.pending(b.name(), BATCH);
+            if batch.is_empty() {
+                break;
+            }
+            match b.submit(&batch) {
+                Ok(()) => queue.remove(b.name(), batch.len()),
+                Err(Error::Rejected(e)) => {
+                    let _ = notices.send(format!(
+                        "{}: dropped {} listens: {e}",
+                        b.name(),
+                        batch.len()
+                    ));
+                    queue.remove(b.name(), batch.len());
+                }
+                Err(Error::Unreachable(e)) => {
+                    let _ = notices.send(format!("{}: listens kept for later: {e}", b.name()));
+                    break;
+                }
+            }
+        }
+    }
+    save(queue, notices);
+}
+
+fn save(queue: &Queue, notices: &Sender<String>) {
+    if let Err(e) = queue.save() {
+        let _ = notices.send(format!("cannot save queued listens: {e}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+    use crate::http::stand_in::{self, StandIn};
+
+    fn listenbrainz(url: String) -> Vec<Box<dyn Backend>> {
+        vec![Box::new(ListenBrainz::new(String::from("token"), url))]
+    }
+
+    #[test]
+    fn queued_listens_wait_for_the_service() {
+        let path = env::temp_dir().join(format!("mplay-{}-scrobbles", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut queue = Queue::load(path.clone());
+        queue.push(
+            "listenbrainz",
+            Listen {
+                artist: String::from("Artist"),
+                title: String::from("Title"),
+                album: None,
+                duration: Some(Duration::from_secs(90)),
+                listened_at: 1_700_000_000,
+            },
+        );
+
+        let (tx, notices) = mpsc::channel();
+        flush(&mut listenbrainz(stand_in::unreachable()), &mut queue, &tx);
+        assert!(
+            notices
+                .try_recv()
+                .unwrap()
+                .starts_with("listenbrainz: listens kept for later")
+        );
+        let kept = Queue::load(path.clone()).pending("listenbrainz", BATCH);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].listened_at, 1_700_000_000);
+
+        // the next launch sends what was kept
+        let server = StandIn::serve(vec![stand_in::response(200, b"{}")]);
+        let scrobbler = Scrobbler::with_backends(listenbrainz(server.url.clone()), path.clone());
+        let request = server.request();
+        assert!(request.body.contains(r#""listened_at":1700000000"#));
+        assert!(request.body.contains(r#""artist_name":"Artist""#));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while path.exists() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!path.exists());
+        assert!(scrobbler.notice().is_none());
+    }
+}
@@ -0,0 +1,99 @@
+//! Listens a service has yet to accept, kept on disk until it does
+//!
+//! Each line holds one listen for one service, as tab separated fields: the service, the time
+//! it was heard, its length in seconds, then artist, title and album
+
+use std::{fs, io, path::PathBuf, time::Duration};
+
+use super::Listen;
+
+pub struct Queue {
+    path: PathBuf,
+    entries: Vec<(String, Listen)>,
+}
+
+impl Queue {
+    /// Reads the queue at `path`, skipping lines it cannot make sense of
+    pub fn load(path: PathBuf) -> Self {
+        let entries = fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(parse)
+            .collect();
+        Self { path, entries }
+    }
+
+    pub fn push(&mut self, service: &str, listen: Listen) {
+        self.entries.push((service.to_owned(), listen));
+    }
+
+    /// The oldest listens waiting for a service, at most `limit` of them
+    pub fn pending(&self, service: &str, limit: usize) -> Vec<Listen> {
+        self.entries
+            .iter()
+            .filter(|(s, _)| s == service)
+            .take(limit)
+            .map(|(_, l)| l.clone())
+            .collect()
+    }
+
+    /// Drops the oldest `count` listens waiting for a service
+    pub fn remove(&mut self, service: &str, count: usize) {
+        let mut removed = 0;
+        self.entries.retain(|(s, _)| {
+            let keep = s != service || removed == count;
+            removed += usize::from(!keep);
+            keep
+        });
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if self.entries.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        let mut out = String::new();
+        for (service, l) in &self.entries {
+            let fields = [
+                service.clone(),
+                l.listened_at.to_string(),
+                l.duration
+                    .map(|d| d.as_secs().to_string())
+                    .unwrap_or_default(),
+                l.artist.clone(),
+                l.title.clone(),
+                l.album.clone().unwrap_or_default(),
+            ];
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|f| f.replace(['\t', '\n'], " "))
+                .collect();
+            out.push_str(&fields.join("\t"));
+            out.push('\n');
+        }
+        // write beside the original and rename over it so a failed write loses nothing
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, out)?;
+        fs::rename(tmp, &self.path)
+    }
+}
+
+fn parse(line: &str) -> Option<(String, Listen)> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let [service, listened_at, duration, artist, title, album] = fields[..] else {
+        return None;
+    };
+    let listen = Listen {
+        artist: artist.to_owned(),
+        title: title.to_owned(),
+        album: (!album.is_empty()).then(|| album.to_owned()),
+        duration: duration.parse().ok().map(Duration::from_secs),
+        listened_at: listened_at.parse().ok()?,
+    };
+    Some((service.to_owned(), listen))
+}
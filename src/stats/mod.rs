@@ -0,0 +1,269 @@
+//! Every play recorded in a local SQLite database, and the figures drawn from it
+//!
+//! A play is kept with the path, identity and tags of its track, when it began, how long was
+//! heard and whether it was skipped before its end. Ratings and favourites are kept by identity
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
+
+use rusqlite::{Connection, params};
+
+use crate::config;
+use crate::playback::PlayerEvent;
+
+mod tracks;
+
+pub use tracks::TrackData;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS plays (
+        id INTEGER PRIMARY KEY,
+        path TEXT NOT NULL,
+        title TEXT NOT NULL,
+        artist TEXT,
+        album TEXT,
+        started INTEGER NOT NULL,
+        listened REAL NOT NULL,
+        duration REAL,
+        completed INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS plays_started ON plays (started);
+    CREATE INDEX IF NOT EXISTS plays_path ON plays (path);
+";
+
+/// Changes to the schema, each applied once in order and counted by `user_version`
+const MIGRATIONS: [&str; 1] = ["
+    ALTER TABLE plays ADD COLUMN track INTEGER;
+    CREATE INDEX plays_track ON plays (track);
+    CREATE TABLE ratings (
+        track INTEGER PRIMARY KEY,
+        rating INTEGER,
+        favourite INTEGER NOT NULL DEFAULT 0
+    );
+"];
+
+/// What plays are counted by
+#[derive(Clone, Copy)]
+pub enum Group {
+    Track,
+    Album,
+    Artist,
+}
+
+/// A track, album or artist with what it was played
+pub struct Ranked {
+    pub name: String,
+    pub plays: u32,
+    pub listened: Duration,
+}
+
+/// Plays of a period taken together
+#[derive(Default)]
+pub struct Summary {
+    pub plays: u32,
+    pub skipped: u32,
+    pub listened: Duration,
+}
+
+pub struct Stats {
+    db: Connection,
+    playing: Option<Play>,
+    /// Plays recorded since opening, to tell when figures drawn before are out of date
+    pub recorded: u64,
+    /// What is known of each track by identity, kept in step with the database
+    tracks: HashMap<u64, TrackData>,
+    /// Plays recorded before tracks were identified, by path, with when the latest began
+    untracked: HashMap<String, (u32, i64)>,
+}
+
+/// The play in progress
+struct Play {
+    path: String,
+    track: u64,
+    title: String,
+    artist: Option<String>,
+    album: Option<String>,
+    started: i64,
+    duration: Option<Duration>,
+    /// Time spent playing rather than paused
+    heard: Duration,
+    last_tick: Instant,
+}
+
+impl Stats {
+    /// Opens the database in the data directory, creating it where it does not exist
+    pub fn open() -> rusqlite::Result<Self> {
+        let dir = config::data_dir();
+        // a missing directory shows up as the database failing to open
+        let _ = std::fs::create_dir_all(&dir);
+        Self::open_at(&dir.join("stats.db"))
+    }
+
+    pub fn open_at(path: &Path) -> rusqlite::Result<Self> {
+        let db = Connection::open(path)?;
+        db.execute_batch(SCHEMA)?;
+        let version: u32 = db.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            db.execute_batch(&format!(
+                "BEGIN; {migration} PRAGMA user_version = {}; COMMIT;",
+                i + 1
+            ))?;
+        }
+        let mut stats = Self {
+            db,
+            playing: None,
+            recorded: 0,
+            tracks: HashMap::new(),
+            untracked: HashMap::new(),
+        };
+        stats.load_tracks()?;
+        Ok(stats)
+    }
+
+    pub fn handle(&mut self, event: &PlayerEvent) -> rusqlite::Result<()> {
+        match event {
+            PlayerEvent::Started { track, duration } => {
+                self.playing = Some(Play {
+                    path: track.path().to_owned(),
+                    track: track.identity(),
+                    title: track.title().to_owned(),
+                    artist: track.artists().map(str::to_owned),
+                    album: track.album().map(str::to_owned),
+                    started: now(),
+                    duration: *duration,
+                    heard: Duration::ZERO,
+                    last_tick: Instant::now(),
+                });
+                Ok(())
+            }
+            PlayerEvent::Ended { skipped } => self.finish(!skipped),
+            PlayerEvent::Failed { .. } | PlayerEvent::Notice(_) => Ok(()),
+        }
+    }
+
+    /// Counts time heard since last called
+    pub fn tick(&mut self, paused: bool) {
+        if let Some(p) = &mut self.playing {
+            let now = Instant::now();
+            if !paused {
+                p.heard += now.duration_since(p.last_tick);
+            }
+            p.last_tick = now;
+        }
+    }
+
+    /// Records the play in progress, if any
+    pub fn finish(&mut self, completed: bool) -> rusqlite::Result<()> {
+        let Some(p) = self.playing.take() else {
+            return Ok(());
+        };
+        self.db.execute(
+            "INSERT INTO plays
+                 (path, title, artist, album, started, listened, duration, completed, track)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                p.path,
+                p.title,
+                p.artist,
+                p.album,
+                p.started,
+                p.heard.as_secs_f64(),
+                p.duration.map(|d| d.as_secs_f64()),
+                completed,
+                p.track as i64,
+            ],
+        )?;
+        let data = self.tracks.entry(p.track).or_default();
+        data.plays += 1;
+        data.last_played = Some(p.started);
+        self.recorded += 1;
+        Ok(())
+    }
+
+    /// Most played tracks, albums or artists since a time, most first
+    pub fn top(&self, group: Group, since: i64, limit: usize) -> rusqlite::Result<Vec<Ranked>> {
+        let (name, filter) = match group {
+            Group::Track => ("title || ' · ' || coalesce(artist, '')", "1"),
+            Group::Album => ("album", "album IS NOT NULL"),
+            Group::Artist => ("artist", "artist IS NOT NULL"),
+        };
+        let group_by = match group {
+            Group::Track => "path",
+            _ => name,
+        };
+        let mut statement = self.db.prepare(&format!(
+            "SELECT {name}, count(*), sum(listened) FROM plays
+             WHERE started >= ?1 AND {filter}
+             GROUP BY {group_by} ORDER BY count(*) DESC, sum(listened) DESC LIMIT ?2"
+        ))?;
+        statement
+            .query_map(params![since, limit as i64], |row| {
+                Ok(Ranked {
+                    name: row.get(0)?,
+                    plays: row.get(1)?,
+                    listened: seconds(row.get(2)?),
+                })
+            })?
+            .collect()
+    }
+
+    pub fn summary(&self, since: i64) -> rusqlite::Result<Summary> {
+        self.db.query_row(
+            "SELECT count(*), coalesce(sum(NOT completed), 0), coalesce(sum(listened), 0)
+             FROM plays WHERE started >= ?1",
+            [since],
+            |row| {
+                Ok(Summary {
+                    plays: row.get(0)?,
+                    skipped: row.get(1)?,
+                    listened: seconds(row.get(2)?),
+                })
+            },
+        )
+    }
+
+    /// Time listened on each of the latest local days up to today, as `YYYY-MM-DD`
+    pub fn daily(&self, days: u32) -> rusqlite::Result<Vec<(String, Duration)>> {
+        let mut statement = self.db.prepare(
+            "WITH RECURSIVE days (day) AS (
+                 SELECT date('now', 'localtime', '-' || (?1 - 1) || ' days')
+                 UNION ALL SELECT date(day, '+1 day') FROM days
+                 WHERE day < date('now', 'localtime')
+             )
+             SELECT day, coalesce(sum(listened), 0) FROM days
+             LEFT JOIN plays ON date(started, 'unixepoch', 'localtime') = day
+                 AND started >= unixepoch('now', '-' || ?1 || ' days')
+             GROUP BY day ORDER BY day",
+        )?;
+        statement
+            .query_map([days], |row| Ok((row.get(0)?, seconds(row.get(1)?))))?
+            .collect()
+    }
+
+    /// Paths of the tracks played since a time
+    pub fn played_since(&self, since: i64) -> rusqlite::Result<HashSet<String>> {
+        let mut statement = self
+            .db
+            .prepare("SELECT DISTINCT path FROM plays WHERE started >= ?1")?;
+        statement.query_map([since], |row| row.get(0))?.collect()
+    }
+}
+
+/// Seconds since the Unix epoch
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Time `days` before now, or the beginning of time for `None`
+pub fn since(days: Option<u32>) -> i64 {
+    days.map_or(0, |d| now() - i64::from(d) * 24 * 60 * 60)
+}
+
+fn seconds(s: f64) -> Duration {
+    Duration::try_from_secs_f64(s).unwrap_or_default()
+}
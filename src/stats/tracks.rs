@@ -0,0 +1,98 @@
+//! Ratings, favourites, play counts and last plays of each track, by identity
+
+use rusqlite::params;
+
+use super::Stats;
+use crate::loader::AudioTrack;
+
+/// What the user has made of a track
+#[derive(Clone, Copy, Default)]
+pub struct TrackData {
+    /// Stars from one to five, where rated
+    pub rating: Option<u8>,
+    pub favourite: bool,
+    pub plays: u32,
+    /// When the latest play began, in seconds since the Unix epoch
+    pub last_played: Option<i64>,
+}
+
+impl Stats {
+    /// Reads ratings and play counts of every track
+    pub(super) fn load_tracks(&mut self) -> rusqlite::Result<()> {
+        let mut statement = self
+            .db
+            .prepare("SELECT track, rating, favourite FROM ratings")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get(1)?, row.get(2)?))
+        })?;
+        for row in rows {
+            let (track, rating, favourite) = row?;
+            let data = self.tracks.entry(track as u64).or_default();
+            data.rating = rating;
+            data.favourite = favourite;
+        }
+
+        let mut statement = self.db.prepare(
+            "SELECT track, path, count(*), max(started) FROM plays
+             GROUP BY coalesce(track, path)",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, Option<i64>>(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+            ))
+        })?;
+        for row in rows {
+            match row? {
+                (Some(track), _, n, last) => {
+                    let data = self.tracks.entry(track as u64).or_default();
+                    data.plays = n;
+                    data.last_played = Some(last);
+                }
+                (None, path, n, last) => {
+                    self.untracked.insert(path, (n, last));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn track(&self, track: &AudioTrack) -> TrackData {
+        let mut data = self
+            .tracks
+            .get(&track.identity())
+            .copied()
+            .unwrap_or_default();
+        if let Some((plays, last)) = self.untracked.get(track.path()) {
+            data.plays += plays;
+            data.last_played = data.last_played.max(Some(*last));
+        }
+        data
+    }
+
+    /// Rates a track from one to five stars, or clears its rating
+    pub fn set_rating(&mut self, track: &AudioTrack, rating: Option<u8>) -> rusqlite::Result<()> {
+        let data = self.tracks.entry(track.identity()).or_default();
+        data.rating = rating;
+        let data = *data;
+        self.save(track, data)
+    }
+
+    pub fn set_favourite(&mut self, track: &AudioTrack, favourite: bool) -> rusqlite::Result<()> {
+        let data = self.tracks.entry(track.identity()).or_default();
+        data.favourite = favourite;
+        let data = *data;
+        self.save(track, data)
+    }
+
+    fn save(&self, track: &AudioTrack, data: TrackData) -> rusqlite::Result<()> {
+        self.db.execute(
+            "INSERT INTO ratings (track, rating, favourite) VALUES (?1, ?2, ?3)
+             ON CONFLICT (track) DO UPDATE SET rating = ?2, favourite = ?3",
+            params![track.identity() as i64, data.rating, data.favourite],
+        )?;
+        Ok(())
+    }
+}
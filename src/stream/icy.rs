@@ -0,0 +1,150 @@
+//! ICY metadata: a block of `key='value';` pairs sent every so many bytes of audio, each
+//! block preceded by a byte giving its length in sixteens
+
+use std::io::{self, Read};
+
+use super::StreamTitle;
+
+/// Audio of a stream with the metadata taken out
+pub(super) struct Icy<R> {
+    inner: R,
+    /// Bytes of audio between blocks of metadata, where the server sends any
+    interval: Option<usize>,
+    /// Bytes of audio before the next block
+    left: usize,
+    title: StreamTitle,
+}
+
+impl<R: Read> Icy<R> {
+    pub(super) fn new(inner: R, interval: Option<usize>, title: StreamTitle) -> Self {
+        Self {
+            inner,
+            interval: interval.filter(|i| *i > 0),
+            left: interval.unwrap_or(0),
+            title,
+        }
+    }
+
+    fn read_metadata(&mut self) -> io::Result<()> {
+        let mut length = [0];
+        self.inner.read_exact(&mut length)?;
+        let mut block = vec![0; usize::from(length[0]) * 16];
+        self.inner.read_exact(&mut block)?;
+        if let Some(title) = stream_title(&String::from_utf8_lossy(&block)) {
+            self.title
+                .set((!title.is_empty()).then(|| title.to_owned()));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Icy<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(interval) = self.interval else {
+            return self.inner.read(buf);
+        };
+        if self.left == 0 {
+            self.read_metadata()?;
+            self.left = interval;
+        }
+        let n = buf.len().min(self.left);
+        let n = self.inner.read(&mut buf[..n])?;
+        self.left -= n;
+        Ok(n)
+    }
+}
+
+/// Value of `StreamTitle` in a block of metadata. A value may hold quotes of its own, so it
+/// runs up to the quote ending the pair rather than the next quote
+fn stream_title(block: &str) -> Option<&str> {
+    let start = block.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &block[start..];
+    let end = rest.find("';").or_else(|| rest.rfind('\''))?;
+    Some(&rest[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Audio of `interval` bytes at a time, each run followed by a block of metadata
+    fn interleave(audio: &[u8], interval: usize, blocks: &[&str]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (i, run) in audio.chunks(interval).enumerate() {
+            out.extend_from_slice(run);
+            if run.len() < interval {
+                break;
+            }
+            let mut block = blocks
+                .get(i)
+                .copied()
+                .unwrap_or_default()
+                .as_bytes()
+                .to_vec();
+            block.resize(block.len().div_ceil(16) * 16, 0);
+            out.push((block.len() / 16) as u8);
+            out.extend(block);
+        }
+        out
+    }
+
+    fn read_all(icy: &mut Icy<&[u8]>, at_once: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut buf = vec![0; at_once];
+        loop {
+            match icy.read(&mut buf).unwrap() {
+                0 => return out,
+                n => out.extend_from_slice(&buf[..n]),
+            }
+        }
+    }
+
+    #[test]
+    fn metadata_is_taken_out_of_the_audio() {
+        let audio: Vec<u8> = (0..100).collect();
+        let data = interleave(
+            &audio,
+            16,
+            &[
+                "StreamTitle='First';",
+                "",
+                "StreamTitle='Second';StreamUrl='';",
+            ],
+        );
+        for at_once in [1, 5, 16, 64] {
+            let title = StreamTitle::default();
+            let mut icy = Icy::new(&data[..], Some(16), title.clone());
+            assert_eq!(read_all(&mut icy, at_once), audio);
+            assert_eq!(title.get().as_deref(), Some("Second"));
+        }
+    }
+
+    #[test]
+    fn empty_title_clears_it() {
+        let data = interleave(&[1; 8], 4, &["StreamTitle='Song';", "StreamTitle='';"]);
+        let title = StreamTitle::default();
+        let mut icy = Icy::new(&data[..], Some(4), title.clone());
+        assert_eq!(read_all(&mut icy, 3), [1; 8]);
+        assert_eq!(title.get(), None);
+    }
+
+    #[test]
+    fn without_interval_everything_is_audio() {
+        let data = b"StreamTitle='Song';";
+        let title = StreamTitle::default();
+        let mut icy = Icy::new(&data[..], None, title.clone());
+        assert_eq!(read_all(&mut icy, 7), data);
+        assert_eq!(title.get(), None);
+    }
+
+    #[test]
+    fn title_may_hold_quotes() {
+        assert_eq!(
+            stream_title("StreamTitle='Don't Stop';StreamUrl='x';"),
+            Some("Don't Stop")
+        );
+        assert_eq!(stream_title("StreamTitle='Unended"), None);
+        assert_eq!(stream_title("StreamTitle='Unended'"), Some("Unended"));
+        assert_eq!(stream_title("StreamUrl='x';"), None);
+    }
+}
@@ -0,0 +1,370 @@
+//! Internet radio: audio read from an HTTP or HTTPS stream as it arrives
+//!
+//! A thread of its own connects, so the interface carries on while it waits, then reads the
+//! stream ahead of playback and connects again where the connection drops. Song titles sent
+//! inline by Icecast and Shoutcast servers, as ICY metadata, are taken out of the audio and
+//! kept for showing
+
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, SyncSender},
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::http::{self, Body};
+
+mod icy;
+
+use icy::Icy;
+
+/// Bytes read at a time
+const CHUNK: usize = 16 * 1024;
+
+/// Chunks read ahead of playback, about a minute of a 128 kbps stream
+const AHEAD: usize = 64;
+
+/// Bytes read before playback begins
+const PREBUFFER: usize = 64 * 1024;
+
+/// Attempts at connecting again once the connection drops, each waiting longer, up to a
+/// share of `STALL` so that playback is still waiting by the last of them
+const RECONNECTS: u32 = 5;
+
+/// How long playback waits for the stream to go on before taking it to have ended
+const STALL: Duration = Duration::from_secs(20);
+
+/// Whether a track's path is the URL of a stream rather than a file
+pub fn is_stream(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Audio of a stream, read in order; it cannot seek
+pub struct Stream {
+    chunks: Mutex<Receiver<Vec<u8>>>,
+    chunk: Vec<u8>,
+    /// Bytes of `chunk` already read
+    at: usize,
+    /// Bytes read since the start
+    position: u64,
+}
+
+/// Title of the song playing on a stream, as the station last sent it
+#[derive(Clone, Default)]
+pub struct StreamTitle(Arc<Mutex<Option<String>>>);
+
+impl Stream {
+    /// Connects to a stream on a thread of its own, which goes on to read it. The stream is
+    /// sent once enough of it has arrived to begin playing
+    pub fn open(url: &str) -> Receiver<io::Result<(Self, StreamTitle)>> {
+        let (opened, rx) = mpsc::channel();
+        let url = url.to_owned();
+        thread::spawn(move || {
+            let title = StreamTitle::default();
+            let mut source = match Source::connect(&url, &title) {
+                Ok(s) => s,
+                Err(e) => {
+                    let _ = opened.send(Err(e));
+                    return;
+                }
+            };
+            let prebuffer = source.prebuffer();
+            if prebuffer.is_empty() {
+                let error = io::Error::other(format!("nothing arrived from `{url}`"));
+                let _ = opened.send(Err(error));
+                return;
+            }
+            let (sender, chunks) = mpsc::sync_channel(AHEAD);
+            let stream = Self {
+                chunks: Mutex::new(chunks),
+                chunk: prebuffer,
+                at: 0,
+                position: 0,
+            };
+            // nobody waits for the stream any longer
+            if opened.send(Ok((stream, title.clone()))).is_err() {
+                return;
+            }
+            source.run(&url, &title, &sender);
+        });
+        rx
+    }
+
+    fn next_chunk(&self) -> Option<Vec<u8>> {
+        let chunks = self.chunks.lock().expect("stream chunks");
+        chunks.recv_timeout(STALL).ok()
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.at == self.chunk.len() {
+            match self.next_chunk() {
+                Some(c) => {
+                    self.chunk = c;
+                    self.at = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.at);
+        buf[..n].copy_from_slice(&self.chunk[self.at..self.at + n]);
+        self.at += n;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+/// Seeking goes no further than telling where the stream is
+impl Seek for Stream {
+    fn seek(&mut self, to: SeekFrom) -> io::Result<u64> {
+        match to {
+            SeekFrom::Current(0) => Ok(self.position),
+            SeekFrom::Start(p) if p == self.position => Ok(p),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "a stream cannot seek",
+            )),
+        }
+    }
+}
+
+impl StreamTitle {
+    pub fn get(&self) -> Option<String> {
+        self.0.lock().expect("stream title").clone()
+    }
+
+    fn set(&self, title: Option<String>) {
+        *self.0.lock().expect("stream title") = title;
+    }
+}
+
+/// One connection to a stream
+struct Source {
+    reader: Icy<io::BufReader<http::Connection>>,
+    /// Length of a stream with an end, such as a file served over HTTP
+    length: Option<u64>,
+    read: u64,
+}
+
+impl Source {
+    fn connect(url: &str, title: &StreamTitle) -> io::Result<Self> {
+        let body = http::get(url, &[("Icy-MetaData", "1")])?;
+        Ok(Self::from_body(body, title))
+    }
+
+    fn from_body(body: Body, title: &StreamTitle) -> Self {
+        let interval = body.header("icy-metaint").and_then(|v| v.parse().ok());
+        let length = body.header("content-length").and_then(|v| v.parse().ok());
+        Self {
+            reader: Icy::new(body.reader, interval, title.clone()),
+            length,
+            read: 0,
+        }
+    }
+
+    /// Reads up to `PREBUFFER` bytes, fewer where the stream ends or drops before then
+    fn prebuffer(&mut self) -> Vec<u8> {
+        let mut buffer = vec![0; PREBUFFER];
+        let mut filled = 0;
+        while filled < PREBUFFER {
+            match self.reader.read(&mut buffer[filled..]) {
+                Ok(n) if n > 0 => filled += n,
+                _ => break,
+            }
+        }
+        self.read += filled as u64;
+        buffer.truncate(filled);
+        buffer
+    }
+
+    /// Reads the stream into `chunks` until it ends or playback stops taking from it,
+    /// connecting again where it drops
+    fn run(mut self, url: &str, title: &StreamTitle, chunks: &SyncSender<Vec<u8>>) {
+        loop {
+            let mut chunk = vec![0; CHUNK];
+            match self.reader.read(&mut chunk) {
+                Ok(n) if n > 0 => {
+                    self.read += n as u64;
+                    chunk.truncate(n);
+                    if chunks.send(chunk).is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                _ if self.length.is_some_and(|l| self.read >= l) => return,
+                _ => (),
+            }
+            match self.reconnect(url, title) {
+                Some(source) => self = source,
+                None => return,
+            }
+        }
+    }
+
+    /// Connects again after the connection dropped. A stream with an end asks for the rest
+    /// from where it got to, and ends where the server would send it from the start, rather
+    /// than play again what was heard
+    fn reconnect(&self, url: &str, title: &StreamTitle) -> Option<Self> {
+        let range = format!("bytes={}-", self.read);
+        let mut headers = vec![("Icy-MetaData", "1")];
+        if self.length.is_some() {
+            headers.push(("Range", &range));
+        }
+        for attempt in 0..RECONNECTS {
+            thread::sleep(backoff(attempt));
+            let Ok(body) = http::get(url, &headers) else {
+                continue;
+            };
+            return match (self.length, body.status) {
+                (None, _) => Some(Self::from_body(body, title)),
+                (Some(length), 206) => Some(Self {
+                    length: Some(length),
+                    read: self.read,
+                    ..Self::from_body(body, title)
+                }),
+                _ => None,
+            };
+        }
+        None
+    }
+}
+
+/// Wait before an attempt at connecting again, doubling each time up to a share of `STALL`
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt).min(STALL / RECONNECTS)
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::Source;
+
+    use super::*;
+    use crate::http::stand_in::{self, StandIn};
+
+    /// Bytes standing in for audio, not repeating within a chunk
+    fn audio(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    fn open(url: &str) -> io::Result<(Stream, StreamTitle)> {
+        Stream::open(url)
+            .recv_timeout(Duration::from_secs(5))
+            .expect("stream opened")
+    }
+
+    #[test]
+    fn file_ends_at_its_length() {
+        let body = audio(PREBUFFER + 3 * CHUNK + 100);
+        let mut response =
+            format!("HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        response.extend(&body);
+        let server = StandIn::serve(vec![response]);
+
+        let (mut stream, title) = open(&server.url).unwrap();
+        let mut read = Vec::new();
+        stream.read_to_end(&mut read).unwrap();
+        assert!(read == body);
+        assert_eq!(title.get(), None);
+        assert_eq!(stream.stream_position().unwrap(), body.len() as u64);
+        assert!(server.request().head.contains("\r\nIcy-MetaData: 1\r\n"));
+    }
+
+    #[test]
+    fn dropped_stream_connects_again() {
+        let first = audio(PREBUFFER + CHUNK / 2);
+        // a single block of metadata partway through
+        let second = audio(12_000);
+        let mut title = b"StreamTitle='Next';".to_vec();
+        title.resize(32, 0);
+        let mut again = b"HTTP/1.0 200 OK\r\nicy-metaint: 10000\r\n\r\n".to_vec();
+        again.extend(&second[..10_000]);
+        again.push(2);
+        again.extend(title);
+        again.extend(&second[10_000..]);
+        let server = StandIn::serve(vec![stand_in::response(200, &first), again]);
+
+        let (mut stream, title) = open(&server.url).unwrap();
+        let mut read = vec![0; first.len() + second.len()];
+        stream.read_exact(&mut read).unwrap();
+        assert!(read[..first.len()] == first);
+        assert!(read[first.len()..] == second);
+        assert_eq!(title.get().as_deref(), Some("Next"));
+        server.request();
+        server.request();
+    }
+
+    #[test]
+    fn dropped_file_goes_on_from_where_it_was() {
+        let body = audio(PREBUFFER + CHUNK);
+        let half = PREBUFFER + 100;
+        let head = |status, length| {
+            format!("HTTP/1.0 {status}\r\nContent-Length: {length}\r\n\r\n").into_bytes()
+        };
+        let mut first = head("200 OK", body.len());
+        first.extend(&body[..half]);
+        let mut rest = head("206 Partial Content", body.len() - half);
+        rest.extend(&body[half..]);
+        let server = StandIn::serve(vec![first, rest]);
+
+        let (mut stream, _) = open(&server.url).unwrap();
+        let mut read = Vec::new();
+        stream.read_to_end(&mut read).unwrap();
+        assert!(read == body);
+        assert!(!server.request().head.contains("Range"));
+        let again = server.request().head;
+        assert!(again.contains(&format!("\r\nRange: bytes={half}-\r\n")));
+    }
+
+    #[test]
+    fn dropped_file_ends_where_it_cannot_go_on() {
+        let body = audio(PREBUFFER + CHUNK);
+        let half = PREBUFFER + 100;
+        let mut first =
+            format!("HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        first.extend(&body[..half]);
+        // the server ignores the range, sending the whole file again
+        let mut whole =
+            format!("HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        whole.extend(&body);
+        let server = StandIn::serve(vec![first, whole]);
+
+        let (mut stream, _) = open(&server.url).unwrap();
+        let mut read = Vec::new();
+        stream.read_to_end(&mut read).unwrap();
+        assert!(read == body[..half]);
+    }
+
+    #[test]
+    fn reconnecting_waits_less_than_a_stall() {
+        let waits: Duration = (0..RECONNECTS).map(backoff).sum();
+        assert!(waits < STALL);
+    }
+
+    #[test]
+    fn ogg_vorbis_decodes() {
+        // two seconds of silence in short blocks, at 8 kHz in mono
+        let file = include_bytes!("fixtures/silence.ogg");
+        let mut response =
+            format!("HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n", file.len()).into_bytes();
+        response.extend(file);
+        let server = StandIn::serve(vec![response]);
+
+        let (stream, _) = open(&server.url).unwrap();
+        let decoder = rodio::Decoder::new(stream).unwrap();
+        assert_eq!((decoder.channels(), decoder.sample_rate()), (1, 8000));
+        let samples: Vec<i16> = decoder.collect();
+        assert_eq!(samples.len(), 125 * 128);
+        assert!(samples.iter().all(|s| *s == 0));
+    }
+
+    #[test]
+    fn refusal_fails_to_open() {
+        let server = StandIn::serve(vec![stand_in::response(404, b"gone")]);
+        assert!(open(&server.url).is_err());
+        assert!(open(&stand_in::unreachable()).is_err());
+    }
+}
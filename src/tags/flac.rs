@@ -0,0 +1,335 @@
+//! Vorbis comments of FLAC files
+//!
+//! A FLAC file opens with its marker and a chain of metadata blocks, the last flagged as such,
+//! before the audio frames. Writing replaces the comment block and copies everything else as is
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use super::{Field, Tags};
+
+const MARKER: &[u8; 4] = b"fLaC";
+const STREAMINFO: u8 = 0;
+const VORBIS_COMMENT: u8 = 4;
+/// Largest block the 24 bit length of a block header can describe
+const MAX_BLOCK: usize = (1 << 24) - 1;
+/// Vendor recorded where a file had no comment block to keep one from
+const VENDOR: &str = "mplay";
+/// Joins the values of a repeated field for editing as one, and splits those of `REPEATED`
+/// again on writing
+const SEPARATOR: &str = "; ";
+/// Fields holding several values as a repeated comment, rather than one value that might
+/// itself contain `SEPARATOR`
+const REPEATED: [&str; 3] = ["ARTIST", "ALBUMARTIST", "GENRE"];
+
+struct Block {
+    kind: u8,
+    data: Vec<u8>,
+}
+
+/// Contents of a comment block, each comment a `NAME=value` string
+struct Comments {
+    vendor: Vec<u8>,
+    entries: Vec<String>,
+}
+
+pub fn read(path: &Path) -> io::Result<Tags> {
+    let blocks = read_blocks(&mut BufReader::new(File::open(path)?))?;
+    let mut tags = Tags::default();
+    let Some(block) = blocks.iter().find(|b| b.kind == VORBIS_COMMENT) else {
+        return Ok(tags);
+    };
+
+    for entry in Comments::parse(&block.data)?.entries {
+        let Some((name, value)) = entry.split_once('=') else {
+            continue;
+        };
+        let Some(field) = Field::ALL
+            .into_iter()
+            .find(|f| f.vorbis().eq_ignore_ascii_case(name))
+        else {
+            continue;
+        };
+        // a field may be repeated, as for several artists
+        let joined = match tags.get(field) {
+            "" => value.to_owned(),
+            v => format!("{v}{SEPARATOR}{value}"),
+        };
+        tags.set(field, joined);
+    }
+    Ok(tags)
+}
+
+/// Sets each comment by name, removing it where the value is empty. Values of a field of
+/// `REPEATED` joined by `SEPARATOR` are written as a repeated comment, and a field left as it
+/// was keeps its entries
+pub fn write(path: &Path, edits: &[(&str, String)]) -> io::Result<()> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut blocks = read_blocks(&mut file)?;
+
+    let i = match blocks.iter().position(|b| b.kind == VORBIS_COMMENT) {
+        Some(i) => i,
+        None => {
+            let after = blocks
+                .iter()
+                .position(|b| b.kind == STREAMINFO)
+                .map_or(0, |i| i + 1);
+            let empty = Comments {
+                vendor: VENDOR.as_bytes().to_vec(),
+                entries: Vec::new(),
+            };
+            blocks.insert(
+                after,
+                Block {
+                    kind: VORBIS_COMMENT,
+                    data: empty.serialise(),
+                },
+            );
+            after
+        }
+    };
+
+    let mut comments = Comments::parse(&blocks[i].data)?;
+    for (name, value) in edits {
+        let named = |e: &String| {
+            e.split_once('=')
+                .is_some_and(|(n, _)| n.eq_ignore_ascii_case(name))
+        };
+        let current: Vec<&str> = comments
+            .entries
+            .iter()
+            .filter(|e| named(e))
+            .filter_map(|e| e.split_once('=').map(|(_, v)| v))
+            .collect();
+        if current.join(SEPARATOR) == *value {
+            continue;
+        }
+        comments.entries.retain(|e| !named(e));
+        let values: Vec<&str> = match REPEATED.iter().any(|r| r.eq_ignore_ascii_case(name)) {
+            true => value.split(SEPARATOR).collect(),
+            false => vec![value],
+        };
+        comments.entries.extend(
+            values
+                .into_iter()
+                .filter(|v| !v.is_empty())
+                .map(|v| format!("{name}={v}")),
+        );
+    }
+    blocks[i].data = comments.serialise();
+    if blocks[i].data.len() > MAX_BLOCK {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "tags too large for a flac comment block",
+        ));
+    }
+
+    let tmp = temp_path(path);
+    let result = write_file(&tmp, &blocks, &mut file)
+        .and_then(|()| fs::set_permissions(&tmp, fs::metadata(path)?.permissions()))
+        .and_then(|()| fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Reads the marker and every metadata block, leaving `file` at the first audio frame
+fn read_blocks(file: &mut impl Read) -> io::Result<Vec<Block>> {
+    let mut marker = [0; 4];
+    file.read_exact(&mut marker)?;
+    if &marker != MARKER {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a flac file, or one preceded by an id3 tag",
+        ));
+    }
+
+    let mut blocks = Vec::new();
+    loop {
+        let mut header = [0; 4];
+        file.read_exact(&mut header)?;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let mut data = vec![0; len];
+        file.read_exact(&mut data)?;
+        blocks.push(Block {
+            kind: header[0] & 0x7f,
+            data,
+        });
+        if header[0] & 0x80 != 0 {
+            return Ok(blocks);
+        }
+    }
+}
+
+/// Writes the marker, `blocks` and the rest of `audio` to `path`, flushed to disk
+fn write_file(path: &Path, blocks: &[Block], audio: &mut impl Read) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(MARKER)?;
+    for (n, block) in blocks.iter().enumerate() {
+        let last = match n + 1 == blocks.len() {
+            true => 0x80,
+            false => 0,
+        };
+        let len = (block.data.len() as u32).to_be_bytes();
+        out.write_all(&[block.kind | last, len[1], len[2], len[3]])?;
+        out.write_all(&block.data)?;
+    }
+    io::copy(audio, &mut out)?;
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+/// Hidden file beside `path`, named so the library watcher takes no notice of it
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.mplay-tmp"))
+}
+
+impl Comments {
+    /// Parses the little endian, length prefixed fields of a comment block
+    fn parse(data: &[u8]) -> io::Result<Self> {
+        let mut rest = data;
+        let vendor = take_field(&mut rest)?.to_vec();
+        let count = take_length(&mut rest)?;
+        let mut entries = Vec::with_capacity(count.min(256));
+        for _ in 0..count {
+            entries.push(String::from_utf8_lossy(take_field(&mut rest)?).into_owned());
+        }
+        Ok(Self { vendor, entries })
+    }
+
+    fn serialise(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend((self.vendor.len() as u32).to_le_bytes());
+        out.extend(&self.vendor);
+        out.extend((self.entries.len() as u32).to_le_bytes());
+        for e in &self.entries {
+            out.extend((e.len() as u32).to_le_bytes());
+            out.extend(e.as_bytes());
+        }
+        out
+    }
+}
+
+fn take<'a>(rest: &mut &'a [u8], n: usize) -> io::Result<&'a [u8]> {
+    if rest.len() < n {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "truncated flac comment block",
+        ));
+    }
+    let (head, tail) = rest.split_at(n);
+    *rest = tail;
+    Ok(head)
+}
+
+fn take_length(rest: &mut &[u8]) -> io::Result<usize> {
+    take(rest, 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+}
+
+fn take_field<'a>(rest: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+    let n = take_length(rest)?;
+    take(rest, n)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    /// A file of a marker, an empty stream info block and the given comments, with a few bytes
+    /// standing in for audio
+    fn fixture(name: &str, entries: &[&str]) -> PathBuf {
+        let comments = Comments {
+            vendor: VENDOR.as_bytes().to_vec(),
+            entries: entries.iter().map(|e| e.to_string()).collect(),
+        };
+        let blocks = [
+            Block {
+                kind: STREAMINFO,
+                data: vec![0; 34],
+            },
+            Block {
+                kind: VORBIS_COMMENT,
+                data: comments.serialise(),
+            },
+        ];
+        let path = env::temp_dir().join(format!("mplay-{}-{name}.flac", std::process::id()));
+        write_file(&path, &blocks, &mut &b"audio"[..]).unwrap();
+        path
+    }
+
+    fn entries(path: &Path) -> Vec<String> {
+        let blocks = read_blocks(&mut BufReader::new(File::open(path).unwrap())).unwrap();
+        let block = blocks.iter().find(|b| b.kind == VORBIS_COMMENT).unwrap();
+        Comments::parse(&block.data).unwrap().entries
+    }
+
+    #[test]
+    fn repeated_fields_read_joined() {
+        let path = fixture("joined", &["ARTIST=A", "TITLE=T", "artist=B"]);
+        let tags = read(&path).unwrap();
+        assert_eq!(tags.get(Field::Artist), "A; B");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn other_edits_keep_repeated_fields() {
+        let path = fixture("kept", &["ARTIST=A", "ARTIST=B", "TITLE=T"]);
+        write(
+            &path,
+            &[
+                ("TITLE", String::from("U")),
+                ("ARTIST", String::from("A; B")),
+            ],
+        )
+        .unwrap();
+        assert_eq!(entries(&path), ["ARTIST=A", "ARTIST=B", "TITLE=U"]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn edited_field_splits_into_repeats() {
+        let path = fixture("split", &["ARTIST=A", "ARTIST=B", "TITLE=T"]);
+        write(&path, &[("ARTIST", String::from("A; C; D"))]).unwrap();
+        assert_eq!(
+            entries(&path),
+            ["TITLE=T", "ARTIST=A", "ARTIST=C", "ARTIST=D"]
+        );
+        write(&path, &[("ARTIST", String::new())]).unwrap();
+        assert_eq!(entries(&path), ["TITLE=T"]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn single_fields_keep_the_separator() {
+        let path = fixture("single", &["TITLE=T", "GENRE=Jazz"]);
+        write(
+            &path,
+            &[
+                ("TITLE", String::from("Sorry; Not Sorry")),
+                ("ALBUM", String::from("Odds; Ends")),
+                ("GENRE", String::from("Jazz; Funk")),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            entries(&path),
+            [
+                "TITLE=Sorry; Not Sorry",
+                "ALBUM=Odds; Ends",
+                "GENRE=Jazz",
+                "GENRE=Funk"
+            ]
+        );
+        let tags = read(&path).unwrap();
+        assert_eq!(tags.get(Field::Title), "Sorry; Not Sorry");
+        assert_eq!(tags.get(Field::Album), "Odds; Ends");
+        assert_eq!(tags.get(Field::Genre), "Jazz; Funk");
+        fs::remove_file(path).unwrap();
+    }
+}
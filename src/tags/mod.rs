@@ -0,0 +1,107 @@
+//! Reads and writes the tags of audio files, for editing them in place
+//!
+//! Only FLAC files, tagged with Vorbis comments, can be written for now. Files are rewritten
+//! beside the original then renamed over it, so a failed write never leaves a damaged file
+
+use std::{io, path::Path};
+
+mod flac;
+
+/// Number of editable fields
+const FIELDS: usize = 8;
+
+/// Tag shown in the editor
+#[derive(Clone, Copy, PartialEq)]
+pub enum Field {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Date,
+    TrackNumber,
+    DiscNumber,
+    Genre,
+}
+
+/// Values of the editable fields of one file, empty where absent
+#[derive(Clone, Default, PartialEq)]
+pub struct Tags([String; FIELDS]);
+
+impl Field {
+    pub const ALL: [Field; FIELDS] = [
+        Field::Title,
+        Field::Artist,
+        Field::Album,
+        Field::AlbumArtist,
+        Field::Date,
+        Field::TrackNumber,
+        Field::DiscNumber,
+        Field::Genre,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Title => "title",
+            Field::Artist => "artist",
+            Field::Album => "album",
+            Field::AlbumArtist => "album artist",
+            Field::Date => "date",
+            Field::TrackNumber => "track",
+            Field::DiscNumber => "disc",
+            Field::Genre => "genre",
+        }
+    }
+
+    /// Name of the field as a Vorbis comment
+    fn vorbis(self) -> &'static str {
+        match self {
+            Field::Title => "TITLE",
+            Field::Artist => "ARTIST",
+            Field::Album => "ALBUM",
+            Field::AlbumArtist => "ALBUMARTIST",
+            Field::Date => "DATE",
+            Field::TrackNumber => "TRACKNUMBER",
+            Field::DiscNumber => "DISCNUMBER",
+            Field::Genre => "GENRE",
+        }
+    }
+}
+
+impl Tags {
+    pub fn get(&self, field: Field) -> &str {
+        &self.0[field as usize]
+    }
+
+    pub fn set(&mut self, field: Field, value: String) {
+        self.0[field as usize] = value;
+    }
+}
+
+pub fn read(path: &Path) -> io::Result<Tags> {
+    check_format(path)?;
+    flac::read(path)
+}
+
+/// Sets each given field, removing it where the value is empty. Other tags are kept
+pub fn write(path: &Path, edits: &[(Field, String)]) -> io::Result<()> {
+    check_format(path)?;
+    let edits: Vec<(&str, String)> = edits.iter().map(|(f, v)| (f.vorbis(), v.clone())).collect();
+    flac::write(path, &edits)
+}
+
+/// Writes a rating of one to five stars as `FMPS_RATING`, from 0 to 1, or removes it for none
+pub fn write_rating(path: &Path, stars: Option<u8>) -> io::Result<()> {
+    check_format(path)?;
+    let value = stars.map_or(String::new(), |s| (f32::from(s) / 5.0).to_string());
+    flac::write(path, &[("FMPS_RATING", value)])
+}
+
+fn check_format(path: &Path) -> io::Result<()> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("flac") => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "tags can only be edited in flac files",
+        )),
+    }
+}
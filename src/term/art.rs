@@ -0,0 +1,86 @@
+//! Album art beside the status panel
+//!
+//! Half blocks are drawn into the frame like any widget. Images sent by escape sequence bypass
+//! the frame, so are written after it, and only again once the track, area or terminal changes
+
+use std::io::Write;
+
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::{cursor::MoveTo, queue, style::Print, terminal},
+    layout::Rect,
+    style::Color,
+};
+
+use super::UserInterface;
+use crate::art::{KITTY_DELETE, Picture, Protocol};
+
+/// Cell size assumed where the terminal does not report its size in pixels
+const FALLBACK_CELL: (u16, u16) = (8, 16);
+
+impl UserInterface {
+    pub(super) fn render_art(&mut self, area: Rect, frame: &mut Frame) {
+        let pane = &mut self.state.art;
+        if pane.art.update(self.player.playing().map(|t| &**t)) || pane.area != area {
+            pane.stale = true;
+        }
+        pane.area = area;
+
+        let Some(Picture::Cells(rows)) = pane.art.picture(area.width, area.height, cell_size())
+        else {
+            return;
+        };
+        let buf = frame.buffer_mut();
+        for (y, row) in (area.y..).zip(rows) {
+            for (x, (top, bottom)) in (area.x..).zip(row) {
+                let cell = &mut buf[(x, y)];
+                cell.set_char('▀')
+                    .set_fg(Color::Rgb(top[0], top[1], top[2]));
+                if let Some([r, g, b]) = *bottom {
+                    cell.set_bg(Color::Rgb(r, g, b));
+                }
+            }
+        }
+    }
+
+    /// Writes art sent by escape sequence over the frame just drawn, first removing any shown
+    pub(super) fn flush_art(&mut self, terminal: &mut DefaultTerminal) {
+        let pane = &mut self.state.art;
+        if !pane.stale {
+            return;
+        }
+        pane.stale = false;
+
+        let area = pane.area;
+        let out = terminal.backend_mut();
+        if let Some(shown) = pane.shown.take() {
+            match pane.art.protocol() {
+                Protocol::Kitty => queue!(out, Print(KITTY_DELETE)),
+                // sixel pixels are only cleared by writing over their cells
+                _ => (shown.y..shown.bottom()).try_for_each(|y| {
+                    queue!(
+                        out,
+                        MoveTo(shown.x, y),
+                        Print(" ".repeat(shown.width as usize))
+                    )
+                }),
+            }
+            .expect("terminal write");
+        }
+        if let Some(Picture::Escape(seq)) = pane.art.picture(area.width, area.height, cell_size()) {
+            queue!(out, MoveTo(area.x, area.y), Print(seq)).expect("terminal write");
+            pane.shown = Some(area);
+        }
+        out.flush().expect("terminal flush");
+    }
+}
+
+/// Size of one cell in pixels
+fn cell_size() -> (u16, u16) {
+    match terminal::window_size() {
+        Ok(s) if s.width > 0 && s.height > 0 && s.columns > 0 && s.rows > 0 => {
+            ((s.width / s.columns).max(1), (s.height / s.rows).max(1))
+        }
+        _ => FALLBACK_CELL,
+    }
+}
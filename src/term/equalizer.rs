@@ -0,0 +1,229 @@
+//! Equalizer panel: per band gain, frequency and width, preamp, balance and effect toggles
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    text::Line,
+    widgets::{Cell, Row, Table},
+};
+
+use super::UserInterface;
+use super::navigate::navigate;
+use crate::config;
+use crate::keymap::Action;
+use crate::playback::{BANDS, DspSettings};
+
+/// Gain limits for preamp and bands, in dB
+const GAIN_RANGE: f32 = 12.0;
+const GAIN_STEP: f32 = 0.5;
+const BALANCE_STEP: f32 = 0.05;
+/// Width of the gain bar, in cells either side of centre
+const BAR_HALF: usize = 12;
+
+/// Row of the panel being edited
+enum Control {
+    Preamp,
+    Band(usize),
+    Balance,
+}
+
+impl Control {
+    fn from_row(row: usize) -> Self {
+        match row {
+            0 => Control::Preamp,
+            n if n <= BANDS => Control::Band(n - 1),
+            _ => Control::Balance,
+        }
+    }
+}
+
+impl UserInterface {
+    pub(super) fn handle_equalizer_action(&mut self, action: Action) {
+        let control = Control::from_row(self.state.equalizer.selected().unwrap_or(0));
+        let dsp = self.player.dsp();
+        // a sixth of an octave
+        let octave_step = 2f32.powf(1.0 / 6.0);
+        match action {
+            Action::Down
+            | Action::Up
+            | Action::PageDown
+            | Action::PageUp
+            | Action::HalfPageDown
+            | Action::HalfPageUp
+            | Action::Bottom
+            | Action::Top => {
+                // every control fits on the screen, so a page is all of them
+                navigate(&mut self.state.equalizer, action, BANDS + 2, BANDS + 2);
+            }
+            Action::Decrease => dsp.update(|s| adjust(s, &control, -1.0)),
+            Action::Increase => dsp.update(|s| adjust(s, &control, 1.0)),
+            Action::LowerFrequency | Action::RaiseFrequency => {
+                if let Control::Band(i) = control {
+                    dsp.update(|s| {
+                        let b = &mut s.bands[i].frequency;
+                        *b = match action {
+                            Action::LowerFrequency => *b / octave_step,
+                            _ => *b * octave_step,
+                        }
+                        .clamp(20.0, 20000.0)
+                        .round();
+                    });
+                }
+            }
+            Action::LowerQ | Action::RaiseQ => {
+                if let Control::Band(i) = control {
+                    dsp.update(|s| {
+                        let q = &mut s.bands[i].q;
+                        *q = match action {
+                            Action::LowerQ => *q - 0.1,
+                            _ => *q + 0.1,
+                        }
+                        .clamp(0.1, 10.0);
+                    });
+                }
+            }
+            Action::NextPreset => dsp.update(DspSettings::next_preset),
+            Action::ResetEqualizer => dsp.update(|s| *s = DspSettings::default()),
+            Action::ToggleMono => dsp.update(|s| s.mono = !s.mono),
+            Action::ToggleLimiter => dsp.update(|s| s.limiter = !s.limiter),
+            Action::ToggleEqualizer => dsp.update(|s| s.enabled = !s.enabled),
+            Action::SaveEqualizer => {
+                let entries = config::dsp_entries(&dsp.settings());
+                self.state.message = Some(match config::save_section("equalizer", &entries) {
+                    Ok(()) => String::from("equalizer saved"),
+                    Err(e) => format!("equalizer not saved: {e}"),
+                });
+            }
+            _ => (),
+        }
+    }
+
+    pub(super) fn render_equalizer(&mut self, area: Rect, frame: &mut Frame) {
+        let settings = self.player.dsp().settings();
+
+        let [table_area, help_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+
+        let header = Row::new([
+            Cell::new("control"),
+            Cell::new("frequency"),
+            Cell::new("gain"),
+            Cell::new("q"),
+            Cell::new(""),
+        ])
+        .style(self.theme.header);
+
+        let mut rows = Vec::with_capacity(BANDS + 2);
+        rows.push(Row::new([
+            Cell::new("preamp"),
+            Cell::new(""),
+            Cell::new(format!("{:+.1} dB", settings.preamp)),
+            Cell::new(""),
+            Cell::new(bar(settings.preamp / GAIN_RANGE)),
+        ]));
+        for (i, b) in settings.bands.iter().enumerate() {
+            rows.push(Row::new([
+                Cell::new(format!("band {}", i + 1)),
+                Cell::new(format_frequency(b.frequency)),
+                Cell::new(format!("{:+.1} dB", b.gain)),
+                Cell::new(format!("{:.2}", b.q)),
+                Cell::new(bar(b.gain / GAIN_RANGE)),
+            ]));
+        }
+        rows.push(Row::new([
+            Cell::new("balance"),
+            Cell::new(""),
+            Cell::new(format!("{:+.2}", settings.balance)),
+            Cell::new(""),
+            Cell::new(bar(settings.balance)),
+        ]));
+
+        let on_off = |b: bool| if b { "on" } else { "off" };
+        let title = format!(
+            "equalizer | preset: {} | mono: {} | limiter: {} | {}",
+            settings.preset.as_deref().unwrap_or("custom"),
+            on_off(settings.mono),
+            on_off(settings.limiter),
+            if settings.enabled {
+                "active"
+            } else {
+                "bypassed"
+            },
+        );
+
+        let tbl = Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Length(10),
+                Constraint::Length(9),
+                Constraint::Length(5),
+                Constraint::Length(BAR_HALF as u16 * 2 + 1),
+            ],
+        )
+        .block(self.theme.block().title(title))
+        .header(header)
+        .column_spacing(2)
+        .row_highlight_style(self.theme.highlight);
+
+        frame.render_stateful_widget(tbl, table_area, &mut self.state.equalizer);
+        let key = |a| self.keymap.hint(a);
+        let help = format!(
+            "{}/{} adjust  {}/{} frequency  {}/{} q  {} preset  {} reset  {} mono  {} limiter  {} bypass  {} save",
+            key(Action::Decrease),
+            key(Action::Increase),
+            key(Action::LowerFrequency),
+            key(Action::RaiseFrequency),
+            key(Action::LowerQ),
+            key(Action::RaiseQ),
+            key(Action::NextPreset),
+            key(Action::ResetEqualizer),
+            key(Action::ToggleMono),
+            key(Action::ToggleLimiter),
+            key(Action::ToggleEqualizer),
+            key(Action::SaveEqualizer),
+        );
+        frame.render_widget(Line::styled(help, self.theme.hint), help_area);
+    }
+}
+
+/// Nudges the selected control by one step in the given direction
+fn adjust(settings: &mut DspSettings, control: &Control, direction: f32) {
+    match control {
+        Control::Preamp => {
+            settings.preamp =
+                (settings.preamp + GAIN_STEP * direction).clamp(-GAIN_RANGE, GAIN_RANGE)
+        }
+        Control::Band(i) => {
+            let gain = &mut settings.bands[*i].gain;
+            *gain = (*gain + GAIN_STEP * direction).clamp(-GAIN_RANGE, GAIN_RANGE);
+            settings.preset = None;
+        }
+        Control::Balance => {
+            settings.balance = (settings.balance + BALANCE_STEP * direction).clamp(-1.0, 1.0)
+        }
+    }
+}
+
+/// Draws a bar extending left or right of centre in proportion to `value` within -1.0 to 1.0
+fn bar(value: f32) -> String {
+    let n = (value.clamp(-1.0, 1.0).abs() * BAR_HALF as f32).round() as usize;
+    let (left, right) = match value < 0.0 {
+        true => (
+            " ".repeat(BAR_HALF - n) + &"█".repeat(n),
+            " ".repeat(BAR_HALF),
+        ),
+        false => (
+            " ".repeat(BAR_HALF),
+            "█".repeat(n) + &" ".repeat(BAR_HALF - n),
+        ),
+    };
+    format!("{left}│{right}")
+}
+
+fn format_frequency(hz: f32) -> String {
+    match hz >= 1000.0 {
+        true => format!("{:.1} kHz", hz / 1000.0),
+        false => format!("{hz:.0} Hz"),
+    }
+}
@@ -0,0 +1,87 @@
+//! Help popup listing the key bindings in effect, by the context they apply in
+
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+};
+
+use super::UserInterface;
+use super::overlay::centered;
+use super::state::Overlay;
+use crate::keymap::{Action, Context};
+
+/// Contexts in the order their bindings are listed
+const CONTEXTS: [Context; 7] = [
+    Context::Global,
+    Context::Tracks,
+    Context::Equalizer,
+    Context::Lyrics,
+    Context::Report,
+    Context::Stats,
+    Context::Podcasts,
+];
+/// Width of the key column
+const KEYS_WIDTH: usize = 18;
+
+impl UserInterface {
+    pub(super) fn handle_help_key(&mut self, key: KeyEvent) {
+        let Some(Overlay::Help(scroll)) = &mut self.state.overlay else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?') => self.state.overlay = None,
+            KeyCode::Down | KeyCode::Char('j') => *scroll = scroll.saturating_add(1),
+            KeyCode::Up | KeyCode::Char('k') => *scroll = scroll.saturating_sub(1),
+            KeyCode::PageDown => *scroll = scroll.saturating_add(10),
+            KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+            _ => (),
+        }
+    }
+
+    /// Bindings and configuration name of every action under a heading for each context, an
+    /// action shared by views listed under each
+    fn help_lines(&self) -> Vec<Line<'static>> {
+        let mut lines = Vec::with_capacity(Action::ALL.len() + 16);
+        for context in CONTEXTS {
+            if !lines.is_empty() {
+                lines.push(Line::default());
+            }
+            lines.push(Line::styled(context.title(), self.theme.title));
+            for action in Action::ALL {
+                if !action.contexts().contains(&context) {
+                    continue;
+                }
+                let keys = match self.keymap.sequences(action) {
+                    s if s.is_empty() => {
+                        Span::styled(format!("{:>KEYS_WIDTH$}", "unbound"), self.theme.dim)
+                    }
+                    s => Span::raw(format!("{:>KEYS_WIDTH$}", s.join(", "))),
+                };
+                lines.push(Line::from(vec![
+                    keys,
+                    Span::raw("  "),
+                    Span::raw(action.description()),
+                    Span::styled(format!("  {}", action.name()), self.theme.dim),
+                ]));
+            }
+        }
+        lines
+    }
+
+    pub(super) fn render_help(&self, frame: &mut Frame, scroll: u16) {
+        let lines = self.help_lines();
+        let area = centered(frame.area(), 72, lines.len() as u16 + 2);
+        // keep the last line in view rather than scrolling past it
+        let scroll = scroll.min((lines.len() as u16 + 2).saturating_sub(area.height));
+        let widget = Paragraph::new(lines).scroll((scroll, 0)).block(
+            self.theme
+                .block()
+                .title("key bindings")
+                .title_bottom(Line::styled("esc close", self.theme.hint).right_aligned()),
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(widget, area);
+    }
+}
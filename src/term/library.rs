@@ -0,0 +1,183 @@
+//! Keeps the track list in step with the library on disk
+
+use std::{
+    path::Path,
+    rc::Rc,
+    sync::mpsc::{Receiver, TryRecvError},
+};
+
+use super::UserInterface;
+use crate::loader::{self, AudioTrack, LibraryCollection, Report, ScanEvent};
+use crate::watch::Change;
+
+/// Progress of a scan of the whole library
+pub(super) struct Scan {
+    events: Receiver<ScanEvent>,
+    found: usize,
+    read: usize,
+    report: Report,
+    /// Tracks read by a rescan, replacing the list once complete. A first scan adds to the list
+    /// directly, so tracks can be played as they are found
+    fresh: Option<LibraryCollection>,
+}
+
+impl UserInterface {
+    /// Starts reading the library on a pool of threads, replacing the list once done where
+    /// `replace` is set and otherwise adding to it as tracks are read
+    pub(super) fn start_scan(&mut self, replace: bool) {
+        self.scan = Some(Scan {
+            events: loader::scan(self.library.clone(), self.follow_symlinks),
+            found: 0,
+            read: 0,
+            report: Report::default(),
+            fresh: replace.then(|| LibraryCollection::with_capacity(self.tracks.len())),
+        });
+    }
+
+    /// Reads the whole library again, for when watching has missed a change
+    pub(super) fn rescan(&mut self) {
+        match self.scan {
+            Some(_) => self.state.message = Some(String::from("scan already in progress")),
+            None => self.start_scan(true),
+        }
+    }
+
+    /// Takes in tracks read by the scan so far, then sorts the list once it completes
+    pub(super) fn poll_scan(&mut self) {
+        let Some(scan) = self.scan.as_mut() else {
+            return;
+        };
+        loop {
+            match scan.events.try_recv() {
+                Ok(ScanEvent::Found) => scan.found += 1,
+                Ok(ScanEvent::Read(tracks)) => {
+                    scan.read += 1;
+                    let tracks = tracks.into_iter().map(Rc::new);
+                    match &mut scan.fresh {
+                        Some(fresh) => fresh.extend(tracks),
+                        None => self.tracks.extend(tracks),
+                    }
+                }
+                Ok(ScanEvent::Failed(d)) => {
+                    scan.read += 1;
+                    scan.report.push(d);
+                }
+                Ok(ScanEvent::Problem(d)) => scan.report.push(d),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+
+        let Some(mut scan) = self.scan.take() else {
+            return;
+        };
+        let selected = self.selected_path();
+        if let Some(fresh) = scan.fresh {
+            self.tracks = fresh;
+        }
+        // tracks arrive in whichever order they are read; settle on one that is stable
+        self.tracks.sort_by(|a, b| a.path().cmp(b.path()));
+        self.reselect(selected);
+        self.refresh_playlist_menu();
+
+        scan.report.complete(self.tracks.iter().map(|t| &**t));
+        let mut message = format!("scanned {} tracks", group_digits(self.tracks.len()));
+        if !scan.report.is_empty() {
+            let n = group_digits(scan.report.diagnostics.len());
+            message.push_str(&format!(", {n} problems in the report"));
+        }
+        self.state.message = Some(message);
+        self.state.report.report = Some(scan.report);
+        self.state.report.table.select_first();
+    }
+
+    /// Files read out of those found, while a scan is running
+    pub(super) fn scan_progress(&self) -> Option<String> {
+        self.scan.as_ref().map(|s| {
+            format!(
+                "scanning {} / {}",
+                group_digits(s.read),
+                group_digits(s.found)
+            )
+        })
+    }
+
+    /// Applies any changes reported by the watcher, keeping the same track selected
+    pub(super) fn apply_changes(&mut self) {
+        let batches: Vec<Vec<Change>> = self.changes.try_iter().collect();
+        if batches.is_empty() {
+            return;
+        }
+
+        let selected = self.selected_path();
+        for change in batches.into_iter().flatten() {
+            match change {
+                Change::Updated(track) => self.upsert(track),
+                Change::Removed(path) => self
+                    .tracks
+                    .retain(|t| !Path::new(t.file()).starts_with(&path)),
+                Change::Rescan => self.rescan(),
+            }
+        }
+        self.reselect(selected);
+        self.refresh_playlist_menu();
+    }
+
+    /// Replaces the listed track with the same path, or adds it where there is none
+    pub(super) fn upsert(&mut self, track: AudioTrack) {
+        let track = Rc::new(track);
+        match self.tracks.iter().position(|t| t.path() == track.path()) {
+            Some(i) => self.tracks[i] = track,
+            None => self.tracks.push(track),
+        }
+    }
+
+    pub(super) fn selected_path(&self) -> Option<String> {
+        let i = self.state.all_tracks.selected()?;
+        self.visible_tracks().get(i).map(|t| t.path().to_owned())
+    }
+
+    /// Selects the track at `path` where it is still listed, otherwise keeps the selection
+    /// within the list
+    pub(super) fn reselect(&mut self, path: Option<String>) {
+        let visible = self.visible_tracks();
+        let found = path.and_then(|p| visible.iter().position(|t| t.path() == p));
+        let row = match found {
+            Some(i) => Some(i),
+            None => self
+                .state
+                .all_tracks
+                .selected()
+                .map(|i| i.min(visible.len().saturating_sub(1))),
+        };
+        self.state.all_tracks.select(row);
+    }
+
+    /// Selects the playing track, clearing a search that hides it
+    pub(super) fn jump_to_playing(&mut self) {
+        let Some(path) = self.player.playing().map(|t| t.path().to_owned()) else {
+            self.state.message = Some(String::from("nothing playing"));
+            return;
+        };
+        if !self.visible_tracks().iter().any(|t| t.path() == path) {
+            self.state.search.query.clear();
+            self.state.search.cursor_index = 0;
+            // rows of a visual range would now point elsewhere
+            self.state.visual = None;
+        }
+        self.reselect(Some(path));
+    }
+}
+
+/// Formats a count with thousands set apart by spaces, as in `20 118`
+pub(super) fn group_digits(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(' ');
+        }
+        out.push(c);
+    }
+    out
+}
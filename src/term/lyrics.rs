@@ -0,0 +1,117 @@
+//! Lyrics panel, following the playback position for synchronised lyrics
+
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    text::Line,
+    widgets::Paragraph,
+};
+
+use super::UserInterface;
+use crate::keymap::Action;
+use crate::lyrics::{self, Lyrics};
+
+/// Step of the offset adjustment, in milliseconds
+const OFFSET_STEP: i64 = 100;
+
+impl UserInterface {
+    pub(super) fn handle_lyrics_action(&mut self, action: Action) {
+        // lines inside the border
+        let page = self.state.areas.body.height.saturating_sub(2).max(2);
+        let pane = &mut self.state.lyrics;
+        match action {
+            Action::LyricsEarlier => pane.offset -= OFFSET_STEP,
+            Action::LyricsLater => pane.offset += OFFSET_STEP,
+            Action::ResetLyricsOffset => pane.offset = 0,
+            Action::Down => pane.scroll = pane.scroll.saturating_add(1),
+            Action::Up => pane.scroll = pane.scroll.saturating_sub(1),
+            Action::PageDown => pane.scroll = pane.scroll.saturating_add(page),
+            Action::PageUp => pane.scroll = pane.scroll.saturating_sub(page),
+            Action::HalfPageDown => pane.scroll = pane.scroll.saturating_add(page / 2),
+            Action::HalfPageUp => pane.scroll = pane.scroll.saturating_sub(page / 2),
+            Action::Top => pane.scroll = 0,
+            _ => (),
+        }
+    }
+
+    /// Reloads lyrics when the playing track has changed since they were last loaded
+    fn refresh_lyrics(&mut self) {
+        let playing = self.player.playing();
+        let path = playing.map(|t| t.path());
+        if self.state.lyrics.track.as_deref() != path {
+            self.state.lyrics.track = path.map(str::to_owned);
+            self.state.lyrics.lyrics = playing.and_then(|t| Lyrics::load(t));
+            self.state.lyrics.scroll = 0;
+        }
+    }
+
+    pub(super) fn render_lyrics(&mut self, area: Rect, frame: &mut Frame) {
+        self.refresh_lyrics();
+        let pane = &self.state.lyrics;
+
+        let mut block = self.theme.block().title("lyrics");
+
+        let Some(lyrics) = &pane.lyrics else {
+            let text = match pane.track {
+                Some(_) => "no lyrics found",
+                None => "nothing playing",
+            };
+            frame.render_widget(
+                Paragraph::new(text)
+                    .alignment(Alignment::Center)
+                    .block(block),
+                area,
+            );
+            return;
+        };
+
+        let position = self
+            .player
+            .position()
+            .map(|p| lyrics::shift(p, pane.offset));
+        let current = position.and_then(|p| lyrics.current_line(p));
+
+        let lines: Vec<Line> = lyrics
+            .lines()
+            .into_iter()
+            .enumerate()
+            .map(|(i, l)| match Some(i) == current {
+                true => Line::styled(l, self.theme.lyric),
+                false => Line::from(l),
+            })
+            .collect();
+
+        // keep the current line centred; plain lyrics scroll by hand
+        let scroll = match (lyrics, current) {
+            (Lyrics::Timed(_), Some(i)) => {
+                (i as u16).saturating_sub(area.height.saturating_sub(2) / 2)
+            }
+            (Lyrics::Timed(_), None) => 0,
+            (Lyrics::Plain(_), _) => pane.scroll,
+        };
+
+        if let Lyrics::Timed(_) = lyrics {
+            block = block.title_bottom(
+                Line::styled(
+                    format!(
+                        "offset {:+.1}s  {}/{} adjust  {} reset",
+                        pane.offset as f32 / 1000.0,
+                        self.keymap.hint(Action::LyricsEarlier),
+                        self.keymap.hint(Action::LyricsLater),
+                        self.keymap.hint(Action::ResetLyricsOffset),
+                    ),
+                    self.theme.hint,
+                )
+                .right_aligned(),
+            );
+        }
+
+        frame.render_widget(
+            Paragraph::new(lines)
+                .alignment(Alignment::Center)
+                .scroll((scroll, 0))
+                .block(block),
+            area,
+        );
+    }
+}
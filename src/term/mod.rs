@@ -6,254 +6,693 @@
 // artist: display all artists, selecing provides view of their albums and tracks
 // album: display all albums, selecting provides view of their tracks
 
-use std::{ops::Deref, rc::Rc, time::Duration};
+use std::{
+    collections::HashMap, io, mem, ops::Deref, path::PathBuf, rc::Rc, sync::mpsc::Receiver,
+    time::Duration,
+};
 
 use ratatui::{
     DefaultTerminal, Frame,
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    crossterm::{
+        event::{
+            self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        },
+        execute,
+    },
     layout::{Constraint, Layout, Position, Rect},
-    prelude::Stylize,
-    style::{Modifier, Style},
-    widgets::{Block, Cell, Paragraph, Row, Table},
+    text::{Line, Span},
+    widgets::{Cell, Paragraph, Row, Table, Tabs},
 };
 
+use crate::art::Protocol;
+use crate::autodj::AutoDj;
+use crate::config::{Config, StatsConfig};
+use crate::keymap::{Action, Key, Keymap, Lookup};
 use crate::loader::{AudioTrack, LibraryCollection};
-use crate::playback::Player;
+use crate::playback::{Player, PlayerEvent};
+use crate::playlist::SmartPlaylist;
+use crate::podcast::Podcasts;
+use crate::query::{Listed, Query, SortKey};
+use crate::scrobble::Scrobbler;
+use crate::stats::{Stats, TrackData};
+use crate::theme::{Depth, Theme};
+use crate::watch::{self, Change};
 
+mod art;
+mod equalizer;
+mod help;
+mod library;
+mod lyrics;
+mod mouse;
+mod navigate;
+mod overlay;
+mod playlist;
+mod podcasts;
+mod rating;
+mod report;
+mod select;
 mod state;
+mod stations;
+mod stats;
+mod tags;
 
-use state::State;
+use navigate::navigate;
+use state::{Overlay, State, View};
 
 pub struct UserInterface {
     active: bool,
+    /// Adds tracks as the queue runs low, where enabled
+    autodj: AutoDj,
+    /// Batches of changes to the library on disk
+    changes: Receiver<Vec<Change>>,
+    follow_symlinks: bool,
+    keymap: Keymap,
+    /// Whether the mouse is captured, leaving text selection to the terminal where not
+    mouse: bool,
+    /// Directory the library is read from
+    library: PathBuf,
     player: Player,
+    /// Feeds subscribed to and their episodes, where the database opened
+    podcasts: Option<Podcasts>,
+    /// Whether ratings are also written to the tags of the files rated
+    rating_tags: bool,
+    /// Library scan in progress
+    scan: Option<library::Scan>,
+    smart_playlists: Vec<SmartPlaylist>,
+    /// Submits listens, where a service is configured
+    scrobbler: Option<Scrobbler>,
+    /// Records plays, where the database opened
+    stats: Option<Stats>,
+    stats_config: StatsConfig,
     state: state::State,
+    /// Radio stations of the configuration
+    stations: Vec<Rc<AudioTrack>>,
+    theme: Theme,
     tracks: LibraryCollection,
 }
 
 impl UserInterface {
-    pub fn new(track_list: LibraryCollection) -> Self {
-        UserInterface {
+    /// Creates the interface and starts reading the library, which fills in while running
+    pub fn new(config: &Config) -> Self {
+        let mut ui = UserInterface {
             active: true,
-            player: Player::new(),
-            state: State::new(),
-            tracks: track_list,
+            autodj: AutoDj::new(&config.autodj),
+            changes: watch::spawn(config.music_dir.clone(), config.follow_symlinks),
+            follow_symlinks: config.follow_symlinks,
+            keymap: config.keys.clone(),
+            mouse: config.mouse,
+            library: config.music_dir.clone(),
+            player: Player::new(config.playback.clone()),
+            podcasts: None,
+            rating_tags: config.rating_tags,
+            scan: None,
+            smart_playlists: config.smart.clone(),
+            scrobbler: Scrobbler::new(&config.scrobble),
+            stats: None,
+            stats_config: config.stats.clone(),
+            stations: config
+                .stations
+                .iter()
+                .map(|(name, url)| Rc::new(AudioTrack::station(name, url)))
+                .collect(),
+            state: State::new(config.art.unwrap_or_else(Protocol::detect)),
+            theme: config
+                .theme
+                .for_depth(config.colours.unwrap_or_else(Depth::detect)),
+            tracks: LibraryCollection::with_capacity(256),
+        };
+        match Stats::open() {
+            Ok(s) => ui.stats = Some(s),
+            Err(e) => ui.state.message = Some(format!("cannot open statistics: {e}")),
+        }
+        match Podcasts::open(config.podcast_dir.clone()) {
+            Ok(p) => ui.podcasts = Some(p),
+            Err(e) => ui.state.message = Some(format!("cannot open podcasts: {e}")),
         }
+        ui.start_scan(false);
+        ui
     }
 
     // I would like to make much more of impl async, but like to work on own sink impl first
-    pub fn run(&mut self, mut terminal: DefaultTerminal) {
+    /// Runs until quit, returning failures met on the way out, to be told once the terminal
+    /// is restored
+    pub fn run(&mut self, mut terminal: DefaultTerminal) -> Vec<String> {
+        if self.mouse {
+            execute!(io::stdout(), EnableMouseCapture).expect("enable mouse capture");
+        }
         while self.active {
             terminal.draw(|frame| self.draw(frame)).unwrap();
+            self.flush_art(&mut terminal);
 
-            self.player.try_next();
+            self.player.tick();
+            self.follow_playback();
+            self.poll_scan();
+            self.apply_changes();
 
             // fans speed up without a longer than 0ms wait, and not feeling any latency yet
             // something to keep an eye out for, and perhaps profile
             if event::poll(Duration::from_millis(100)).is_ok_and(|r| r) {
-                if let Event::Key(k) = event::read().expect("event read") {
-                    self.handle_key(k);
+                match event::read().expect("event read") {
+                    Event::Key(k) => self.handle_key(k),
+                    Event::Mouse(m) => self.handle_mouse(m),
+                    // the terminal is cleared on resize, taking any image with it, and rows
+                    // clicked before no longer lie where they were
+                    Event::Resize(..) => {
+                        self.state.art.stale = true;
+                        self.state.last_click = None;
+                    }
+                    _ => (),
+                }
+            }
+        }
+        if self.mouse {
+            execute!(io::stdout(), DisableMouseCapture).expect("disable mouse capture");
+        }
+        let mut failures = Vec::new();
+        // a track cut short by quitting counts as skipped
+        if let Some(stats) = &mut self.stats
+            && let Err(e) = stats.finish(false)
+        {
+            failures.push(format!("cannot record play: {e}"));
+        }
+        if let Some(podcasts) = &mut self.podcasts
+            && let Err(e) = podcasts.save()
+        {
+            failures.push(format!("cannot save episode position: {e}"));
+        }
+        failures
+    }
+
+    /// Passes on what the player did since the last frame
+    fn follow_playback(&mut self) {
+        let events = self.player.events();
+        for e in &events {
+            match e {
+                PlayerEvent::Started { track, .. } => self.autodj.played(track),
+                PlayerEvent::Failed { track, error } => {
+                    self.state.message = Some(format!("cannot play {}: {error}", track.title()))
+                }
+                PlayerEvent::Notice(notice) => self.state.message = Some(notice.clone()),
+                PlayerEvent::Ended { .. } => (),
+            }
+        }
+        self.fill_queue();
+        self.follow_podcasts(&events);
+        if let Some(stats) = &mut self.stats {
+            for e in &events {
+                if let Err(e) = stats.handle(e) {
+                    self.state.message = Some(format!("cannot record play: {e}"));
                 }
             }
+            stats.tick(self.player.is_paused());
+        }
+        if let Some(scrobbler) = &mut self.scrobbler {
+            events.iter().for_each(|e| scrobbler.handle(e));
+            scrobbler.tick(self.player.is_paused());
+            if let Some(notice) = scrobbler.notice() {
+                self.state.message = Some(notice);
+            }
         }
     }
 
+    /// Tops up the queue while a track plays, leaving playback stopped where it has stopped
+    fn fill_queue(&mut self) {
+        if !self.autodj.enabled || self.player.playing().is_none() {
+            return;
+        }
+        let queued = self.player.upcoming().count();
+        let last = self
+            .player
+            .upcoming()
+            .last()
+            .or(self.player.playing())
+            .cloned();
+        let stats = self.stats.as_ref();
+        let tracks = self.autodj.fill(&self.tracks, queued, last, |t| {
+            stats.map_or_else(TrackData::default, |s| s.track(t))
+        });
+        tracks.into_iter().for_each(|t| self.player.append_queue(t));
+    }
+
+    fn toggle_autodj(&mut self) {
+        self.autodj.enabled = !self.autodj.enabled;
+        self.state.message = Some(match self.autodj.enabled {
+            true => format!("auto-DJ on, choosing by {}", self.autodj.strategy.title()),
+            false => String::from("auto-DJ off"),
+        });
+    }
+
+    fn next_dj_strategy(&mut self) {
+        self.autodj.strategy = self.autodj.strategy.next();
+        self.state.message = Some(format!(
+            "auto-DJ chooses by {}",
+            self.autodj.strategy.title()
+        ));
+    }
+
     fn draw(&mut self, frame: &mut Frame) {
-        let [search_area, body_area, status_area] = Layout::vertical([
-            Constraint::Length(3),
-            Constraint::Percentage(90),
+        let [tabs_area, main_area, status_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Fill(1),
             Constraint::Length(6),
         ])
         .areas(frame.area());
 
-        self.render_search(search_area, frame);
-        self.render_all_tracks(body_area, frame);
+        self.render_tabs(tabs_area, frame);
+        self.state.areas.tabs = tabs_area;
+        self.state.areas.body = main_area;
+        match self.state.view {
+            View::Tracks => {
+                let [search_area, body_area] =
+                    Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(main_area);
+                self.render_search(search_area, frame);
+                self.render_all_tracks(body_area, frame);
+                self.state.areas.body = body_area;
+            }
+            View::Equalizer => self.render_equalizer(main_area, frame),
+            View::Lyrics => self.render_lyrics(main_area, frame),
+            View::Report => self.render_report(main_area, frame),
+            View::Stats => self.render_stats(main_area, frame),
+            View::Podcasts => self.render_podcasts(main_area, frame),
+        }
         self.render_status(status_area, frame);
+        self.render_overlay(frame);
     }
 
     fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
         match self.state.mode {
             state::Mode::Playback => {
-                if key.kind == KeyEventKind::Press {
-                    // toggle between selected panels (widgets)?
-                    match key.code {
-                        KeyCode::Char('q') => self.active = false,
-                        KeyCode::Down => self.state.all_tracks.select_next(),
-                        KeyCode::Up => self.state.all_tracks.select_previous(),
-                        KeyCode::PageDown => self.state.all_tracks.select_last(),
-                        KeyCode::PageUp => self.state.all_tracks.select_first(),
-                        KeyCode::Char('u') => {
-                            // move up 10 lines.  prefer this to be a variable number
-                            // determined by visible lines on the screen
-                            if let Some(i) = self.state.all_tracks.selected() {
-                                let n = usize::saturating_sub(i, 10);
-                                self.state.all_tracks.select(Some(n));
-                            }
-                        }
-                        KeyCode::Char('d') => {
-                            // inverse of `u`
-                            if let Some(i) = self.state.all_tracks.selected() {
-                                let n = usize::saturating_add(i, 10);
-                                self.state.all_tracks.select(Some(n));
-                            }
-                        }
-                        KeyCode::Enter => {
-                            match self.state.all_tracks.selected() {
-                                Some(i) => {
-                                    // index is no longer accurate as the resulting map has been filtered
-                                    // no method to return selected row, only index, so need to filter again here
-                                    let t: Vec<Rc<AudioTrack>> = self
-                                        .tracks
-                                        .iter()
-                                        .filter_map(|v| {
-                                            let q = self.state.search.query.to_lowercase();
-                                            match v.deref() {
-                                                AudioTrack::Extended(x)
-                                                    if x.title.to_lowercase().contains(&q)
-                                                        || x.album.to_lowercase().contains(&q)
-                                                        || x.artists
-                                                            .to_lowercase()
-                                                            .contains(&q) =>
-                                                {
-                                                    Some(v.clone())
-                                                }
-                                                AudioTrack::Limited(x)
-                                                    if x.title.to_lowercase().contains(&q) =>
-                                                {
-                                                    Some(v.clone())
-                                                }
-                                                _ => None,
-                                            }
-                                        })
-                                        .collect();
-                                    self.player.append_queue(t[i].clone());
-                                }
-                                None => unreachable!(), // index out of bounds
-                            };
-                            self.state.all_tracks.select_next();
-                        }
-                        KeyCode::Char(' ') => self.player.toggle_pause(),
-                        KeyCode::Char('c') => self.player.clear_queue(),
-                        KeyCode::Char('>') => self.player.skip_one(),
-                        KeyCode::Char('/') => self.state.mode = state::Mode::Search,
-                        _ => (),
-                    }
+                self.state.message = None;
+                if self.state.overlay.is_some() {
+                    self.handle_overlay_key(key);
+                    return;
                 }
+                self.handle_bound_key(Key::from(key));
             }
-            state::Mode::Search => {
-                if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Esc => {
-                            self.state.search.query = String::new();
-                            self.state.search.cursor_index = 0;
-                            self.state.mode = state::Mode::Playback;
-                        }
-                        KeyCode::Enter => self.state.mode = state::Mode::Playback,
-                        KeyCode::Char(c) => self.state.search.new_char(c),
-                        KeyCode::Left => self.state.search.move_cursor_left(),
-                        KeyCode::Right => self.state.search.move_cursor_right(),
-                        KeyCode::Backspace | KeyCode::Delete => self.state.search.delete_char(),
-                        _ => (),
-                    }
+            state::Mode::Search => match key.code {
+                KeyCode::Esc => {
+                    self.state.search.query = String::new();
+                    self.state.search.cursor_index = 0;
+                    self.state.mode = state::Mode::Playback;
+                }
+                KeyCode::Enter => self.state.mode = state::Mode::Playback,
+                KeyCode::Char(c) => self.state.search.new_char(c),
+                KeyCode::Left => self.state.search.move_cursor_left(),
+                KeyCode::Right => self.state.search.move_cursor_right(),
+                KeyCode::Backspace | KeyCode::Delete => self.state.search.delete_char(),
+                _ => (),
+            },
+            state::Mode::Jump => {
+                self.state.message = None;
+                self.state.mode = state::Mode::Playback;
+                if let KeyCode::Char(c) = key.code {
+                    self.jump_to_letter(c);
+                }
+            }
+            state::Mode::Rate => {
+                self.state.message = None;
+                self.state.mode = state::Mode::Playback;
+                if let KeyCode::Char(c) = key.code {
+                    self.rate(c);
+                }
+            }
+            state::Mode::Subscribe => self.handle_subscribe_key(key),
+        }
+    }
+
+    /// Adds a key to those pressed so far, performing the action they are bound to once they
+    /// make up a whole sequence
+    fn handle_bound_key(&mut self, key: Key) {
+        let context = self.state.view.context();
+        let mut keys = mem::take(&mut self.state.pending);
+        keys.push(key);
+        match self.keymap.lookup(context, &keys) {
+            Lookup::Exact(action) => self.perform(action),
+            Lookup::Partial(_) => self.state.pending = keys,
+            Lookup::None if keys.len() > 1 => {
+                // the sequence went no further: act on the keys before, then start over
+                keys.pop();
+                if let Lookup::Partial(Some(action)) = self.keymap.lookup(context, &keys) {
+                    self.perform(action);
+                }
+                self.handle_bound_key(key);
+            }
+            Lookup::None => (),
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Quit => self.active = false,
+            Action::Help => self.state.overlay = Some(Overlay::Help(0)),
+            Action::NextView => self.state.view = self.state.view.next(),
+            Action::Devices => self.open_devices(),
+            Action::Stations => self.open_stations(),
+            Action::TogglePause => self.player.toggle_pause(),
+            Action::Skip => self.player.skip_one(),
+            Action::ClearQueue => self.player.clear_queue(),
+            Action::Slower => self.player.set_speed(self.player.speed() - 0.05),
+            Action::Faster => self.player.set_speed(self.player.speed() + 0.05),
+            Action::ResetSpeed => self.player.set_speed(1.0),
+            Action::TogglePitch => self.player.toggle_preserve_pitch(),
+            Action::LoopStart => self.player.set_loop_start(),
+            Action::LoopEnd => self.player.set_loop_end(),
+            Action::ClearLoop => self.player.clear_loop(),
+            Action::ToggleAutoDj => self.toggle_autodj(),
+            Action::NextDjStrategy => self.next_dj_strategy(),
+            _ => match self.state.view {
+                View::Tracks => self.handle_tracks_action(action),
+                View::Equalizer => self.handle_equalizer_action(action),
+                View::Lyrics => self.handle_lyrics_action(action),
+                View::Report => self.handle_report_action(action),
+                View::Stats => self.handle_stats_action(action),
+                View::Podcasts => self.handle_podcasts_action(action),
+            },
+        }
+    }
+
+    fn handle_tracks_action(&mut self, action: Action) {
+        match action {
+            Action::Down
+            | Action::Up
+            | Action::PageDown
+            | Action::PageUp
+            | Action::HalfPageDown
+            | Action::HalfPageUp
+            | Action::Bottom
+            | Action::Top
+            | Action::ScreenTop
+            | Action::ScreenMiddle
+            | Action::ScreenBottom
+            | Action::Center => {
+                let len = self.visible_tracks().len();
+                let page = self.page_rows();
+                navigate(&mut self.state.all_tracks, action, len, page);
+            }
+            Action::Enqueue if self.choosing() => self.enqueue_chosen(false),
+            Action::Enqueue => {
+                // index is no longer accurate as the resulting map has been filtered
+                // no method to return selected row, only index, so need to filter again here.
+                // the list may still be empty while the library is scanned
+                if let Some(t) = self
+                    .state
+                    .all_tracks
+                    .selected()
+                    .and_then(|i| self.visible_tracks().get(i).cloned())
+                {
+                    self.player.append_queue(t);
                 }
+                self.state.all_tracks.select_next();
             }
+            Action::Search => self.state.mode = state::Mode::Search,
+            Action::Rescan => self.rescan(),
+            Action::EditTags => self.open_tag_editor(false),
+            Action::EditAlbumTags => self.open_tag_editor(true),
+            Action::PlayNext => self.enqueue_chosen(true),
+            Action::Visual => self.toggle_visual(),
+            Action::Mark => self.toggle_mark(),
+            Action::MarkAll => self.mark_all(),
+            Action::AddToPlaylist => self.open_playlists(),
+            Action::QueuePlaylist => self.open_playlist_menu(),
+            Action::ClearMarks => self.clear_choice(),
+            Action::JumpToPlaying => self.jump_to_playing(),
+            Action::JumpToLetter => self.start_letter_jump(),
+            Action::Rate => self.start_rating(),
+            Action::ToggleFavourite => self.toggle_favourite(),
+            Action::NextSort => self.next_sort(),
+            Action::ReverseSort => self.reverse_sort(),
+            _ => (),
         }
     }
 
+    /// Tracks matching the search query, in the order they are listed
+    fn visible_tracks(&self) -> Vec<Rc<AudioTrack>> {
+        self.visible_rows().into_iter().map(|r| r.track).collect()
+    }
+
+    /// Rows of the track list: tracks matching the search, sorted by the chosen column
+    fn visible_rows(&self) -> Vec<Listed> {
+        let query = Query::search(&self.state.search.query);
+        let mut rows = query.select(&self.tracks, |t| self.track_data(t));
+        self.state.sort.apply(&mut rows);
+        rows
+    }
+
+    fn render_tabs(&mut self, area: Rect, frame: &mut Frame) {
+        let selected = View::ALL.iter().position(|v| *v == self.state.view);
+        let tabs = Tabs::new(View::ALL.map(View::title))
+            .select(selected)
+            .highlight_style(self.theme.tab);
+        frame.render_widget(tabs, area);
+    }
+
     fn render_search(&mut self, area: Rect, frame: &mut Frame) {
         let x = self.state.search.query.as_str();
-        let w = Paragraph::new(x).block(
-            Block::bordered()
-                .title("search")
-                .title_style(Modifier::BOLD),
-        );
+        let mut block = self.theme.block().title("search");
 
         match self.state.mode {
-            state::Mode::Playback => (),
-            state::Mode::Search => frame.set_cursor_position(Position::new(
-                area.x + self.state.search.cursor_index as u16 + 1,
-                area.y + 1,
-            )),
+            state::Mode::Playback
+            | state::Mode::Jump
+            | state::Mode::Rate
+            | state::Mode::Subscribe => (),
+            state::Mode::Search => {
+                block = block.border_style(self.theme.search);
+                frame.set_cursor_position(Position::new(
+                    area.x + self.state.search.cursor_index as u16 + 1,
+                    area.y + 1,
+                ))
+            }
         }
 
-        frame.render_widget(w, area);
+        frame.render_widget(Paragraph::new(x).block(block), area);
     }
 
     fn render_all_tracks(&mut self, area: Rect, frame: &mut Frame) {
+        let sort = self.state.sort;
+        // the sorted column is marked with the direction of the sort
+        let heading = |name: &'static str, key: SortKey| match (sort.key == key, sort.descending) {
+            (false, _) => Cell::new(name),
+            (true, false) => Cell::new(format!("{name} ▲")),
+            (true, true) => Cell::new(format!("{name} ▼")),
+        };
         let header = Row::new([
-            Cell::new("index"),
-            Cell::new("title"),
-            Cell::new("artist(s)"),
-            Cell::new("album"),
-            Cell::new("release date"),
+            Cell::new(""),
+            Cell::new(""),
+            heading("index", SortKey::Library),
+            Cell::new(""),
+            heading("title", SortKey::Title),
+            heading("artist(s)", SortKey::Artist),
+            heading("album", SortKey::Album),
+            heading("release date", SortKey::Date),
+            heading("rating", SortKey::Rating),
+            heading("plays", SortKey::Plays),
         ])
-        .bold();
+        .style(self.theme.header);
+
+        let playing = self.player.playing().map(|t| t.path());
+        // a track queued more than once shows where it next plays
+        let mut queued: HashMap<&str, usize> = HashMap::new();
+        for (i, t) in self.player.upcoming().enumerate() {
+            queued.entry(t.path()).or_insert(i + 1);
+        }
 
         let rows: Vec<Row> = self
-            .tracks
-            .iter()
+            .visible_rows()
+            .into_iter()
             .enumerate()
-            .filter_map(|(k, v)| {
-                let q = self.state.search.query.to_lowercase();
-                match v.deref() {
-                    AudioTrack::Extended(x)
-                        if x.title.to_lowercase().contains(&q)
-                            || x.album.to_lowercase().contains(&q)
-                            || x.artists.to_lowercase().contains(&q) =>
-                    {
-                        Some(Row::new([
-                            Cell::new(k.to_string()),
-                            Cell::new(x.title.clone()),
-                            Cell::new(x.artists.clone()),
-                            Cell::new(x.album.clone()),
-                            Cell::new(x.date.clone()),
-                        ]))
-                    }
-                    AudioTrack::Limited(x) if x.title.to_lowercase().contains(&q) => {
-                        Some(Row::new([
-                            Cell::new(k.to_string()),
-                            Cell::new(x.title.clone()),
-                        ]))
+            .map(
+                |(
+                    row,
+                    Listed {
+                        index,
+                        track: v,
+                        data,
+                    },
+                )| {
+                    let mark = match self.is_chosen(row, &v) {
+                        true => Cell::new("●").style(self.theme.marked),
+                        false => Cell::new(""),
+                    };
+                    let is_playing = playing == Some(v.path());
+                    let position = match (is_playing, queued.get(v.path())) {
+                        (true, _) => Cell::new("▶"),
+                        (false, Some(n)) => Cell::new(n.to_string()),
+                        (false, None) => Cell::new(""),
+                    };
+                    let favourite = match data.favourite {
+                        true => Cell::new("♥").style(self.theme.rating),
+                        false => Cell::new(""),
+                    };
+                    let stars = data.rating.map_or(0, usize::from);
+                    let rating = Cell::new("★".repeat(stars)).style(self.theme.rating);
+                    let plays = match data.plays {
+                        0 => Cell::new(""),
+                        n => Cell::new(Line::from(n.to_string()).right_aligned()),
+                    };
+                    let (artists, album, date) = match v.deref() {
+                        AudioTrack::Extended(x) => {
+                            (x.artists.clone(), x.album.clone(), x.date.clone())
+                        }
+                        AudioTrack::Limited(_) => Default::default(),
+                    };
+                    let row = Row::new([
+                        mark,
+                        position,
+                        Cell::new(index.to_string()),
+                        favourite,
+                        Cell::new(v.title().to_owned()),
+                        Cell::new(artists),
+                        Cell::new(album),
+                        Cell::new(date),
+                        rating,
+                        plays,
+                    ]);
+                    match is_playing {
+                        true => row.style(self.theme.playing),
+                        false => row,
                     }
-                    _ => None,
-                }
-            })
+                },
+            )
             .collect();
 
+        let mut block = self.theme.block();
+        if let Some(p) = self.scan_progress() {
+            block = block.title(Line::from(p).right_aligned());
+        }
+
         let tbl = Table::new(
             rows,
             [
+                Constraint::Length(1),
+                Constraint::Length(3),
                 Constraint::Max(0),
+                Constraint::Length(1),
                 Constraint::Fill(4),
                 Constraint::Fill(2),
                 Constraint::Fill(2),
                 Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(7),
             ],
         )
-        .block(Block::bordered().border_set(ratatui::symbols::border::ROUNDED))
+        .block(block)
         .header(header)
         .column_spacing(2)
-        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        .row_highlight_style(self.theme.highlight);
 
         frame.render_stateful_widget(tbl, area, &mut self.state.all_tracks);
     }
 
     /// Render the status panel
     fn render_status(&mut self, area: Rect, frame: &mut Frame) {
-        // TODO: duration, play/pause
-        let last_played: String = match &self.player.last_played {
+        // TODO: play/pause
+        let last_played: String = match self.player.playing() {
             Some(s) => match s.deref() {
                 AudioTrack::Extended(i) => {
                     format!("{0}\n{1}\n{2} - {3}", i.title, i.artists, i.album, i.date)
                 }
-                AudioTrack::Limited(i) => i.title.clone(),
+                AudioTrack::Limited(i) => match self.player.stream_title() {
+                    Some(song) => format!("{song}\n{}", i.title),
+                    None => i.title.clone(),
+                },
+            },
+            None => match self.player.connecting() {
+                Some(t) => format!("connecting to {}", t.title()),
+                None => String::from("none"),
             },
-            None => String::from("none"),
         };
-        let title = Block::bordered()
-            .title("currently playing")
-            .title_style(Modifier::BOLD);
-        let widget = Paragraph::new(last_played).block(title);
-        frame.render_widget(widget, area);
+        let mut title = self.theme.block().title("currently playing");
+        if let Some(m) = &self.state.message {
+            title = title.title_bottom(m.as_str());
+        }
+        if let Some(d) = self.player.device() {
+            title = title.title(Line::from(d).right_aligned());
+        }
+        title = title.title_bottom(Line::from(self.playback_summary()).right_aligned());
+
+        let inner = title.inner(area);
+        frame.render_widget(title, area);
+        // cells are about twice as high as wide, so this leaves a square for the art
+        let [art_area, _, info_area] = Layout::horizontal([
+            Constraint::Length(inner.height * 2),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+        self.render_art(art_area, frame);
+        let [text_area, progress_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(info_area);
+        frame.render_widget(
+            Paragraph::new(last_played).style(self.theme.playing),
+            text_area,
+        );
+        frame.render_widget(self.progress_bar(progress_area.width), progress_area);
+        self.state.areas.progress = progress_area;
     }
+
+    /// Progress through the current track, with A-B loop points marked where set
+    fn progress_bar(&self, width: u16) -> Line<'static> {
+        let (Some(position), Some(duration)) = (self.player.position(), self.player.duration())
+        else {
+            return Line::default();
+        };
+        let width = width as usize;
+        let cell = |d: Duration| {
+            let f = d.as_secs_f64() / duration.as_secs_f64().max(f64::EPSILON);
+            ((f * width as f64) as usize).min(width.saturating_sub(1))
+        };
+        let played = cell(position);
+        let (a, b) = self.player.loop_points();
+        let (a, b) = (a.map(cell), b.map(cell));
+
+        let spans: Vec<Span> = (0..width)
+            .map(|i| {
+                if Some(i) == a {
+                    Span::styled("A", self.theme.progress_loop)
+                } else if Some(i) == b {
+                    Span::styled("B", self.theme.progress_loop)
+                } else {
+                    let (symbol, mut style) = match i <= played {
+                        true => ("━", self.theme.progress),
+                        false => ("─", self.theme.progress_rest),
+                    };
+                    if a.is_some_and(|a| i > a) && b.is_none_or(|b| i < b) {
+                        style = style.patch(self.theme.progress_loop);
+                    }
+                    Span::styled(symbol, style)
+                }
+            })
+            .collect();
+        Line::from(spans)
+    }
+
+    /// Elapsed and total time of the current track, followed by the speed where not 1x
+    fn playback_summary(&self) -> String {
+        let mut summary = match (self.player.position(), self.player.duration()) {
+            (Some(p), Some(d)) => format!("{} / {}", format_time(p), format_time(d)),
+            (Some(p), None) => format_time(p),
+            _ => String::new(),
+        };
+        let speed = self.player.speed();
+        if speed != 1.0 {
+            let mode = match self.player.preserve_pitch() {
+                true => "pitch kept",
+                false => "resampled",
+            };
+            summary.push_str(&format!("  {speed:.2}x ({mode})"));
+        }
+        if self.autodj.enabled {
+            summary.push_str(&format!("  dj: {}", self.autodj.strategy.title()));
+        }
+        summary
+    }
+}
+
+/// Formats a duration as minutes and seconds
+fn format_time(d: Duration) -> String {
+    let s = d.as_secs();
+    format!("{}:{:02}", s / 60, s % 60)
 }
@@ -0,0 +1,121 @@
+//! Mouse input: clicking rows, tabs and the progress bar, and scrolling lists
+
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    crossterm::event::{MouseButton, MouseEvent, MouseEventKind},
+    layout::{Position, Rect},
+    widgets::TableState,
+};
+
+use super::UserInterface;
+use super::state::{Mode, View};
+use crate::keymap::Action;
+
+/// Longest gap between the clicks of a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+/// Rows moved by a step of the scroll wheel
+const WHEEL_ROWS: usize = 3;
+
+impl UserInterface {
+    pub(super) fn handle_mouse(&mut self, event: MouseEvent) {
+        // popups and the search box read keys alone
+        if self.state.overlay.is_some() || matches!(self.state.mode, Mode::Search) {
+            return;
+        }
+        let at = Position::new(event.column, event.row);
+        let areas = &self.state.areas;
+        match event.kind {
+            MouseEventKind::ScrollDown if areas.body.contains(at) => {
+                (0..WHEEL_ROWS).for_each(|_| self.perform(Action::Down))
+            }
+            MouseEventKind::ScrollUp if areas.body.contains(at) => {
+                (0..WHEEL_ROWS).for_each(|_| self.perform(Action::Up))
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                if areas.tabs.contains(at) {
+                    if let Some(view) = tab_at(areas.tabs, at.x) {
+                        self.state.view = view;
+                    }
+                } else if areas.progress.contains(at) {
+                    self.seek_to(at.x - areas.progress.x);
+                } else if areas.body.contains(at) {
+                    self.click_row(at.y);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Selects the row of the current view at a line of the screen, playing a track clicked
+    /// twice in quick succession
+    fn click_row(&mut self, y: u16) {
+        let body = self.state.areas.body;
+        let table = match self.state.view {
+            View::Tracks => &mut self.state.all_tracks,
+            View::Equalizer => &mut self.state.equalizer,
+            View::Report => &mut self.state.report.table,
+            View::Podcasts => &mut self.state.podcasts.episodes,
+            View::Lyrics | View::Stats => return,
+        };
+        // rows begin below the border and the header
+        let Some(row) = row_at(body, table, y) else {
+            return;
+        };
+        table.select(Some(row));
+
+        let now = Instant::now();
+        let double = self
+            .state
+            .last_click
+            .is_some_and(|(at, r)| r == row && now.duration_since(at) <= DOUBLE_CLICK);
+        self.state.last_click = match double {
+            true => None,
+            false => Some((now, row)),
+        };
+        if double
+            && self.state.view == View::Tracks
+            && let Some(track) = self.visible_tracks().get(row).cloned()
+        {
+            self.player.play_now(track);
+        }
+        if double && self.state.view == View::Podcasts {
+            self.play_episode();
+        }
+    }
+
+    /// Seeks to the point of the current track under a column of the progress bar
+    fn seek_to(&mut self, column: u16) {
+        let Some(duration) = self.player.duration() else {
+            return;
+        };
+        let width = self.state.areas.progress.width.max(1);
+        let to = duration.mul_f64(column as f64 / width as f64);
+        if !self.player.seek(to) {
+            self.state.message = Some(String::from("cannot seek in this track"));
+        }
+    }
+}
+
+/// Row of a bordered table with a header drawn in `area` at a line of the screen
+fn row_at(area: Rect, table: &TableState, y: u16) -> Option<usize> {
+    let first = area.y + 2;
+    if y < first || y + 1 >= area.bottom() {
+        return None;
+    }
+    Some(table.offset() + (y - first) as usize)
+}
+
+/// View whose tab lies under a column, the tabs being drawn padded by a space either side and
+/// divided by a single character
+fn tab_at(area: Rect, x: u16) -> Option<View> {
+    let mut start = area.x;
+    for view in View::ALL {
+        let end = start + view.title().chars().count() as u16 + 2;
+        if (start..end).contains(&x) {
+            return Some(view);
+        }
+        start = end + 1;
+    }
+    None
+}
@@ -0,0 +1,71 @@
+//! Moving the selection of a table by rows, pages and positions on the screen
+
+use ratatui::widgets::TableState;
+
+use super::UserInterface;
+use super::state::Mode;
+use crate::keymap::Action;
+
+impl UserInterface {
+    /// Rows of a table shown in the body, below its border and header
+    pub(super) fn page_rows(&self) -> usize {
+        self.state.areas.body.height.saturating_sub(3) as usize
+    }
+
+    /// Waits for a letter to select the next title starting with it
+    pub(super) fn start_letter_jump(&mut self) {
+        self.state.mode = Mode::Jump;
+        self.state.message = Some(String::from("jump to title starting with…"));
+    }
+
+    /// Selects the first title after the selected row starting with a letter, wrapping around
+    pub(super) fn jump_to_letter(&mut self, letter: char) {
+        let letter = letter.to_lowercase().collect::<String>();
+        let visible = self.visible_tracks();
+        let starts = |i: &usize| visible[*i].title().to_lowercase().starts_with(&letter);
+        let from = self.state.all_tracks.selected().map_or(0, |i| i + 1);
+        let found = (from..visible.len()).chain(0..from).find(starts);
+        match found {
+            Some(i) => self.state.all_tracks.select(Some(i)),
+            None => self.state.message = Some(format!("no title starting with `{letter}`")),
+        }
+    }
+}
+
+/// Moves the selection of a table of `len` rows showing `page` at once, scrolling along with
+/// the selection on page moves so the rows keep their place on the screen
+pub(super) fn navigate(table: &mut TableState, action: Action, len: usize, page: usize) {
+    let Some(last) = len.checked_sub(1) else {
+        table.select(None);
+        return;
+    };
+    let page = page.clamp(1, len);
+    let half = (page / 2).max(1);
+    // first row shown when scrolled to the end
+    let end = len - page;
+    let from = table.selected().map(|i| i.min(last));
+    let offset = table.offset().min(end);
+    let down = |n: usize| from.map_or(0, |i| (i + n).min(last));
+    let up = |n: usize| from.map_or(0, |i| i.saturating_sub(n));
+
+    let (row, offset) = match action {
+        Action::Down => (down(1), offset),
+        Action::Up => (up(1), offset),
+        Action::PageDown => (down(page), (offset + page).min(end)),
+        Action::PageUp => (up(page), offset.saturating_sub(page)),
+        Action::HalfPageDown => (down(half), (offset + half).min(end)),
+        Action::HalfPageUp => (up(half), offset.saturating_sub(half)),
+        Action::Top => (0, 0),
+        Action::Bottom => (last, end),
+        Action::ScreenTop => (offset, offset),
+        Action::ScreenMiddle => (offset + (page - 1) / 2, offset),
+        Action::ScreenBottom => (offset + page - 1, offset),
+        Action::Center => {
+            let row = from.unwrap_or(0);
+            (row, row.saturating_sub(page / 2).min(end))
+        }
+        _ => return,
+    };
+    table.select(Some(row));
+    *table.offset_mut() = offset;
+}
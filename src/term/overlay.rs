@@ -0,0 +1,97 @@
+//! Popups drawn over the current view
+
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Flex, Layout, Rect},
+    widgets::{Clear, List, ListState},
+};
+
+use super::UserInterface;
+use super::state::Overlay;
+use crate::playback;
+
+impl UserInterface {
+    pub(super) fn open_devices(&mut self) {
+        let mut names = vec![String::from("system default")];
+        names.extend(playback::devices());
+        let current = self
+            .player
+            .device()
+            .and_then(|d| names.iter().skip(1).position(|n| n == d))
+            .map_or(0, |i| i + 1);
+        self.state.overlay = Some(Overlay::Devices(
+            names,
+            ListState::default().with_selected(Some(current)),
+        ));
+    }
+
+    pub(super) fn handle_overlay_key(&mut self, key: KeyEvent) {
+        let Some(overlay) = &mut self.state.overlay else {
+            return;
+        };
+        match overlay {
+            Overlay::Devices(names, list) => match key.code {
+                KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('o') => self.state.overlay = None,
+                KeyCode::Down => list.select_next(),
+                KeyCode::Up => list.select_previous(),
+                KeyCode::Enter => {
+                    let device = match list.selected() {
+                        Some(0) | None => None,
+                        Some(i) => names.get(i).cloned(),
+                    };
+                    self.state.overlay = None;
+                    self.player.set_device(device);
+                    self.state.message = Some(match self.player.device() {
+                        Some(d) => format!("playing to {d}"),
+                        None => String::from("no output device available"),
+                    });
+                }
+                _ => (),
+            },
+            Overlay::Tags(_) => self.handle_tags_key(key),
+            Overlay::Playlists(_) => self.handle_playlists_key(key),
+            Overlay::Queue(_) => self.handle_playlist_menu_key(key),
+            Overlay::Stations(_) => self.handle_stations_key(key),
+            Overlay::Help(_) => self.handle_help_key(key),
+        }
+    }
+
+    pub(super) fn render_overlay(&mut self, frame: &mut Frame) {
+        if let Some(Overlay::Help(scroll)) = self.state.overlay {
+            self.render_help(frame, scroll);
+            return;
+        }
+        let Some(overlay) = &mut self.state.overlay else {
+            return;
+        };
+        match overlay {
+            Overlay::Devices(names, list) => {
+                let area = centered(frame.area(), 60, names.len() as u16 + 2);
+                let widget = List::new(names.iter().map(String::as_str))
+                    .block(self.theme.block().title("output device"))
+                    .highlight_style(self.theme.highlight);
+                frame.render_widget(Clear, area);
+                frame.render_stateful_widget(widget, area, list);
+            }
+            Overlay::Tags(form) => Self::render_tags(frame, &self.theme, form),
+            Overlay::Playlists(picker) => Self::render_playlists(frame, &self.theme, picker),
+            Overlay::Queue(menu) => Self::render_playlist_menu(frame, &self.theme, menu),
+            Overlay::Stations(list) => {
+                Self::render_stations(frame, &self.theme, &self.stations, list)
+            }
+            Overlay::Help(_) => (),
+        }
+    }
+}
+
+/// Rect of at most the given size centred within `area`
+pub(super) fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
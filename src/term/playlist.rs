@@ -0,0 +1,223 @@
+//! Picker adding the chosen tracks to a playlist, and the menu queueing a whole playlist
+
+use std::collections::HashMap;
+
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::Position,
+    text::{Line, Span},
+    widgets::{Clear, List, ListState},
+};
+
+use super::UserInterface;
+use super::overlay::centered;
+use super::state::{MenuEntry, Overlay, PlaylistMenu, PlaylistPicker};
+use crate::playlist;
+use crate::theme::Theme;
+
+/// Entry standing for a playlist not yet created
+const NEW: &str = "new playlist…";
+
+impl UserInterface {
+    pub(super) fn open_playlists(&mut self) {
+        // saved as `file#NN`, such a track names no file any other player could open
+        if self.chosen_tracks().iter().any(|t| t.cue().is_some()) {
+            self.state.message = Some(String::from(
+                "tracks of a CUE sheet cannot be saved to a playlist",
+            ));
+            return;
+        }
+        let tracks: Vec<String> = self
+            .chosen_tracks()
+            .iter()
+            .map(|t| t.path().to_owned())
+            .collect();
+        if tracks.is_empty() {
+            return;
+        }
+        self.clear_choice();
+        self.state.overlay = Some(Overlay::Playlists(PlaylistPicker {
+            tracks,
+            names: playlist::names(),
+            list: ListState::default().with_selected(Some(0)),
+            naming: None,
+        }));
+    }
+
+    pub(super) fn handle_playlists_key(&mut self, key: KeyEvent) {
+        let Some(Overlay::Playlists(picker)) = &mut self.state.overlay else {
+            return;
+        };
+
+        if let Some(name) = &mut picker.naming {
+            match key.code {
+                KeyCode::Esc => picker.naming = None,
+                KeyCode::Char(c) => name.push(c),
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Enter => {
+                    let name = name.clone();
+                    self.add_to_playlist(&name);
+                }
+                _ => (),
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.state.overlay = None,
+            KeyCode::Down => picker.list.select_next(),
+            KeyCode::Up => picker.list.select_previous(),
+            KeyCode::Enter => match picker.list.selected() {
+                Some(0) | None => picker.naming = Some(String::new()),
+                Some(i) => {
+                    let name = picker.names[i - 1].clone();
+                    self.add_to_playlist(&name);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn add_to_playlist(&mut self, name: &str) {
+        let Some(Overlay::Playlists(picker)) = self.state.overlay.take() else {
+            return;
+        };
+        self.state.message = Some(
+            match playlist::append(name, picker.tracks.iter().map(String::as_str)) {
+                Ok(()) => format!("added {} tracks to {name}", picker.tracks.len()),
+                Err(e) => format!("cannot add to {name}: {e}"),
+            },
+        );
+    }
+
+    pub(super) fn render_playlists(frame: &mut Frame, theme: &Theme, picker: &mut PlaylistPicker) {
+        let area = centered(frame.area(), 48, picker.names.len() as u16 + 3);
+        let mut items = vec![Line::from(match &picker.naming {
+            Some(name) => format!("name: {name}"),
+            None => String::from(NEW),
+        })];
+        items.extend(picker.names.iter().map(|n| Line::from(n.as_str())));
+
+        let title = format!("add {} tracks to playlist", picker.tracks.len());
+        let widget = List::new(items)
+            .block(theme.block().title(title))
+            .highlight_style(theme.highlight);
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(widget, area, &mut picker.list);
+        if let Some(name) = &picker.naming {
+            let x = area.x + 1 + "name: ".len() as u16 + name.chars().count() as u16;
+            frame.set_cursor_position(Position::new(x.min(area.right() - 2), area.y + 1));
+        }
+    }
+
+    pub(super) fn open_playlist_menu(&mut self) {
+        let entries = self.playlist_entries();
+        if entries.is_empty() {
+            self.state.message = Some(String::from("no playlists saved or defined"));
+            return;
+        }
+        self.state.overlay = Some(Overlay::Queue(PlaylistMenu {
+            entries,
+            list: ListState::default().with_selected(Some(0)),
+        }));
+    }
+
+    /// Evaluates the playlists of the menu again, for when the library has changed
+    pub(super) fn refresh_playlist_menu(&mut self) {
+        if !matches!(self.state.overlay, Some(Overlay::Queue(_))) {
+            return;
+        }
+        let entries = self.playlist_entries();
+        if let Some(Overlay::Queue(menu)) = &mut self.state.overlay {
+            menu.entries = entries;
+        }
+    }
+
+    /// Saved playlists then smart ones, each with the tracks of the library it holds
+    fn playlist_entries(&self) -> Vec<MenuEntry> {
+        let library: HashMap<&str, _> = self.tracks.iter().map(|t| (t.path(), t)).collect();
+        let mut entries: Vec<MenuEntry> = playlist::names()
+            .into_iter()
+            .map(|name| {
+                // an unreadable playlist is listed as empty
+                let paths = playlist::read(&name).unwrap_or_default();
+                let tracks: Vec<_> = paths
+                    .iter()
+                    .filter_map(|p| library.get(p.as_str()).map(|t| (*t).clone()))
+                    .collect();
+                MenuEntry {
+                    missing: paths.len() - tracks.len(),
+                    name,
+                    smart: false,
+                    error: None,
+                    tracks,
+                }
+            })
+            .collect();
+        entries.extend(self.smart_playlists.iter().map(|p| MenuEntry {
+            name: p.name.clone(),
+            smart: true,
+            error: p.error().map(str::to_owned),
+            tracks: p.evaluate(&self.tracks, |t| self.track_data(t)),
+            missing: 0,
+        }));
+        entries
+    }
+
+    pub(super) fn handle_playlist_menu_key(&mut self, key: KeyEvent) {
+        let Some(Overlay::Queue(menu)) = &mut self.state.overlay else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.state.overlay = None,
+            KeyCode::Down => menu.list.select_next(),
+            KeyCode::Up => menu.list.select_previous(),
+            KeyCode::Enter => {
+                let Some(entry) = menu.list.selected().and_then(|i| menu.entries.get(i)) else {
+                    return;
+                };
+                if let Some(e) = &entry.error {
+                    self.state.message = Some(format!("{}: {e}", entry.name));
+                    return;
+                }
+                let mut message = format!("queued {} tracks of {}", entry.tracks.len(), entry.name);
+                if entry.missing > 0 {
+                    message.push_str(&format!(", {} not in the library", entry.missing));
+                }
+                let tracks = entry.tracks.clone();
+                self.state.overlay = None;
+                self.state.message = Some(message);
+                tracks.into_iter().for_each(|t| self.player.append_queue(t));
+            }
+            _ => (),
+        }
+    }
+
+    pub(super) fn render_playlist_menu(frame: &mut Frame, theme: &Theme, menu: &mut PlaylistMenu) {
+        let area = centered(frame.area(), 56, menu.entries.len() as u16 + 2);
+        let items = menu.entries.iter().map(|e| {
+            let kind = match e.smart {
+                true => "smart, ",
+                false => "",
+            };
+            let detail = match &e.error {
+                Some(_) => format!("  {kind}rules not understood"),
+                None => format!("  {kind}{} tracks", e.tracks.len()),
+            };
+            Line::from(vec![
+                Span::raw(e.name.as_str()),
+                Span::styled(detail, theme.dim),
+            ])
+        });
+        let widget = List::new(items)
+            .block(theme.block().title("queue playlist"))
+            .highlight_style(theme.highlight);
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(widget, area, &mut menu.list);
+    }
+}
@@ -0,0 +1,368 @@
+//! Podcasts panel: feeds subscribed to beside the episodes of the one chosen, to play, download
+//! and mark finished
+
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Layout, Position, Rect},
+    text::Line,
+    widgets::{Cell, List, ListItem, Paragraph, Row, Table},
+};
+
+use super::UserInterface;
+use super::format_time;
+use super::navigate::navigate;
+use super::state::{Listing, Mode, PodcastsPane};
+use crate::keymap::Action;
+use crate::playback::PlayerEvent;
+use crate::podcast::{Episode, Feed};
+
+impl UserInterface {
+    pub(super) fn handle_podcasts_action(&mut self, action: Action) {
+        if self.podcasts.is_none() {
+            self.state.message = Some(String::from("no podcasts database"));
+            return;
+        }
+        self.refresh_listing();
+        let page = self.page_rows();
+        let pane = &mut self.state.podcasts;
+        let feeds = pane.listing.as_ref().map_or(0, |l| l.feeds.len());
+        let episodes = pane.listing.as_ref().map_or(0, |l| l.episodes.len());
+        match action {
+            Action::PreviousFeed | Action::NextFeed => {
+                let i = pane.feeds.selected().unwrap_or(0);
+                let i = match action {
+                    Action::PreviousFeed => i.saturating_sub(1),
+                    _ => (i + 1).min(feeds.saturating_sub(1)),
+                };
+                pane.feeds.select(Some(i));
+                pane.episodes.select(None);
+            }
+            Action::Subscribe => {
+                pane.url.clear();
+                self.state.mode = Mode::Subscribe;
+            }
+            Action::Unsubscribe => self.unsubscribe(),
+            Action::RefreshFeeds => self.refresh_feeds(),
+            Action::PlayEpisode => self.play_episode(),
+            Action::DownloadEpisode => self.download_episode(),
+            Action::ToggleFinished => self.toggle_finished(),
+            _ => navigate(&mut pane.episodes, action, episodes, page),
+        }
+    }
+
+    pub(super) fn handle_subscribe_key(&mut self, key: KeyEvent) {
+        let url = &mut self.state.podcasts.url;
+        match key.code {
+            KeyCode::Esc => self.state.mode = Mode::Playback,
+            KeyCode::Char(c) => url.push(c),
+            KeyCode::Backspace => {
+                url.pop();
+            }
+            KeyCode::Enter => {
+                self.state.mode = Mode::Playback;
+                let url = url.trim().to_owned();
+                let Some(podcasts) = self.podcasts.as_mut().filter(|_| !url.is_empty()) else {
+                    return;
+                };
+                self.state.message = Some(match podcasts.subscribe(&url) {
+                    Ok(()) => format!("subscribed to {url}"),
+                    Err(e) => format!("cannot subscribe: {e}"),
+                });
+            }
+            _ => (),
+        }
+    }
+
+    /// Follows the episode playing, resuming it where it last stopped, and takes in completed
+    /// fetches and downloads
+    pub(super) fn follow_podcasts(&mut self, events: &[PlayerEvent]) {
+        let Some(podcasts) = &mut self.podcasts else {
+            return;
+        };
+        for e in events {
+            match podcasts.handle(e) {
+                Ok(Some(at)) => {
+                    self.state.message = Some(match self.player.seek(at) {
+                        true => format!("resuming at {}", format_time(at)),
+                        false => String::from("cannot resume the episode where it stopped"),
+                    })
+                }
+                Ok(None) => (),
+                Err(e) => self.state.message = Some(format!("cannot save episode position: {e}")),
+            }
+        }
+        if let Err(e) = podcasts.tick(self.player.position()) {
+            self.state.message = Some(format!("cannot save episode position: {e}"));
+        }
+        if let Some(notice) = podcasts.poll() {
+            self.state.message = Some(notice);
+        }
+    }
+
+    /// Reads feeds and episodes again where the database has changed or another feed is shown
+    fn refresh_listing(&mut self) {
+        let Some(podcasts) = &self.podcasts else {
+            return;
+        };
+        let pane = &mut self.state.podcasts;
+        let chosen = |l: &Listing| {
+            let i = pane.feeds.selected().unwrap_or(0);
+            l.feeds.get(i).or(l.feeds.last()).map(|f| f.id)
+        };
+        if pane
+            .listing
+            .as_ref()
+            .is_some_and(|l| l.changes == podcasts.changes && l.feed == chosen(l))
+        {
+            return;
+        }
+        let listing = (|| {
+            let feeds = podcasts.feeds()?;
+            let i = pane
+                .feeds
+                .selected()
+                .unwrap_or(0)
+                .min(feeds.len().saturating_sub(1));
+            let feed = feeds.get(i).map(|f| f.id);
+            let episodes = match feed {
+                Some(id) => podcasts.episodes(id)?,
+                None => Vec::new(),
+            };
+            Ok::<_, rusqlite::Error>(Listing {
+                changes: podcasts.changes,
+                feeds,
+                feed,
+                episodes,
+            })
+        })();
+        match listing {
+            Ok(l) => {
+                pane.feeds.select(Some(
+                    pane.feeds
+                        .selected()
+                        .unwrap_or(0)
+                        .min(l.feeds.len().saturating_sub(1)),
+                ));
+                pane.listing = Some(l);
+            }
+            Err(e) => {
+                pane.listing = None;
+                self.state.message = Some(format!("cannot read podcasts: {e}"));
+            }
+        }
+    }
+
+    pub(super) fn play_episode(&mut self) {
+        let (Some(podcasts), (_, Some(episode))) =
+            (&mut self.podcasts, chosen(&self.state.podcasts))
+        else {
+            return;
+        };
+        match podcasts.play(episode) {
+            Ok(track) => {
+                self.state.message = Some(format!("playing {}", episode.title));
+                self.player.play_now(track);
+            }
+            Err(e) => self.state.message = Some(format!("cannot play episode: {e}")),
+        }
+    }
+
+    fn download_episode(&mut self) {
+        let (Some(podcasts), (Some(feed), Some(episode))) =
+            (&mut self.podcasts, chosen(&self.state.podcasts))
+        else {
+            return;
+        };
+        self.state.message = Some(match &episode.file {
+            Some(f) if f.exists() => format!("already downloaded to {}", f.display()),
+            _ => {
+                podcasts.download(feed, episode);
+                format!("downloading {}", episode.title)
+            }
+        });
+    }
+
+    fn toggle_finished(&mut self) {
+        let (Some(podcasts), (_, Some(episode))) =
+            (&mut self.podcasts, chosen(&self.state.podcasts))
+        else {
+            return;
+        };
+        if let Err(e) = podcasts.set_finished(episode.id, !episode.finished) {
+            self.state.message = Some(format!("cannot mark episode: {e}"));
+        }
+    }
+
+    fn unsubscribe(&mut self) {
+        let (Some(podcasts), (Some(feed), _)) = (&mut self.podcasts, chosen(&self.state.podcasts))
+        else {
+            return;
+        };
+        self.state.message = Some(match podcasts.unsubscribe(feed.id) {
+            Ok(()) => format!("unsubscribed from {}", feed.title),
+            Err(e) => format!("cannot unsubscribe: {e}"),
+        });
+    }
+
+    fn refresh_feeds(&mut self) {
+        let Some(podcasts) = &mut self.podcasts else {
+            return;
+        };
+        self.state.message = Some(match podcasts.refresh() {
+            Ok(n) => format!("fetching {n} feeds"),
+            Err(e) => format!("cannot read podcasts: {e}"),
+        });
+    }
+
+    pub(super) fn render_podcasts(&mut self, area: Rect, frame: &mut Frame) {
+        self.refresh_listing();
+        let area = match self.state.mode {
+            Mode::Subscribe => {
+                let [input_area, rest] =
+                    Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(area);
+                let url = self.state.podcasts.url.as_str();
+                let input = Paragraph::new(url).block(
+                    self.theme
+                        .block()
+                        .title("subscribe to feed URL")
+                        .border_style(self.theme.search),
+                );
+                frame.render_widget(input, input_area);
+                let x = input_area.x + 1 + url.chars().count() as u16;
+                frame.set_cursor_position(Position::new(
+                    x.min(input_area.right() - 2),
+                    input_area.y + 1,
+                ));
+                rest
+            }
+            _ => area,
+        };
+
+        let hint = format!(
+            "{} subscribe  {} unsubscribe  {} refresh",
+            self.keymap.hint(Action::Subscribe),
+            self.keymap.hint(Action::Unsubscribe),
+            self.keymap.hint(Action::RefreshFeeds),
+        );
+        let mut feeds_block = self
+            .theme
+            .block()
+            .title("feeds")
+            .title_bottom(Line::styled(hint, self.theme.hint));
+        let Some(podcasts) = &self.podcasts else {
+            let text = Paragraph::new("no podcasts database").block(feeds_block);
+            frame.render_widget(text, area);
+            return;
+        };
+        let busy = match podcasts.busy() {
+            (0, 0) => None,
+            (0, d) => Some(format!("downloading {d}")),
+            (f, 0) => Some(format!("fetching {f}")),
+            (f, d) => Some(format!("fetching {f}, downloading {d}")),
+        };
+        if let Some(b) = busy {
+            feeds_block = feeds_block.title(Line::from(b).right_aligned());
+        }
+        let pane = &mut self.state.podcasts;
+        let Some(listing) = pane.listing.as_ref().filter(|l| !l.feeds.is_empty()) else {
+            let text = format!(
+                "no feeds subscribed to; {} subscribes to one by its URL",
+                self.keymap.hint(Action::Subscribe)
+            );
+            frame.render_widget(Paragraph::new(text).block(feeds_block), area);
+            return;
+        };
+
+        let [feeds_area, episodes_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(3)]).areas(area);
+        self.state.areas.body = episodes_area;
+
+        let feeds = listing.feeds.iter().map(|f| {
+            let mut line = Line::from(f.title.as_str());
+            if podcasts.is_fetching(f.id) {
+                line.push_span(" ⟳");
+            }
+            if f.unfinished > 0 {
+                line.push_span(format!("  {}", f.unfinished));
+            }
+            ListItem::new(line)
+        });
+        let feeds = List::new(feeds)
+            .block(feeds_block)
+            .highlight_style(self.theme.highlight);
+        frame.render_stateful_widget(feeds, feeds_area, &mut pane.feeds);
+
+        let hint = format!(
+            "{} play  {} download  {} finished  {} {} feed",
+            self.keymap.hint(Action::PlayEpisode),
+            self.keymap.hint(Action::DownloadEpisode),
+            self.keymap.hint(Action::ToggleFinished),
+            self.keymap.hint(Action::PreviousFeed),
+            self.keymap.hint(Action::NextFeed),
+        );
+        let title = listing
+            .feeds
+            .iter()
+            .find(|f| Some(f.id) == listing.feed)
+            .map_or("episodes", |f| f.url.as_str());
+        let block = self
+            .theme
+            .block()
+            .title(title)
+            .title_bottom(Line::styled(hint, self.theme.hint).right_aligned());
+
+        let rows = listing.episodes.iter().map(|e| {
+            let state = match (podcasts.is_downloading(e.id), &e.file) {
+                (true, _) => "↓",
+                (false, Some(_)) => "●",
+                (false, None) => "",
+            };
+            let heard = match (e.finished, e.position.is_zero()) {
+                (true, _) => String::from("finished"),
+                (false, false) => format_time(e.position),
+                (false, true) => String::new(),
+            };
+            let row = Row::new([
+                Cell::new(state),
+                Cell::new(e.title.as_str()),
+                Cell::new(e.published.as_deref().unwrap_or("")),
+                Cell::new(e.duration.map(format_time).unwrap_or_default()),
+                Cell::new(heard),
+            ]);
+            match e.finished {
+                true => row.style(self.theme.dim),
+                false => row,
+            }
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(1),
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Length(8),
+                Constraint::Length(8),
+            ],
+        )
+        .block(block)
+        .header(Row::new(["", "title", "published", "length", "heard"]).style(self.theme.header))
+        .column_spacing(2)
+        .row_highlight_style(self.theme.highlight);
+
+        frame.render_stateful_widget(table, episodes_area, &mut pane.episodes);
+    }
+}
+
+/// The feed shown and the episode selected in it
+fn chosen(pane: &PodcastsPane) -> (Option<&Feed>, Option<&Episode>) {
+    let Some(listing) = &pane.listing else {
+        return (None, None);
+    };
+    let feed = listing.feeds.iter().find(|f| Some(f.id) == listing.feed);
+    let episode = pane
+        .episodes
+        .selected()
+        .and_then(|i| listing.episodes.get(i));
+    (feed, episode)
+}
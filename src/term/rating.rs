@@ -0,0 +1,112 @@
+//! Rating tracks and marking favourites, and the column the track list is sorted by
+
+use std::path::Path;
+
+use super::UserInterface;
+use super::state::Mode;
+use crate::loader::AudioTrack;
+use crate::stats::TrackData;
+use crate::tags;
+
+/// Most stars a track can be given
+const STARS: u32 = 5;
+
+impl UserInterface {
+    /// What the user has made of a track, blank where there is no database
+    pub(super) fn track_data(&self, track: &AudioTrack) -> TrackData {
+        self.stats
+            .as_ref()
+            .map(|s| s.track(track))
+            .unwrap_or_default()
+    }
+
+    /// Waits for a digit to rate the chosen tracks with
+    pub(super) fn start_rating(&mut self) {
+        if self.stats.is_none() {
+            self.state.message = Some(String::from("no statistics database"));
+            return;
+        }
+        self.state.mode = Mode::Rate;
+        self.state.message = Some(format!("rate from 1 to {STARS} stars, or 0 to clear…"));
+    }
+
+    /// Gives the chosen tracks a number of stars, clearing their rating for 0
+    pub(super) fn rate(&mut self, digit: char) {
+        let Some(stars) = digit.to_digit(10).filter(|d| *d <= STARS) else {
+            self.state.message = Some(format!("ratings run from 0 to {STARS}"));
+            return;
+        };
+        let rating = (stars > 0).then_some(stars as u8);
+        let tracks = self.chosen_tracks();
+        let selected = self.selected_path();
+        let Some(db) = &mut self.stats else {
+            return;
+        };
+        for t in &tracks {
+            if let Err(e) = db.set_rating(t, rating) {
+                self.state.message = Some(format!("cannot save rating: {e}"));
+                return;
+            }
+        }
+        let mut failure = None;
+        if self.rating_tags {
+            // the file of a CUE sheet track holds the whole album, so its rating stays apart
+            for t in tracks.iter().filter(|t| t.cue().is_none()) {
+                if let Err(e) = tags::write_rating(Path::new(t.path()), rating) {
+                    failure = Some(format!("{}: {e}", t.path()));
+                }
+            }
+        }
+        self.clear_choice();
+        self.reselect(selected);
+
+        let done = match rating {
+            Some(_) => format!("rated {} tracks {stars} stars", tracks.len()),
+            None => format!("cleared the rating of {} tracks", tracks.len()),
+        };
+        self.state.message = Some(match failure {
+            None => done,
+            Some(e) => format!("{done}; rating not written to tags of {e}"),
+        });
+    }
+
+    /// Makes the chosen tracks favourites, or unmakes them where all of them already are
+    pub(super) fn toggle_favourite(&mut self) {
+        let tracks = self.chosen_tracks();
+        let favourite = !tracks.iter().all(|t| self.track_data(t).favourite);
+        let selected = self.selected_path();
+        let Some(db) = &mut self.stats else {
+            self.state.message = Some(String::from("no statistics database"));
+            return;
+        };
+        for t in &tracks {
+            if let Err(e) = db.set_favourite(t, favourite) {
+                self.state.message = Some(format!("cannot save favourite: {e}"));
+                return;
+            }
+        }
+        self.clear_choice();
+        self.reselect(selected);
+        self.state.message = Some(match favourite {
+            true => format!("{} tracks made favourites", tracks.len()),
+            false => format!("{} tracks no longer favourites", tracks.len()),
+        });
+    }
+
+    /// Sorts by the next column, keeping the selected track selected
+    pub(super) fn next_sort(&mut self) {
+        let selected = self.selected_path();
+        self.state.sort.key = self.state.sort.key.next();
+        self.state.sort.descending = false;
+        // rows of a visual range would now point elsewhere
+        self.state.visual = None;
+        self.reselect(selected);
+    }
+
+    pub(super) fn reverse_sort(&mut self) {
+        let selected = self.selected_path();
+        self.state.sort.descending = !self.state.sort.descending;
+        self.state.visual = None;
+        self.reselect(selected);
+    }
+}
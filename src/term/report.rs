@@ -0,0 +1,93 @@
+//! Scan report panel, listing problems found in the library by kind
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Rect},
+    text::Line,
+    widgets::{Cell, Paragraph, Row, Table},
+};
+
+use super::UserInterface;
+use super::library::group_digits;
+use super::navigate::navigate;
+use crate::keymap::Action;
+use crate::loader::Problem;
+
+impl UserInterface {
+    pub(super) fn handle_report_action(&mut self, action: Action) {
+        let len = self
+            .state
+            .report
+            .report
+            .as_ref()
+            .map_or(0, |r| r.diagnostics.len());
+        let page = self.page_rows();
+        match action {
+            Action::Rescan => self.rescan(),
+            _ => navigate(&mut self.state.report.table, action, len, page),
+        }
+    }
+
+    pub(super) fn render_report(&mut self, area: Rect, frame: &mut Frame) {
+        let hint = format!("{} rescan", self.keymap.hint(Action::Rescan));
+        let mut block = self
+            .theme
+            .block()
+            .title("scan report")
+            .title_bottom(Line::styled(hint, self.theme.hint).right_aligned());
+        if let Some(p) = self.scan_progress() {
+            block = block.title(Line::from(p).right_aligned());
+        }
+
+        let Some(report) = &self.state.report.report else {
+            let text = Paragraph::new("the library has not finished scanning").block(block);
+            frame.render_widget(text, area);
+            return;
+        };
+        if report.is_empty() {
+            frame.render_widget(Paragraph::new("no problems found").block(block), area);
+            return;
+        }
+
+        let summary: Vec<String> = report
+            .counts()
+            .iter()
+            .map(|(kind, n)| format!("{} {kind}", group_digits(*n)))
+            .collect();
+        block = block.title_bottom(summary.join(", "));
+
+        let rows = report.diagnostics.iter().map(|d| {
+            let path = d.path.strip_prefix(&self.library).unwrap_or(&d.path);
+            let detail = match &d.problem {
+                // the kind column already says as much
+                Problem::PermissionDenied | Problem::Untagged => String::new(),
+                Problem::Duplicate(first) => {
+                    let first = first.strip_prefix(&self.library).unwrap_or(first);
+                    format!("of {}", first.display())
+                }
+                Problem::UnreadableDirectory(e) => e.to_string(),
+                Problem::ProbeFailed(e) => e.to_string(),
+            };
+            Row::new([
+                Cell::new(d.problem.kind()),
+                Cell::new(path.display().to_string()),
+                Cell::new(detail),
+            ])
+        });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(22),
+                Constraint::Fill(3),
+                Constraint::Fill(2),
+            ],
+        )
+        .block(block)
+        .header(Row::new(["problem", "path", "detail"]).style(self.theme.header))
+        .column_spacing(2)
+        .row_highlight_style(self.theme.highlight);
+
+        frame.render_stateful_widget(table, area, &mut self.state.report.table);
+    }
+}
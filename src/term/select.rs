@@ -0,0 +1,113 @@
+//! Choosing several tracks at once, by marking rows or selecting a visual range, and the
+//! actions taken on every chosen track
+
+use std::{collections::HashSet, rc::Rc};
+
+use super::UserInterface;
+use crate::loader::AudioTrack;
+
+impl UserInterface {
+    /// Whether the track at `row` of the list is marked or within the visual range
+    pub(super) fn is_chosen(&self, row: usize, track: &AudioTrack) -> bool {
+        self.visual_range().is_some_and(|r| r.contains(&row))
+            || self.state.marked.contains(track.path())
+    }
+
+    fn visual_range(&self) -> Option<std::ops::RangeInclusive<usize>> {
+        let anchor = self.state.visual?;
+        let cursor = self.state.all_tracks.selected()?;
+        Some(anchor.min(cursor)..=anchor.max(cursor))
+    }
+
+    /// Whether any tracks are marked or a range is being selected
+    pub(super) fn choosing(&self) -> bool {
+        self.state.visual.is_some() || !self.state.marked.is_empty()
+    }
+
+    /// Marked tracks and those in the visual range in library order, or else the selected track
+    pub(super) fn chosen_tracks(&self) -> Vec<Rc<AudioTrack>> {
+        let visible = self.visible_tracks();
+        if !self.choosing() {
+            return self
+                .state
+                .all_tracks
+                .selected()
+                .and_then(|i| visible.get(i).cloned())
+                .into_iter()
+                .collect();
+        }
+
+        let mut paths: HashSet<&str> = self.state.marked.iter().map(String::as_str).collect();
+        if let Some(range) = self.visual_range() {
+            paths.extend(
+                visible
+                    .iter()
+                    .skip(*range.start())
+                    .take(range.count())
+                    .map(|t| t.path()),
+            );
+        }
+        self.tracks
+            .iter()
+            .filter(|t| paths.contains(t.path()))
+            .cloned()
+            .collect()
+    }
+
+    /// Forgets marks and leaves visual mode, once an action has used them
+    pub(super) fn clear_choice(&mut self) {
+        self.state.marked.clear();
+        self.state.visual = None;
+    }
+
+    /// Starts a visual range at the selected row, or marks the range and ends it
+    pub(super) fn toggle_visual(&mut self) {
+        match self.visual_range() {
+            Some(range) => {
+                let visible = self.visible_tracks();
+                let rows = visible.iter().skip(*range.start()).take(range.count());
+                self.state.marked.extend(rows.map(|t| t.path().to_owned()));
+                self.state.visual = None;
+            }
+            None => self.state.visual = self.state.all_tracks.selected(),
+        }
+    }
+
+    /// Marks or unmarks the selected row, moving on to the next
+    pub(super) fn toggle_mark(&mut self) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        if !self.state.marked.remove(&path) {
+            self.state.marked.insert(path);
+        }
+        self.state.all_tracks.select_next();
+    }
+
+    /// Marks every track matching the search
+    pub(super) fn mark_all(&mut self) {
+        let visible = self.visible_tracks();
+        self.state
+            .marked
+            .extend(visible.iter().map(|t| t.path().to_owned()));
+        self.state.message = Some(format!("{} tracks marked", self.state.marked.len()));
+    }
+
+    /// Queues the chosen tracks at the end of the queue, or ahead of it where `next` is set
+    pub(super) fn enqueue_chosen(&mut self, next: bool) {
+        let tracks = self.chosen_tracks();
+        let n = tracks.len();
+        if n == 0 {
+            return;
+        }
+        match next {
+            true => self.player.queue_next(tracks),
+            false => tracks.into_iter().for_each(|t| self.player.append_queue(t)),
+        }
+        self.clear_choice();
+        self.state.message = Some(match next {
+            true => format!("playing {n} tracks next"),
+            false => format!("queued {n} tracks"),
+        });
+    }
+}
@@ -1,11 +1,54 @@
 //! Tracks global state and performs event handling
 
-use ratatui::widgets::TableState;
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    layout::Rect,
+    widgets::{ListState, TableState},
+};
+
+use crate::art::{Art, Protocol};
+use crate::keymap::{Context, Key};
+use crate::loader::{AudioTrack, Report};
+use crate::lyrics::Lyrics;
+use crate::podcast::{Episode, Feed};
+use crate::query::Sort;
+use crate::stats::{Ranked, Summary};
+use crate::tags::{Field, Tags};
 
 pub struct State {
     pub all_tracks: TableState,
+    /// Where parts of the screen were drawn in the latest frame, for finding what is clicked
+    pub areas: Areas,
+    pub art: ArtPane,
+    /// Selected row of the equalizer panel: preamp, each band, then balance
+    pub equalizer: TableState,
+    /// Time and row of the latest click on a row, to tell a double click
+    pub last_click: Option<(Instant, usize)>,
+    pub lyrics: LyricsPane,
+    /// Paths of the tracks marked for a bulk action
+    pub marked: HashSet<String>,
+    /// Short lived feedback shown in the status panel, such as the result of a save
+    pub message: Option<String>,
     pub mode: Mode,
+    /// Popup drawn over the body, taking all key input until closed
+    pub overlay: Option<Overlay>,
+    /// Keys pressed so far of a sequence bound to an action
+    pub pending: Vec<Key>,
+    pub podcasts: PodcastsPane,
+    pub report: ReportPane,
     pub search: Search,
+    /// Column and direction the track list is ordered by
+    pub sort: Sort,
+    pub stats: StatsPane,
+    pub view: View,
+    /// Row a visual range was started from, the range running to the selected row
+    pub visual: Option<usize>,
 }
 
 pub struct Search {
@@ -20,18 +63,241 @@ pub struct Search {
 pub enum Mode {
     Playback,
     Search,
+    /// Waiting for the letter of a title to jump to
+    Jump,
+    /// Waiting for the number of stars to rate the chosen tracks
+    Rate,
+    /// Typing the URL of a feed to subscribe to
+    Subscribe,
+}
+
+/// Lyrics of the playing track, loaded when the track changes
+pub struct LyricsPane {
+    /// Path of the track the lyrics belong to
+    pub track: Option<String>,
+    pub lyrics: Option<Lyrics>,
+    /// User adjustment applied on top of any offset in the lyrics, in milliseconds
+    pub offset: i64,
+    /// First visible line, for plain lyrics scrolled by hand
+    pub scroll: u16,
+}
+
+/// Problems found by the last completed scan
+pub struct ReportPane {
+    pub report: Option<Report>,
+    pub table: TableState,
+}
+
+/// Feeds and episodes of the podcasts view, read from the database again when out of date
+pub struct PodcastsPane {
+    pub feeds: ListState,
+    pub episodes: TableState,
+    /// URL of a feed being typed, to subscribe to
+    pub url: String,
+    pub listing: Option<Listing>,
+}
+
+pub struct Listing {
+    /// Changes made to the database when read
+    pub changes: u64,
+    pub feeds: Vec<Feed>,
+    /// Feed the episodes are of
+    pub feed: Option<i64>,
+    pub episodes: Vec<Episode>,
+}
+
+#[derive(Default)]
+pub struct Areas {
+    pub tabs: Rect,
+    /// Panel of the current view
+    pub body: Rect,
+    pub progress: Rect,
+}
+
+/// Album art of the playing track and where it was last written
+pub struct ArtPane {
+    pub art: Art,
+    /// Cells given to the art in the latest frame
+    pub area: Rect,
+    /// Cells covered by an image written by escape sequence, which the frame knows nothing of
+    pub shown: Option<Rect>,
+    /// Whether the image must be written again after the frame
+    pub stale: bool,
+}
+
+pub enum Overlay {
+    /// Output device picker; the first entry stands for the system default
+    Devices(Vec<String>, ListState),
+    Tags(Box<TagForm>),
+    Playlists(PlaylistPicker),
+    Queue(PlaylistMenu),
+    /// Radio stations of the configuration
+    Stations(ListState),
+    /// Key bindings, scrolled by the given number of lines
+    Help(u16),
+}
+
+/// Picker adding tracks to a saved playlist or a new one; the first entry starts a new one
+pub struct PlaylistPicker {
+    /// Paths of the tracks to add
+    pub tracks: Vec<String>,
+    pub names: Vec<String>,
+    pub list: ListState,
+    /// Name of a new playlist being typed
+    pub naming: Option<String>,
+}
+
+/// Saved playlists and smart playlists to queue, with the tracks of the library each holds
+pub struct PlaylistMenu {
+    pub entries: Vec<MenuEntry>,
+    pub list: ListState,
+}
+
+pub struct MenuEntry {
+    pub name: String,
+    pub smart: bool,
+    pub tracks: Vec<Rc<AudioTrack>>,
+    /// Tracks of a saved playlist not found in the library
+    pub missing: usize,
+    /// What was not understood in the rules of a smart playlist
+    pub error: Option<String>,
+}
+
+/// Tag editing form for one file, or for several at once
+pub struct TagForm {
+    pub paths: Vec<PathBuf>,
+    pub title: String,
+    /// Values shown, which for several files are those they have in common
+    pub tags: Tags,
+    /// Fields differing between files, shown as such until given a value
+    pub mixed: Vec<Field>,
+    /// Fields edited, the only ones written on saving
+    pub changed: Vec<Field>,
+    pub selected: usize,
+    /// Whether typing goes into the selected field
+    pub editing: bool,
+}
+
+/// Panel occupying the body of the screen, cycled with tab
+#[derive(Clone, Copy, PartialEq)]
+pub enum View {
+    Tracks,
+    Equalizer,
+    Lyrics,
+    Report,
+    Stats,
+    Podcasts,
+}
+
+/// Figures of the statistics view, drawn from the database when out of date
+pub struct StatsPane {
+    /// Index of the period shown among those configured
+    pub period: usize,
+    pub figures: Option<Figures>,
+    /// Rank selected, the same row across the lists of most played
+    pub ranks: TableState,
+    /// Rows of the lists shown at once, as last drawn
+    pub page: usize,
+}
+
+pub struct Figures {
+    pub period: usize,
+    /// Plays recorded when drawn
+    pub recorded: u64,
+    pub summary: Summary,
+    pub tracks: Vec<Ranked>,
+    pub albums: Vec<Ranked>,
+    pub artists: Vec<Ranked>,
+    pub daily: Vec<(String, Duration)>,
 }
 
 impl State {
-    pub fn new() -> Self {
+    pub fn new(art: Protocol) -> Self {
         Self {
             all_tracks: TableState::default(),
+            areas: Areas::default(),
+            art: ArtPane {
+                art: Art::new(art),
+                area: Rect::default(),
+                shown: None,
+                stale: false,
+            },
+            equalizer: TableState::default().with_selected(0),
+            last_click: None,
+            lyrics: LyricsPane {
+                track: None,
+                lyrics: None,
+                offset: 0,
+                scroll: 0,
+            },
+            marked: HashSet::new(),
+            message: None,
             mode: Mode::Playback,
+            overlay: None,
+            pending: Vec::new(),
+            podcasts: PodcastsPane {
+                feeds: ListState::default().with_selected(Some(0)),
+                episodes: TableState::default(),
+                url: String::new(),
+                listing: None,
+            },
+            report: ReportPane {
+                report: None,
+                table: TableState::default(),
+            },
             search: Search::new(),
+            sort: Sort::default(),
+            stats: StatsPane {
+                period: 0,
+                figures: None,
+                ranks: TableState::default(),
+                page: 0,
+            },
+            view: View::Tracks,
+            visual: None,
         }
     }
 }
 
+impl View {
+    pub const ALL: [View; 6] = [
+        View::Tracks,
+        View::Lyrics,
+        View::Equalizer,
+        View::Report,
+        View::Stats,
+        View::Podcasts,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            View::Tracks => "tracks",
+            View::Equalizer => "equalizer",
+            View::Lyrics => "lyrics",
+            View::Report => "scan report",
+            View::Stats => "stats",
+            View::Podcasts => "podcasts",
+        }
+    }
+
+    /// Context of the key bindings active in the view
+    pub fn context(self) -> Context {
+        match self {
+            View::Tracks => Context::Tracks,
+            View::Equalizer => Context::Equalizer,
+            View::Lyrics => Context::Lyrics,
+            View::Report => Context::Report,
+            View::Stats => Context::Stats,
+            View::Podcasts => Context::Podcasts,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|v| *v == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
 impl Search {
     fn new() -> Self {
         Self {
@@ -0,0 +1,76 @@
+//! Menu of the radio stations of the configuration, tuning in to the one chosen
+
+use std::rc::Rc;
+
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    text::{Line, Span},
+    widgets::{Clear, List, ListState},
+};
+
+use super::UserInterface;
+use super::overlay::centered;
+use super::state::Overlay;
+use crate::loader::AudioTrack;
+use crate::theme::Theme;
+
+impl UserInterface {
+    pub(super) fn open_stations(&mut self) {
+        if self.stations.is_empty() {
+            self.state.message = Some(String::from("no stations in the configuration"));
+            return;
+        }
+        let playing = self.player.playing().map(|t| t.path());
+        let current = self
+            .stations
+            .iter()
+            .position(|s| Some(s.path()) == playing)
+            .unwrap_or(0);
+        self.state.overlay = Some(Overlay::Stations(
+            ListState::default().with_selected(Some(current)),
+        ));
+    }
+
+    pub(super) fn handle_stations_key(&mut self, key: KeyEvent) {
+        let Some(Overlay::Stations(list)) = &mut self.state.overlay else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.state.overlay = None,
+            KeyCode::Down => list.select_next(),
+            KeyCode::Up => list.select_previous(),
+            KeyCode::Enter => {
+                let Some(station) = list.selected().and_then(|i| self.stations.get(i)).cloned()
+                else {
+                    return;
+                };
+                self.state.overlay = None;
+                self.state.message = Some(format!("tuning in to {}", station.title()));
+                self.player.play_now(station);
+            }
+            _ => (),
+        }
+    }
+
+    pub(super) fn render_stations(
+        frame: &mut Frame,
+        theme: &Theme,
+        stations: &[Rc<AudioTrack>],
+        list: &mut ListState,
+    ) {
+        let area = centered(frame.area(), 72, stations.len() as u16 + 2);
+        let items = stations.iter().map(|s| {
+            Line::from(vec![
+                Span::raw(s.title()),
+                Span::styled(format!("  {}", s.path()), theme.dim),
+            ])
+        });
+        let widget = List::new(items)
+            .block(theme.block().title("radio stations"))
+            .highlight_style(theme.highlight);
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(widget, area, list);
+    }
+}
@@ -0,0 +1,227 @@
+//! Statistics panel: most played tracks, albums and artists, skips and time listened each day
+//! over a chosen period
+
+use std::time::Duration;
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    text::Line,
+    widgets::{Bar, BarChart, BarGroup, Block, Cell, Paragraph, Row, Table},
+};
+
+use super::UserInterface;
+use super::library::group_digits;
+use super::navigate::navigate;
+use super::state::Figures;
+use crate::keymap::Action;
+use crate::stats::{self, Group, Ranked};
+
+/// Entries in each list of most played
+const TOP: usize = 50;
+
+/// Most days of listening time shown
+const DAYS: u32 = 90;
+
+impl UserInterface {
+    pub(super) fn handle_stats_action(&mut self, action: Action) {
+        let pane = &mut self.state.stats;
+        let periods = self.stats_config.periods.len();
+        match action {
+            Action::PreviousPeriod => pane.period = pane.period.saturating_sub(1),
+            Action::NextPeriod => pane.period = (pane.period + 1).min(periods - 1),
+            Action::QueueForgotten => self.queue_forgotten(),
+            _ => {
+                let len = pane.figures.as_ref().map_or(0, |f| {
+                    f.tracks.len().max(f.albums.len()).max(f.artists.len())
+                });
+                navigate(&mut pane.ranks, action, len, pane.page);
+            }
+        }
+    }
+
+    /// Queues every track of the library not played within the configured number of days
+    fn queue_forgotten(&mut self) {
+        let Some(db) = &self.stats else {
+            self.state.message = Some(String::from("no statistics database"));
+            return;
+        };
+        let days = self.stats_config.forgotten;
+        let played = match db.played_since(stats::since(Some(days))) {
+            Ok(p) => p,
+            Err(e) => {
+                self.state.message = Some(format!("cannot read statistics: {e}"));
+                return;
+            }
+        };
+        let forgotten: Vec<_> = self
+            .tracks
+            .iter()
+            .filter(|t| !played.contains(t.path()))
+            .cloned()
+            .collect();
+        self.state.message = Some(format!(
+            "queued {} tracks not played in {days} days",
+            group_digits(forgotten.len())
+        ));
+        forgotten
+            .into_iter()
+            .for_each(|t| self.player.append_queue(t));
+    }
+
+    /// Draws the figures of the period shown again where a play has since been recorded
+    fn refresh_figures(&mut self) {
+        let Some(db) = &self.stats else {
+            return;
+        };
+        let pane = &mut self.state.stats;
+        if pane
+            .figures
+            .as_ref()
+            .is_some_and(|f| f.period == pane.period && f.recorded == db.recorded)
+        {
+            return;
+        }
+        let days = self.stats_config.periods[pane.period];
+        let since = stats::since(days);
+        let figures = (|| {
+            Ok::<_, rusqlite::Error>(Figures {
+                period: pane.period,
+                recorded: db.recorded,
+                summary: db.summary(since)?,
+                tracks: db.top(Group::Track, since, TOP)?,
+                albums: db.top(Group::Album, since, TOP)?,
+                artists: db.top(Group::Artist, since, TOP)?,
+                daily: db.daily(days.unwrap_or(DAYS).min(DAYS))?,
+            })
+        })();
+        match figures {
+            Ok(f) => pane.figures = Some(f),
+            Err(e) => {
+                pane.figures = None;
+                self.state.message = Some(format!("cannot read statistics: {e}"));
+            }
+        }
+    }
+
+    pub(super) fn render_stats(&mut self, area: Rect, frame: &mut Frame) {
+        self.refresh_figures();
+        let days = self.stats_config.periods[self.state.stats.period];
+        let title = match days {
+            Some(d) => format!("stats · last {d} days"),
+            None => String::from("stats · all time"),
+        };
+        let hint = format!(
+            "{} {} period  {} queue not played in {} days",
+            self.keymap.hint(Action::PreviousPeriod),
+            self.keymap.hint(Action::NextPeriod),
+            self.keymap.hint(Action::QueueForgotten),
+            self.stats_config.forgotten,
+        );
+        let block = self
+            .theme
+            .block()
+            .title(title)
+            .title_bottom(Line::styled(hint, self.theme.hint).right_aligned());
+
+        let Some(figures) = &self.state.stats.figures else {
+            let text = match self.stats {
+                Some(_) => "statistics cannot be read",
+                None => "no statistics database",
+            };
+            frame.render_widget(Paragraph::new(text).block(block), area);
+            return;
+        };
+
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let [summary_area, top_area, daily_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(8),
+        ])
+        .areas(inner);
+
+        let s = &figures.summary;
+        let skip_rate = match s.plays {
+            0 => 0,
+            n => s.skipped * 100 / n,
+        };
+        let summary = format!(
+            "{} plays, {skip_rate}% skipped, {} listened",
+            group_digits(s.plays as usize),
+            hours(s.listened)
+        );
+        frame.render_widget(Paragraph::new(summary), summary_area);
+
+        let columns: [Rect; 3] = Layout::horizontal([Constraint::Fill(1); 3])
+            .spacing(2)
+            .areas(top_area);
+        let lists = [
+            ("track", &figures.tracks),
+            ("album", &figures.albums),
+            ("artist", &figures.artists),
+        ];
+        // below the header of each list
+        self.state.stats.page = top_area.height.saturating_sub(1) as usize;
+        for ((heading, ranked), column) in lists.into_iter().zip(columns) {
+            let mut ranks = self.state.stats.ranks.clone();
+            if ranks.selected().is_some_and(|i| i >= ranked.len()) {
+                ranks.select(None);
+            }
+            frame.render_stateful_widget(self.ranking(heading, ranked), column, &mut ranks);
+        }
+
+        // as many of the latest days as fit, a bar and a gap each
+        let fit = (daily_area.width / 6) as usize;
+        let bars: Vec<Bar> = figures.daily[figures.daily.len().saturating_sub(fit)..]
+            .iter()
+            .map(|(day, listened)| {
+                let minutes = listened.as_secs() / 60;
+                Bar::default()
+                    .value(minutes)
+                    .text_value(match minutes {
+                        0 => String::new(),
+                        m => m.to_string(),
+                    })
+                    .label(Line::from(day.get(5..).unwrap_or(day).to_owned()))
+            })
+            .collect();
+        let chart = BarChart::default()
+            .block(Block::new().title(Line::styled("minutes listened", self.theme.header)))
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(5)
+            .bar_gap(1)
+            .bar_style(self.theme.progress);
+        frame.render_widget(chart, daily_area);
+    }
+
+    fn ranking<'a>(&self, heading: &'a str, ranked: &'a [Ranked]) -> Table<'a> {
+        let rows = ranked.iter().map(|r| {
+            Row::new([
+                Cell::new(r.name.as_str()),
+                Cell::new(Line::from(group_digits(r.plays as usize)).right_aligned()),
+                Cell::new(Line::from(hours(r.listened)).right_aligned()),
+            ])
+        });
+        Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(6),
+                Constraint::Length(8),
+            ],
+        )
+        .header(Row::new([heading, "plays", "time"]).style(self.theme.header))
+        .row_highlight_style(self.theme.highlight)
+    }
+}
+
+/// Formats a length of time as hours and minutes, as in `12h 05m`
+fn hours(d: Duration) -> String {
+    let minutes = d.as_secs() / 60;
+    match minutes / 60 {
+        0 => format!("{minutes}m"),
+        h => format!("{h}h {:02}m", minutes % 60),
+    }
+}
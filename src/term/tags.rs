@@ -0,0 +1,227 @@
+//! Tag editor, for the selected track or every track of its album at once
+
+use std::path::{Path, PathBuf};
+
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::Position,
+    text::{Line, Span},
+    widgets::{Clear, List, ListItem, ListState},
+};
+
+use super::UserInterface;
+use super::overlay::centered;
+use super::state::{Overlay, TagForm};
+use crate::loader;
+use crate::tags::{self, Field};
+use crate::theme::Theme;
+
+/// Width of the field labels, including the separating space
+const LABEL_WIDTH: u16 = 14;
+
+impl UserInterface {
+    /// Opens the editor for the chosen tracks or, where `album` is set, for every track of the
+    /// selected track's album in the same directory
+    pub(super) fn open_tag_editor(&mut self, album: bool) {
+        if self.chosen_tracks().iter().any(|t| t.cue().is_some()) {
+            self.state.message = Some(String::from(
+                "tracks of a CUE sheet take their tags from the sheet",
+            ));
+            return;
+        }
+        let (paths, title) = match album {
+            false => {
+                let paths: Vec<PathBuf> = self
+                    .chosen_tracks()
+                    .iter()
+                    .map(|t| PathBuf::from(t.path()))
+                    .collect();
+                let title = match paths.len() {
+                    1 => String::from("edit tags"),
+                    n => format!("edit tags: {n} tracks"),
+                };
+                (paths, title)
+            }
+            true => {
+                let Some(selected) = self
+                    .state
+                    .all_tracks
+                    .selected()
+                    .and_then(|i| self.visible_tracks().get(i).cloned())
+                else {
+                    return;
+                };
+                let Some(name) = selected.album() else {
+                    self.state.message = Some(String::from("track has no album to edit"));
+                    return;
+                };
+                let dir = Path::new(selected.path()).parent();
+                let paths: Vec<PathBuf> = self
+                    .tracks
+                    .iter()
+                    .filter(|t| t.album() == Some(name) && Path::new(t.path()).parent() == dir)
+                    .filter(|t| t.cue().is_none())
+                    .map(|t| PathBuf::from(t.path()))
+                    .collect();
+                let title = format!("edit tags: {} tracks of {name}", paths.len());
+                (paths, title)
+            }
+        };
+        self.clear_choice();
+
+        let mut read = paths.iter().map(|p| tags::read(p));
+        let mut tags = match read.next() {
+            Some(Ok(t)) => t,
+            Some(Err(e)) => {
+                self.state.message = Some(format!("cannot read tags: {e}"));
+                return;
+            }
+            None => return,
+        };
+        // keep only the values every file shares
+        let mut mixed = Vec::new();
+        for other in read {
+            let other = match other {
+                Ok(t) => t,
+                Err(e) => {
+                    self.state.message = Some(format!("cannot read tags: {e}"));
+                    return;
+                }
+            };
+            for field in Field::ALL {
+                if tags.get(field) != other.get(field) && !mixed.contains(&field) {
+                    tags.set(field, String::new());
+                    mixed.push(field);
+                }
+            }
+        }
+
+        self.state.overlay = Some(Overlay::Tags(Box::new(TagForm {
+            paths,
+            title,
+            tags,
+            mixed,
+            changed: Vec::new(),
+            selected: 0,
+            editing: false,
+        })));
+    }
+
+    pub(super) fn handle_tags_key(&mut self, key: KeyEvent) {
+        let Some(Overlay::Tags(form)) = &mut self.state.overlay else {
+            return;
+        };
+        let field = Field::ALL[form.selected];
+
+        if form.editing {
+            let mut value = form.tags.get(field).to_owned();
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => form.editing = false,
+                KeyCode::Char(c) => value.push(c),
+                KeyCode::Backspace => {
+                    value.pop();
+                }
+                KeyCode::Delete => value.clear(),
+                _ => (),
+            }
+            if value != form.tags.get(field) {
+                form.tags.set(field, value);
+                if !form.changed.contains(&field) {
+                    form.changed.push(field);
+                }
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.state.overlay = None,
+            KeyCode::Down => form.selected = (form.selected + 1).min(Field::ALL.len() - 1),
+            KeyCode::Up => form.selected = form.selected.saturating_sub(1),
+            KeyCode::Enter => form.editing = true,
+            KeyCode::Char('s') => self.save_tags(),
+            _ => (),
+        }
+    }
+
+    /// Writes the changed fields to every file of the form, then reads the files back into the
+    /// library
+    fn save_tags(&mut self) {
+        let Some(Overlay::Tags(form)) = self.state.overlay.take() else {
+            return;
+        };
+        let edits: Vec<(Field, String)> = form
+            .changed
+            .iter()
+            .map(|f| (*f, form.tags.get(*f).to_owned()))
+            .collect();
+        if edits.is_empty() {
+            self.state.message = Some(String::from("no tags changed"));
+            return;
+        }
+
+        let selected = self.selected_path();
+        let mut written = 0;
+        let mut failure = None;
+        for path in &form.paths {
+            match tags::write(path, &edits) {
+                Ok(()) => {
+                    written += 1;
+                    if let Ok(tracks) = loader::read_audio_file(path) {
+                        tracks.into_iter().for_each(|t| self.upsert(t));
+                    }
+                }
+                Err(e) => failure = Some(format!("{}: {e}", path.display())),
+            }
+        }
+        self.reselect(selected);
+
+        self.state.message = Some(match failure {
+            None => format!("tags saved to {written} files"),
+            Some(e) => format!("tags saved to {written} of {} files; {e}", form.paths.len()),
+        });
+    }
+
+    pub(super) fn render_tags(frame: &mut Frame, theme: &Theme, form: &TagForm) {
+        let area = centered(frame.area(), 72, Field::ALL.len() as u16 + 2);
+        let items = Field::ALL.iter().map(|f| {
+            let value = match form.tags.get(*f) {
+                "" if form.mixed.contains(f) && !form.changed.contains(f) => {
+                    Span::styled("(various)", theme.dim)
+                }
+                v => Span::raw(v.to_owned()),
+            };
+            let label = format!("{:>w$} ", f.label(), w = LABEL_WIDTH as usize - 1);
+            ListItem::new(Line::from(vec![Span::styled(label, theme.title), value]))
+        });
+
+        let hint = match form.editing {
+            true => "enter done · del clear",
+            false => "enter edit · s save · esc close",
+        };
+        let widget = List::new(items)
+            .block(
+                theme
+                    .block()
+                    .title(form.title.clone())
+                    .title_bottom(Line::styled(hint, theme.hint).right_aligned()),
+            )
+            .highlight_style(theme.highlight);
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(
+            widget,
+            area,
+            &mut ListState::default().with_selected(Some(form.selected)),
+        );
+
+        if form.editing {
+            let field = Field::ALL[form.selected];
+            let x = area.x + 1 + LABEL_WIDTH + form.tags.get(field).chars().count() as u16;
+            frame.set_cursor_position(Position::new(
+                x.min(area.right().saturating_sub(2)),
+                area.y + 1 + form.selected as u16,
+            ));
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Colours as written in the configuration, and their nearest match on terminals showing fewer
+
+use std::env;
+
+use ratatui::style::Color;
+
+/// The 16 terminal colours by name, with the values xterm gives them
+const ANSI: [(&str, Color, [u8; 3]); 16] = [
+    ("black", Color::Black, [0, 0, 0]),
+    ("red", Color::Red, [205, 0, 0]),
+    ("green", Color::Green, [0, 205, 0]),
+    ("yellow", Color::Yellow, [205, 205, 0]),
+    ("blue", Color::Blue, [0, 0, 238]),
+    ("magenta", Color::Magenta, [205, 0, 205]),
+    ("cyan", Color::Cyan, [0, 205, 205]),
+    ("gray", Color::Gray, [229, 229, 229]),
+    ("darkgray", Color::DarkGray, [127, 127, 127]),
+    ("lightred", Color::LightRed, [255, 0, 0]),
+    ("lightgreen", Color::LightGreen, [0, 255, 0]),
+    ("lightyellow", Color::LightYellow, [255, 255, 0]),
+    ("lightblue", Color::LightBlue, [92, 92, 255]),
+    ("lightmagenta", Color::LightMagenta, [255, 0, 255]),
+    ("lightcyan", Color::LightCyan, [0, 255, 255]),
+    ("white", Color::White, [255, 255, 255]),
+];
+
+/// Levels of the 6x6x6 colour cube of the 256 colour palette
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// How many colours the terminal shows
+#[derive(Clone, Copy, PartialEq)]
+pub enum Depth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    /// Modifiers alone, as asked for by `NO_COLOR`
+    None,
+}
+
+impl Depth {
+    /// Guesses from the environment, honouring `NO_COLOR`
+    pub fn detect() -> Self {
+        if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return Depth::None;
+        }
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return Depth::TrueColor;
+        }
+        match env::var("TERM").unwrap_or_default() {
+            t if t == "dumb" => Depth::None,
+            t if t.contains("256color") => Depth::Ansi256,
+            _ => Depth::Ansi16,
+        }
+    }
+
+    /// Nearest colour the terminal shows, or `None` where it is to show none
+    pub fn convert(self, colour: Color) -> Option<Color> {
+        match (self, colour) {
+            (Depth::None, _) => None,
+            (Depth::TrueColor, c) => Some(c),
+            (Depth::Ansi256, Color::Rgb(r, g, b)) => Some(Color::Indexed(nearest_256([r, g, b]))),
+            (Depth::Ansi16, Color::Rgb(r, g, b)) => Some(nearest_16([r, g, b])),
+            (Depth::Ansi16, Color::Indexed(i)) => Some(nearest_16(indexed_rgb(i))),
+            (_, c) => Some(c),
+        }
+    }
+}
+
+impl std::str::FromStr for Depth {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "truecolor" => Ok(Depth::TrueColor),
+            "256" => Ok(Depth::Ansi256),
+            "16" => Ok(Depth::Ansi16),
+            "none" => Ok(Depth::None),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Parses a colour name, `#rrggbb` or 256 colour index
+pub fn parse(s: &str) -> Result<Color, String> {
+    if let Some((_, c, _)) = ANSI.iter().find(|(name, ..)| name.eq_ignore_ascii_case(s)) {
+        return Ok(*c);
+    }
+    if let Some(hex) = s.strip_prefix('#')
+        && hex.len() == 6
+        && let Ok(v) = u32::from_str_radix(hex, 16)
+    {
+        return Ok(Color::Rgb((v >> 16) as u8, (v >> 8) as u8, v as u8));
+    }
+    match s.parse() {
+        Ok(i) => Ok(Color::Indexed(i)),
+        Err(_) => Err(format!("unknown colour `{s}`")),
+    }
+}
+
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (*x as i32 - y as i32).pow(2) as u32)
+        .sum()
+}
+
+/// Index of the nearest colour of the cube or the grey ramp; the first 16 are left out, their
+/// values being up to the terminal
+fn nearest_256(rgb: [u8; 3]) -> u8 {
+    let level = |c: u8| {
+        (0..CUBE.len())
+            .min_by_key(|i| (CUBE[*i] as i32 - c as i32).abs())
+            .unwrap_or(0)
+    };
+    let [r, g, b] = rgb.map(level);
+    let cube = [CUBE[r], CUBE[g], CUBE[b]];
+
+    // greys run from 8 to 238 in steps of 10
+    let mean = rgb.iter().map(|c| *c as u32).sum::<u32>() / 3;
+    let step = (mean.saturating_sub(3) / 10).min(23) as u8;
+    let grey = 8 + step * 10;
+
+    match distance(rgb, [grey; 3]) < distance(rgb, cube) {
+        true => 232 + step,
+        false => 16 + 36 * r as u8 + 6 * g as u8 + b as u8,
+    }
+}
+
+fn nearest_16(rgb: [u8; 3]) -> Color {
+    ANSI.iter()
+        .min_by_key(|(_, _, v)| distance(rgb, *v))
+        .map_or(Color::Reset, |(_, c, _)| *c)
+}
+
+/// Value of a colour of the 256 colour palette
+fn indexed_rgb(i: u8) -> [u8; 3] {
+    match i {
+        0..16 => ANSI[i as usize].2,
+        16..232 => {
+            let i = i - 16;
+            [
+                CUBE[(i / 36) as usize],
+                CUBE[(i / 6 % 6) as usize],
+                CUBE[(i % 6) as usize],
+            ]
+        }
+        _ => [8 + (i - 232) * 10; 3],
+    }
+}
@@ -0,0 +1,305 @@
+//! Colours and styles of the interface, from a built-in theme adjusted by the `[theme]` section
+//! of the configuration
+//!
+//! A style is written as words: modifiers (`bold`, `dim`, `italic`, `underlined`, `reversed`),
+//! a foreground colour, and `on` followed by a background colour. Colours are names of the 16
+//! terminal colours, `#rrggbb` or a 256 colour index, brought down to what the terminal shows
+
+use ratatui::{
+    style::{Color, Modifier, Style, Stylize},
+    widgets::{Block, BorderType},
+};
+
+mod colour;
+
+pub use colour::Depth;
+
+/// Every element of the interface that may be styled
+#[derive(Clone, Copy, PartialEq)]
+pub enum Element {
+    Title,
+    Border,
+    Header,
+    Highlight,
+    Playing,
+    Marked,
+    Rating,
+    Tab,
+    Search,
+    Progress,
+    ProgressRest,
+    ProgressLoop,
+    Lyric,
+    Hint,
+    Dim,
+}
+
+#[derive(Clone)]
+pub struct Theme {
+    /// Titles of panels and popups, and headings within them
+    pub title: Style,
+    pub border: Style,
+    pub border_type: BorderType,
+    /// Column headers of tables
+    pub header: Style,
+    /// Selected row of a table or list
+    pub highlight: Style,
+    /// The track being played
+    pub playing: Style,
+    /// Marks of tracks chosen for a bulk action
+    pub marked: Style,
+    /// Stars and favourite hearts
+    pub rating: Style,
+    /// Tab of the current view
+    pub tab: Style,
+    /// Search box while typing
+    pub search: Style,
+    /// Played part of the progress bar
+    pub progress: Style,
+    /// Part of the progress bar yet to play
+    pub progress_rest: Style,
+    /// Loop points and the looped part of the progress bar
+    pub progress_loop: Style,
+    /// Current line of synchronised lyrics
+    pub lyric: Style,
+    /// Key hints
+    pub hint: Style,
+    /// Secondary text, such as mixed values and unbound actions
+    pub dim: Style,
+    /// Elements set by the configuration, which a built-in theme leaves alone
+    custom: Vec<(Element, Style)>,
+    custom_border: Option<BorderType>,
+}
+
+impl Element {
+    const ALL: [Element; 15] = [
+        Element::Title,
+        Element::Border,
+        Element::Header,
+        Element::Highlight,
+        Element::Playing,
+        Element::Marked,
+        Element::Rating,
+        Element::Tab,
+        Element::Search,
+        Element::Progress,
+        Element::ProgressRest,
+        Element::ProgressLoop,
+        Element::Lyric,
+        Element::Hint,
+        Element::Dim,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Element::Title => "title",
+            Element::Border => "border",
+            Element::Header => "header",
+            Element::Highlight => "highlight",
+            Element::Playing => "playing",
+            Element::Marked => "marked",
+            Element::Rating => "rating",
+            Element::Tab => "tab",
+            Element::Search => "search",
+            Element::Progress => "progress",
+            Element::ProgressRest => "progress_rest",
+            Element::ProgressLoop => "progress_loop",
+            Element::Lyric => "lyric",
+            Element::Hint => "hint",
+            Element::Dim => "dim",
+        }
+    }
+}
+
+impl Default for Theme {
+    /// Terminal colours, distinguishing elements by modifiers alone
+    fn default() -> Self {
+        Self {
+            title: Style::new().bold(),
+            border: Style::new(),
+            border_type: BorderType::Rounded,
+            header: Style::new().bold(),
+            highlight: Style::new().reversed(),
+            playing: Style::new().bold(),
+            marked: Style::new(),
+            rating: Style::new(),
+            tab: Style::new().reversed(),
+            search: Style::new(),
+            progress: Style::new(),
+            progress_rest: Style::new(),
+            progress_loop: Style::new().bold(),
+            lyric: Style::new().bold().reversed(),
+            hint: Style::new().italic(),
+            dim: Style::new().dim(),
+            custom: Vec::new(),
+            custom_border: None,
+        }
+    }
+}
+
+impl Theme {
+    /// Dark blue greys with a warm accent, for dark terminals
+    fn dusk() -> Self {
+        let accent = Color::Rgb(0xeb, 0xcb, 0x8b);
+        let frost = Color::Rgb(0x88, 0xc0, 0xd0);
+        let grey = Color::Rgb(0x4c, 0x56, 0x6a);
+        Self {
+            title: Style::new().fg(frost).bold(),
+            border: Style::new().fg(grey),
+            header: Style::new().fg(frost).bold(),
+            highlight: Style::new()
+                .fg(Color::Rgb(0xec, 0xef, 0xf4))
+                .bg(Color::Rgb(0x43, 0x4c, 0x5e)),
+            playing: Style::new().fg(accent).bold(),
+            marked: Style::new().fg(accent),
+            rating: Style::new().fg(accent),
+            tab: Style::new().fg(Color::Rgb(0x2e, 0x34, 0x40)).bg(frost),
+            search: Style::new().fg(accent),
+            progress: Style::new().fg(frost),
+            progress_rest: Style::new().fg(grey),
+            progress_loop: Style::new().fg(accent).bold(),
+            lyric: Style::new().fg(accent).bold(),
+            hint: Style::new().fg(Color::Rgb(0x81, 0xa1, 0xc1)).italic(),
+            dim: Style::new().fg(grey),
+            ..Self::default()
+        }
+    }
+
+    /// Dark text with muted colours, for light terminals
+    fn paper() -> Self {
+        let ink = Color::Rgb(0x1f, 0x3a, 0x5f);
+        let accent = Color::Rgb(0xb3, 0x3e, 0x1f);
+        let grey = Color::Rgb(0x9a, 0x9a, 0x9a);
+        Self {
+            title: Style::new().fg(ink).bold(),
+            border: Style::new().fg(grey),
+            border_type: BorderType::Plain,
+            header: Style::new().fg(ink).bold(),
+            highlight: Style::new().bg(Color::Rgb(0xd8, 0xe2, 0xee)),
+            playing: Style::new().fg(accent).bold(),
+            marked: Style::new().fg(accent),
+            rating: Style::new().fg(accent),
+            tab: Style::new().fg(Color::Rgb(0xff, 0xff, 0xff)).bg(ink),
+            search: Style::new().fg(accent),
+            progress: Style::new().fg(ink),
+            progress_rest: Style::new().fg(grey),
+            progress_loop: Style::new().fg(accent).bold(),
+            lyric: Style::new().fg(accent).bold(),
+            hint: Style::new().fg(Color::Rgb(0x5a, 0x6b, 0x7d)).italic(),
+            dim: Style::new().fg(grey),
+            ..Self::default()
+        }
+    }
+
+    /// Replaces every style not set by the configuration with those of a built-in theme
+    pub fn apply_preset(&mut self, name: &str) -> Result<(), String> {
+        let theme = match name {
+            "default" => Self::default(),
+            "dusk" => Self::dusk(),
+            "paper" => Self::paper(),
+            _ => return Err(format!("unknown theme `{name}`")),
+        };
+        let custom = std::mem::take(&mut self.custom);
+        let custom_border = self.custom_border;
+        *self = theme;
+        for (element, style) in custom {
+            *self.style_mut(element) = style;
+            self.custom.push((element, style));
+        }
+        if let Some(b) = custom_border {
+            self.border_type = b;
+            self.custom_border = Some(b);
+        }
+        Ok(())
+    }
+
+    /// Sets the style of an element by name, or the kind of border
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if name == "borders" {
+            self.border_type = match value {
+                "plain" => BorderType::Plain,
+                "rounded" => BorderType::Rounded,
+                "double" => BorderType::Double,
+                "thick" => BorderType::Thick,
+                _ => return Err(format!("unknown border type `{value}`")),
+            };
+            self.custom_border = Some(self.border_type);
+            return Ok(());
+        }
+        let Some(element) = Element::ALL.into_iter().find(|e| e.name() == name) else {
+            return Err(format!("unknown theme element `{name}`"));
+        };
+        let style = parse_style(value)?;
+        *self.style_mut(element) = style;
+        self.custom.retain(|(e, _)| *e != element);
+        self.custom.push((element, style));
+        Ok(())
+    }
+
+    /// The theme with every colour brought down to what the terminal shows
+    pub fn for_depth(&self, depth: Depth) -> Self {
+        let mut theme = self.clone();
+        let mut plain = Self::default();
+        for element in Element::ALL {
+            let style = theme.style_mut(element);
+            if depth == Depth::None && (style.fg.is_some() || style.bg.is_some()) {
+                // an element told apart by colour alone would vanish, so fall back on modifiers
+                *style = plain.style_mut(element).add_modifier(style.add_modifier);
+                continue;
+            }
+            style.fg = style.fg.and_then(|c| depth.convert(c));
+            style.bg = style.bg.and_then(|c| depth.convert(c));
+        }
+        theme
+    }
+
+    /// Bordered block in the theme, with its title styled
+    pub fn block(&self) -> Block<'static> {
+        Block::bordered()
+            .border_type(self.border_type)
+            .border_style(self.border)
+            .title_style(self.title)
+    }
+
+    fn style_mut(&mut self, element: Element) -> &mut Style {
+        match element {
+            Element::Title => &mut self.title,
+            Element::Border => &mut self.border,
+            Element::Header => &mut self.header,
+            Element::Highlight => &mut self.highlight,
+            Element::Playing => &mut self.playing,
+            Element::Marked => &mut self.marked,
+            Element::Rating => &mut self.rating,
+            Element::Tab => &mut self.tab,
+            Element::Search => &mut self.search,
+            Element::Progress => &mut self.progress,
+            Element::ProgressRest => &mut self.progress_rest,
+            Element::ProgressLoop => &mut self.progress_loop,
+            Element::Lyric => &mut self.lyric,
+            Element::Hint => &mut self.hint,
+            Element::Dim => &mut self.dim,
+        }
+    }
+}
+
+/// Parses a style such as `bold yellow on #202020`; `none` is the terminal's own
+fn parse_style(s: &str) -> Result<Style, String> {
+    let mut style = Style::new();
+    let mut words = s.split_whitespace();
+    while let Some(word) = words.next() {
+        match word {
+            "none" => (),
+            "bold" => style = style.add_modifier(Modifier::BOLD),
+            "dim" => style = style.add_modifier(Modifier::DIM),
+            "italic" => style = style.add_modifier(Modifier::ITALIC),
+            "underlined" => style = style.add_modifier(Modifier::UNDERLINED),
+            "reversed" => style = style.add_modifier(Modifier::REVERSED),
+            "on" => match words.next() {
+                Some(c) => style = style.bg(colour::parse(c)?),
+                None => return Err(format!("no background colour after `on` in `{s}`")),
+            },
+            c => style = style.fg(colour::parse(c)?),
+        }
+    }
+    Ok(style)
+}
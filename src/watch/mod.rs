@@ -0,0 +1,228 @@
+//! Watches the library for changes on disk, reporting them in batches once they settle
+//!
+//! inotify is not recursive, so every directory beneath the root is watched on its own. Events
+//! are collected until none arrive for `DEBOUNCE`, so copying an album produces one batch, and
+//! the affected files are read on the watching thread rather than the UI's
+
+use std::{
+    collections::HashMap,
+    ffi::{CString, OsStr},
+    io, mem,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+    ptr,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use crate::loader::{self, AudioTrack};
+
+/// Quiet period after the last event before a batch is reported
+const DEBOUNCE: Duration = Duration::from_millis(500);
+const MASK: u32 = libc::IN_CLOSE_WRITE
+    | libc::IN_MOVED_TO
+    | libc::IN_MOVED_FROM
+    | libc::IN_DELETE
+    | libc::IN_CREATE
+    | libc::IN_ONLYDIR;
+
+pub enum Change {
+    /// A track was added, or its file rewritten
+    Updated(AudioTrack),
+    /// A file or directory went away, taking every track beneath it
+    Removed(PathBuf),
+    /// Events were lost, so the whole library must be scanned again
+    Rescan,
+}
+
+/// What last happened to a path within the current batch
+enum Pending {
+    Written,
+    Removed,
+}
+
+struct Watcher {
+    fd: libc::c_int,
+    /// Watched directory of each watch descriptor
+    dirs: HashMap<libc::c_int, PathBuf>,
+    pending: HashMap<PathBuf, Pending>,
+    overflowed: bool,
+    follow_symlinks: bool,
+}
+
+/// Starts watching `root` on a new thread. The channel disconnects where inotify is unavailable
+pub fn spawn(root: PathBuf, follow_symlinks: bool) -> Receiver<Vec<Change>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        if let Ok(mut watcher) = Watcher::new(follow_symlinks) {
+            watcher.add_tree(&root);
+            watcher.run(&tx);
+        }
+    });
+    rx
+}
+
+impl Watcher {
+    fn new(follow_symlinks: bool) -> io::Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            fd,
+            dirs: HashMap::new(),
+            pending: HashMap::new(),
+            overflowed: false,
+            follow_symlinks,
+        })
+    }
+
+    /// Reads events until the receiver is dropped, sending a batch whenever they settle
+    fn run(&mut self, tx: &Sender<Vec<Change>>) {
+        loop {
+            let timeout = match self.pending.is_empty() && !self.overflowed {
+                true => -1,
+                false => DEBOUNCE.as_millis() as libc::c_int,
+            };
+            let mut poll = libc::pollfd {
+                fd: self.fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            match unsafe { libc::poll(&mut poll, 1, timeout) } {
+                0 => {
+                    if tx.send(self.flush()).is_err() {
+                        return;
+                    }
+                }
+                n if n > 0 => self.read_events(),
+                _ if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => (),
+                _ => return,
+            }
+        }
+    }
+
+    fn read_events(&mut self) {
+        let mut buf = [0u8; 4096];
+        let n = unsafe { libc::read(self.fd, buf.as_mut_ptr().cast(), buf.len()) };
+        let Ok(n) = usize::try_from(n) else {
+            return;
+        };
+
+        let header = mem::size_of::<libc::inotify_event>();
+        let mut offset = 0;
+        while offset + header <= n {
+            let event: libc::inotify_event =
+                unsafe { ptr::read_unaligned(buf[offset..].as_ptr().cast()) };
+            let name = &buf[offset + header..offset + header + event.len as usize];
+            // names are padded with nuls to keep events aligned
+            let name = name.split(|b| *b == 0).next().unwrap_or_default();
+            offset += header + event.len as usize;
+            self.handle(event.wd, event.mask, OsStr::from_bytes(name));
+        }
+    }
+
+    fn handle(&mut self, wd: libc::c_int, mask: u32, name: &OsStr) {
+        if mask & libc::IN_Q_OVERFLOW != 0 {
+            self.overflowed = true;
+            return;
+        }
+        if mask & libc::IN_IGNORED != 0 {
+            self.dirs.remove(&wd);
+            return;
+        }
+        let Some(dir) = self.dirs.get(&wd) else {
+            return;
+        };
+        let path = dir.join(name);
+
+        let is_dir = mask & libc::IN_ISDIR != 0;
+        if !is_dir && loader::is_sheet(&path) {
+            // a sheet changes how the files beside it are split, so they are read again
+            if let Some(dir) = path.parent() {
+                self.pending.insert(dir.to_path_buf(), Pending::Written);
+            }
+        } else if mask & (libc::IN_MOVED_FROM | libc::IN_DELETE) != 0 {
+            if is_dir {
+                self.unwatch(&path);
+            }
+            self.pending.insert(path, Pending::Removed);
+        } else if is_dir {
+            // files may land in a new directory before it is watched, so it is read whole
+            self.add_tree(&path);
+            self.pending.insert(path, Pending::Written);
+        } else if mask & (libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO) != 0
+            && loader::is_supported(&path)
+        {
+            self.pending.insert(path, Pending::Written);
+        }
+    }
+
+    /// Watches `root` and every directory beneath it
+    fn add_tree(&mut self, root: &Path) {
+        let mut dirs = vec![root.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            let Ok(c) = CString::new(dir.clone().into_os_string().into_vec()) else {
+                continue;
+            };
+            let wd = unsafe { libc::inotify_add_watch(self.fd, c.as_ptr(), MASK) };
+            // a directory already watched is reached again through a link; go no further
+            if wd < 0 || self.dirs.contains_key(&wd) {
+                continue;
+            }
+            if let Ok(entries) = dir.read_dir() {
+                dirs.extend(entries.flatten().map(|e| e.path()).filter(|p| {
+                    match self.follow_symlinks {
+                        true => p.is_dir(),
+                        false => p.symlink_metadata().is_ok_and(|m| m.is_dir()),
+                    }
+                }));
+            }
+            self.dirs.insert(wd, dir);
+        }
+    }
+
+    /// Stops watching `root` and every directory beneath it, as when it is moved away
+    fn unwatch(&mut self, root: &Path) {
+        let (gone, kept) = mem::take(&mut self.dirs)
+            .into_iter()
+            .partition(|(_, dir)| dir.starts_with(root));
+        self.dirs = kept;
+        for wd in gone.into_keys() {
+            unsafe { libc::inotify_rm_watch(self.fd, wd) };
+        }
+    }
+
+    /// Reads the files written since the last batch, reporting removals first
+    fn flush(&mut self) -> Vec<Change> {
+        if mem::take(&mut self.overflowed) {
+            self.pending.clear();
+            return vec![Change::Rescan];
+        }
+
+        let mut changes = Vec::with_capacity(self.pending.len());
+        let mut written = Vec::new();
+        for (path, pending) in self.pending.drain() {
+            match pending {
+                Pending::Removed => changes.push(Change::Removed(path)),
+                Pending::Written => written.extend(loader::audio_files(path, self.follow_symlinks)),
+            }
+        }
+        // unreadable files, such as those still being written, are picked up when next closed
+        for path in written {
+            if let Ok(tracks) = loader::read_audio_file(&path) {
+                // a CUE sheet may now split the file differently, so its old tracks go first
+                changes.push(Change::Removed(path));
+                changes.extend(tracks.into_iter().map(Change::Updated));
+            }
+        }
+        changes
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}